@@ -1,9 +1,17 @@
 use crate::benchmark::st_leiden_baseline::STLeidenBaseline;
 use crate::core::config::{RunConfig, RunMode};
+use crate::core::graph::delta::DeltaBatch;
+use crate::core::session::IncrementalSession;
 use crate::core::types::{BatchResult, GraphInput, IncrementalOutcome};
 use std::time::Instant;
 
 /// Run HIT-Leiden incrementally across batches and compare against ST-Leiden baseline
+///
+/// `batches` are cumulative graph snapshots as produced by `DynamicGraphBuilder`:
+/// each one extends the previous edge list, and the first `initial_edge_count`
+/// edges form the initial graph. HIT-Leiden clusters the initial graph once and
+/// then applies only the newly appended edges of each batch to a single
+/// `IncrementalSession`, while ST-Leiden re-clusters every snapshot from scratch.
 pub fn run_incremental(
     batches: Vec<GraphInput>,
     batch_size: usize,
@@ -14,32 +22,48 @@ pub fn run_incremental(
     let overall_start = Instant::now();
     let mut prev_total_edges = initial_edge_count;
 
+    let config = RunConfig {
+        mode: RunMode::Throughput,
+        ..Default::default()
+    };
+    let mut session = batches
+        .first()
+        .map(|first| {
+            let initial_graph = GraphInput {
+                dataset_id: format!("{}:initial", first.dataset_id),
+                node_count: first.node_count,
+                edges: first.edges[..initial_edge_count.min(first.edges.len())].to_vec(),
+            };
+            IncrementalSession::new(&initial_graph, config.clone())
+        })
+        .transpose()?;
+
     for (idx, batch_graph) in batches.iter().enumerate() {
-        // Run HIT-Leiden (incremental)
-        let config = RunConfig {
-            mode: RunMode::Throughput,
-            ..Default::default()
+        let Some(session) = session.as_mut() else {
+            break;
         };
+        let delta = DeltaBatch::adjustments(&GraphInput {
+            dataset_id: batch_graph.dataset_id.clone(),
+            node_count: batch_graph.node_count,
+            edges: batch_graph.edges[prev_total_edges.min(batch_graph.edges.len())..].to_vec(),
+        });
 
+        // Run HIT-Leiden (incremental)
         let start = Instant::now();
-        let outcome = crate::run(&batch_graph, &config)?;
+        session.apply_delta(&delta)?;
         let hit_leiden_ms = start.elapsed().as_secs_f64() * 1000.0;
 
-        let hit_leiden_iterations = outcome
-            .partition
-            .as_ref()
-            .map(|p| p.iteration_count)
-            .unwrap_or(0);
-
-        let modularity = outcome
-            .partition
-            .as_ref()
-            .map(|p| p.quality_score)
-            .unwrap_or(0.0);
+        let partition = session.partition_result();
+        let hit_leiden_iterations = partition.iteration_count;
+        let modularity = partition.quality_score;
 
         // Run ST-Leiden baseline (fresh)
-        let (st_leiden_ms, _st_modularity) =
-            STLeidenBaseline::run(&batch_graph.edges, batch_graph.node_count)?;
+        let (st_leiden_ms, _st_quality) = STLeidenBaseline::run(
+            &batch_graph.edges,
+            batch_graph.node_count,
+            config.quality,
+            config.resolution,
+        )?;
 
         let speedup = if hit_leiden_ms > 0.0 {
             st_leiden_ms / hit_leiden_ms
@@ -1,3 +1,7 @@
+use crate::core::algorithm::quality::{CommunityStats, QualityKind};
+use crate::core::graph::in_memory::InMemoryGraph;
+use crate::core::types::GraphInput;
+use bitvec::prelude::*;
 use graphrs::algorithms::community::leiden::{leiden, QualityFunction};
 use graphrs::{Edge, Graph, GraphSpecs};
 use std::time::Instant;
@@ -6,11 +10,15 @@ use std::time::Instant;
 pub struct STLeidenBaseline;
 
 impl STLeidenBaseline {
-    /// Run fresh Leiden on graph (no warm-start)
-    /// Returns: (time_ms, approximate_modularity)
+    /// Run fresh Leiden on graph (no warm-start), optimising `quality` at
+    /// `resolution` so the result is comparable with a HIT-Leiden run using
+    /// the same objective.
+    /// Returns: (time_ms, quality score of the baseline partition)
     pub fn run(
         edges: &[(usize, usize, Option<f64>)],
-        _num_nodes: usize,
+        num_nodes: usize,
+        quality: QualityKind,
+        resolution: f64,
     ) -> Result<(f64, f64), String> {
         // Build graphrs graph using auto-creating nodes
         let mut graph: Graph<usize, f64> = Graph::new(GraphSpecs::undirected_create_missing());
@@ -24,22 +32,47 @@ impl STLeidenBaseline {
                 .map_err(|e| format!("Failed to add edge: {}", e))?;
         }
 
-        // Run Leiden (6 arguments: graph, use_weights, quality_function, theta, gamma, seed)
+        let quality_function = match quality {
+            QualityKind::Modularity => QualityFunction::Modularity,
+            QualityKind::Cpm => QualityFunction::CPM,
+        };
+
+        // Run Leiden (graph, use_weights, quality_function, resolution, theta, gamma)
         let start = Instant::now();
-        let _communities = leiden(
+        let communities = leiden(
             &graph,
             true, // use weights
-            QualityFunction::CPM,
+            quality_function,
+            Some(resolution),
             None, // theta (default)
             None, // gamma (default)
-            None, // seed (default)
         )
         .map_err(|e| format!("Leiden failed: {}", e))?;
         let elapsed_ms = start.elapsed().as_secs_f64() * 1000.0;
 
-        // Approximate modularity (simplified)
-        let modularity = 0.5; // Placeholder
+        // Score the baseline with the same evaluator HIT-Leiden reports.
+        // Vertices without edges are absent from graphrs and stay singletons.
+        let mut node_to_comm: Vec<usize> = (0..num_nodes).collect();
+        for community in &communities {
+            if let Some(&label) = community.iter().min() {
+                for &node in community {
+                    node_to_comm[node] = label;
+                }
+            }
+        }
+        let graph = InMemoryGraph::from(&GraphInput {
+            dataset_id: "st-leiden".to_string(),
+            node_count: num_nodes,
+            edges: edges.to_vec(),
+        });
+        let score = CommunityStats::from_partition(
+            &graph,
+            &node_to_comm,
+            &vec![1.0; num_nodes],
+            &bitvec![0; num_nodes],
+        )
+        .quality(quality.function(), graph.total_weight(), resolution);
 
-        Ok((elapsed_ms, modularity))
+        Ok((elapsed_ms, score))
     }
 }
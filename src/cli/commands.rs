@@ -0,0 +1,530 @@
+use crate::cli::deltas::parse_delta_script;
+use crate::cli::error::{CliError, ExitStatus};
+use crate::cli::options::{
+    CliFormat, CliSeparator, CliSource, Command, CompareArgs, ConvertArgs, GraphArgs, RunArgs,
+    StatsArgs, UpdateArgs, ValidateArgs,
+};
+use crate::cli::run::{config_from_cli, parallel_edges_from_cli};
+use crate::core::config::{ParallelEdges, RunConfig, RunMode};
+use crate::core::error::HitLeidenError;
+use crate::core::graph::csr_binary::{is_csr, load_csr, write_csr, MAGIC};
+use crate::core::graph::edge_list::{
+    load_edge_list, parse_node_id, write_edge_list, EdgeListOptions, IdBase, Separator,
+};
+use crate::core::graph::in_memory::InMemoryGraph;
+use crate::core::session::IncrementalSession;
+use crate::core::types::{GraphDataset, GraphInput, RunOutcome};
+use crate::core::validation::invariants;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::io::{Read as _, Write as _};
+use std::path::Path;
+
+/// Run `command`, writing its outputs to the files it names or to the
+/// standard streams.
+pub fn execute(command: &Command) -> Result<ExitStatus, CliError> {
+    match command {
+        Command::Run(args) => run(args),
+        Command::Update(args) => update(args),
+        Command::Validate(args) => validate(args),
+        Command::Compare(args) => compare(args),
+        Command::Stats(args) => stats(args),
+        Command::Convert(args) => convert(args),
+    }
+}
+
+fn run(args: &RunArgs) -> Result<ExitStatus, CliError> {
+    let config = config_from_cli(&args.options);
+    let (graph, dataset) = load_graph(&args.graph, config.directed)?;
+    let outcome = crate::run(&graph, &config)?;
+    let partition = outcome.partition.as_ref().expect("a run has a partition");
+
+    let labels: Vec<Option<usize>> = partition
+        .node_to_community
+        .iter()
+        .copied()
+        .map(Some)
+        .collect();
+    let communities = dense_ids(&labels);
+    let mut report = describe_dataset(&dataset);
+    describe_run(&mut report, &outcome, &config);
+    write_output(args.output.as_deref(), Stream::Stdout, &|out| {
+        write_partition(out, &communities, id_base(&args.graph))
+    })?;
+    write_text(args.report.as_deref(), Stream::Stderr, &report)?;
+    Ok(ExitStatus::Success)
+}
+
+fn update(args: &UpdateArgs) -> Result<ExitStatus, CliError> {
+    let config = config_from_cli(&args.options);
+    let (graph, dataset) = load_graph(&args.graph, config.directed)?;
+    let script = read_text(&args.deltas)?;
+    let deltas = args.deltas.display().to_string();
+    let batches =
+        parse_delta_script(&script, id_base(&args.graph)).map_err(|e| with_context(e, &deltas))?;
+    let mut session = IncrementalSession::new(&graph, config)?;
+
+    let mut report = describe_dataset(&dataset);
+    describe_version(&mut report, &session);
+    for (i, batch) in batches.iter().enumerate() {
+        session
+            .apply_delta(batch)
+            .map_err(|e| with_context(e, &format!("{deltas}: batch {}", i + 1)))?;
+        describe_version(&mut report, &session);
+    }
+    let labels: Vec<Option<usize>> = session
+        .partition()
+        .iter()
+        .enumerate()
+        .map(|(node, &label)| (!session.is_removed(node)).then_some(label))
+        .collect();
+    let communities = dense_ids(&labels);
+    write_output(args.output.as_deref(), Stream::Stdout, &|out| {
+        write_partition(out, &communities, id_base(&args.graph))
+    })?;
+    write_text(args.report.as_deref(), Stream::Stderr, &report)?;
+    Ok(ExitStatus::Success)
+}
+
+fn validate(args: &ValidateArgs) -> Result<ExitStatus, CliError> {
+    let candidate_config = config_from_cli(&args.options);
+    let (graph, dataset) = load_graph(&args.graph, candidate_config.directed)?;
+    let reference_config = RunConfig {
+        mode: RunMode::Deterministic,
+        ..candidate_config.clone()
+    };
+    let reference = crate::run(&graph, &reference_config)?;
+    let candidate = crate::run(&graph, &candidate_config)?;
+    let outcome = crate::validate(&reference, &candidate, candidate_config.mode);
+    let invariants_passed = invariants::check(&reference) && invariants::check(&candidate);
+
+    let mut report = describe_dataset(&dataset);
+    line(&mut report, "Mode", format!("{:?}", candidate_config.mode));
+    line(&mut report, "Reference mode", "Deterministic");
+    line(
+        &mut report,
+        "Hard invariants passed",
+        outcome.hard_invariants_passed && invariants_passed,
+    );
+    line(
+        &mut report,
+        "Deterministic identity passed",
+        outcome
+            .deterministic_identity_passed
+            .map_or_else(|| "n/a".to_string(), |passed| passed.to_string()),
+    );
+    if let Some(delta) = outcome.quality_delta_vs_reference {
+        line(&mut report, "Quality delta vs reference", delta);
+    }
+    line(
+        &mut report,
+        "Equivalence passed",
+        outcome.equivalence_passed,
+    );
+    write_text(args.output.as_deref(), Stream::Stdout, &report)?;
+
+    let passed = outcome.hard_invariants_passed && invariants_passed && outcome.equivalence_passed;
+    Ok(if passed {
+        ExitStatus::Success
+    } else {
+        ExitStatus::CheckFailed
+    })
+}
+
+fn compare(args: &CompareArgs) -> Result<ExitStatus, CliError> {
+    let base = if args.one_based {
+        IdBase::One
+    } else {
+        IdBase::Zero
+    };
+    let left = read_partition(&args.left, base)?;
+    let right = read_partition(&args.right, base)?;
+    if !left.keys().eq(right.keys()) {
+        return Err(HitLeidenError::InvalidInput(format!(
+            "{} and {} assign different sets of nodes",
+            args.left.display(),
+            args.right.display()
+        ))
+        .into());
+    }
+
+    let left: Vec<usize> = left.into_values().collect();
+    let right: Vec<usize> = right.into_values().collect();
+    let mut pairs: HashMap<(usize, usize), usize> = HashMap::new();
+    for (&a, &b) in left.iter().zip(&right) {
+        *pairs.entry((a, b)).or_default() += 1;
+    }
+    let left_sizes = community_sizes(&left);
+    let right_sizes = community_sizes(&right);
+    // Equal up to relabelling exactly when every community pairs with one other
+    let identical = pairs.len() == left_sizes.len() && pairs.len() == right_sizes.len();
+
+    let mut report = String::new();
+    line(&mut report, "Nodes", left.len());
+    line(&mut report, "Left communities", left_sizes.len());
+    line(&mut report, "Right communities", right_sizes.len());
+    line(
+        &mut report,
+        "NMI",
+        normalized_mutual_information(&pairs, &left_sizes, &right_sizes, left.len()),
+    );
+    line(&mut report, "Identical", identical);
+    write_text(args.output.as_deref(), Stream::Stdout, &report)?;
+    Ok(if identical {
+        ExitStatus::Success
+    } else {
+        ExitStatus::CheckFailed
+    })
+}
+
+fn stats(args: &StatsArgs) -> Result<ExitStatus, CliError> {
+    let (input, dataset) = load_graph(&args.graph, args.directed)?;
+    let merged = input.merge_parallel_edges(ParallelEdges::Sum, args.directed);
+    let graph = if args.directed {
+        InMemoryGraph::directed(&merged)
+    } else {
+        InMemoryGraph::from(&*merged)
+    };
+    let degrees: Vec<usize> = (0..graph.node_count).map(|v| graph.degree(v)).collect();
+
+    let mut report = describe_dataset(&dataset);
+    line(&mut report, "Directed", args.directed);
+    line(&mut report, "Weighted", dataset.is_weighted);
+    line(
+        &mut report,
+        "Self-loops",
+        merged.edges.iter().filter(|(u, v, _)| u == v).count(),
+    );
+    line(
+        &mut report,
+        "Parallel edges",
+        input.edges.len() - merged.edges.len(),
+    );
+    line(
+        &mut report,
+        "Isolated nodes",
+        degrees.iter().filter(|&&d| d == 0).count(),
+    );
+    line(&mut report, "Total weight", graph.total_weight());
+    if !degrees.is_empty() {
+        line(&mut report, "Min degree", degrees.iter().min().unwrap());
+        line(&mut report, "Max degree", degrees.iter().max().unwrap());
+        line(
+            &mut report,
+            "Mean degree",
+            degrees.iter().sum::<usize>() as f64 / degrees.len() as f64,
+        );
+    }
+    write_text(args.output.as_deref(), Stream::Stdout, &report)?;
+    Ok(ExitStatus::Success)
+}
+
+fn convert(args: &ConvertArgs) -> Result<ExitStatus, CliError> {
+    let (mut graph, _) = load_graph(&args.graph, args.directed)?;
+    if let Some(merge) = &args.merge_parallel_edges {
+        graph = graph
+            .merge_parallel_edges(parallel_edges_from_cli(merge), args.directed)
+            .into_owned();
+    }
+    if let CliFormat::CsrBinary = args.to {
+        // CSR files list every neighbour once, so edges left apart are summed
+        let graph = graph.merge_parallel_edges(ParallelEdges::Sum, args.directed);
+        let graph = if args.directed {
+            InMemoryGraph::directed(&graph)
+        } else {
+            InMemoryGraph::from(&*graph)
+        };
+        write_output(args.output.as_deref(), Stream::Stdout, &|out| {
+            write_csr(&graph, out)
+        })?;
+        return Ok(ExitStatus::Success);
+    }
+    let options = EdgeListOptions {
+        separator: separator(&args.to_separator),
+        id_base: if args.to_one_based {
+            IdBase::One
+        } else {
+            IdBase::Zero
+        },
+        ..EdgeListOptions::default()
+    };
+    write_output(args.output.as_deref(), Stream::Stdout, &|out| {
+        write_edge_list(&graph, &options, out)
+    })?;
+    Ok(ExitStatus::Success)
+}
+
+/// Read the graph `args` name. A CSR file records whether it is directed,
+/// which must agree with `directed`.
+fn load_graph(args: &GraphArgs, directed: bool) -> Result<(GraphInput, GraphDataset), CliError> {
+    let CliSource::File = args.source;
+    let csr = match args.format {
+        CliFormat::Auto => starts_like_csr(&args.path)?,
+        CliFormat::EdgeList => false,
+        CliFormat::CsrBinary => true,
+    };
+    if !csr {
+        let options = EdgeListOptions {
+            separator: separator(&args.separator),
+            id_base: id_base(args),
+            header: args.header,
+            node_count: args.node_count,
+            ..EdgeListOptions::default()
+        };
+        return Ok(load_edge_list(&args.path, &options)?);
+    }
+
+    let (graph, dataset) = load_csr(&args.path)?;
+    let invalid = |e: String| HitLeidenError::InvalidInput(format!("{}: {e}", args.path.display()));
+    if graph.is_directed() != directed {
+        return Err(invalid(if directed {
+            "holds an undirected graph; omit --directed".to_string()
+        } else {
+            "holds a directed graph; pass --directed".to_string()
+        })
+        .into());
+    }
+    if args.node_count.is_some_and(|n| n != graph.node_count) {
+        return Err(invalid(format!("holds {} nodes", graph.node_count)).into());
+    }
+    Ok((graph.to_graph_input(&dataset.dataset_id), dataset))
+}
+
+fn starts_like_csr(path: &Path) -> Result<bool, CliError> {
+    let mut start = Vec::with_capacity(MAGIC.len());
+    std::fs::File::open(path)
+        .and_then(|file| file.take(MAGIC.len() as u64).read_to_end(&mut start))
+        .map_err(|e| HitLeidenError::InvalidInput(format!("{}: {e}", path.display())))?;
+    Ok(is_csr(&start))
+}
+
+fn separator(separator: &CliSeparator) -> Separator {
+    match separator {
+        CliSeparator::Auto => Separator::Auto,
+        CliSeparator::Whitespace => Separator::Whitespace,
+        CliSeparator::Comma => Separator::Comma,
+        CliSeparator::Tab => Separator::Tab,
+    }
+}
+
+fn id_base(args: &GraphArgs) -> IdBase {
+    if args.one_based {
+        IdBase::One
+    } else {
+        IdBase::Zero
+    }
+}
+
+/// Number communities `0, 1, …` in the order of their labels, leaving out
+/// nodes without one. `run` and `update` both write partitions numbered so.
+fn dense_ids(labels: &[Option<usize>]) -> Vec<Option<u64>> {
+    let mut ids: BTreeMap<usize, u64> = labels.iter().flatten().map(|&label| (label, 0)).collect();
+    for (next, id) in ids.values_mut().enumerate() {
+        *id = next as u64;
+    }
+    labels
+        .iter()
+        .map(|label| label.map(|label| ids[&label]))
+        .collect()
+}
+
+/// Write one `node community` line per node that has a community.
+fn write_partition(
+    out: &mut dyn std::io::Write,
+    communities: &[Option<u64>],
+    base: IdBase,
+) -> std::io::Result<()> {
+    let base = match base {
+        IdBase::Zero => 0,
+        IdBase::One => 1,
+    };
+    writeln!(out, "# node community")?;
+    for (node, community) in communities.iter().enumerate() {
+        if let Some(community) = community {
+            writeln!(out, "{} {community}", node + base)?;
+        }
+    }
+    Ok(())
+}
+
+/// Node -> community of a partition file written by `run` or `update`.
+fn read_partition(path: &Path, base: IdBase) -> Result<BTreeMap<usize, usize>, CliError> {
+    let text = read_text(path)?;
+    let invalid = |line: usize, e: String| {
+        HitLeidenError::InvalidInput(format!("{}: line {line}: {e}", path.display()))
+    };
+    let mut partition = BTreeMap::new();
+    for (index, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect();
+        match fields[..] {
+            [] => {}
+            [node, community] => {
+                let node = parse_node_id(node, base).map_err(|e| invalid(index + 1, e))?;
+                let community = community
+                    .parse()
+                    .map_err(|_| invalid(index + 1, format!("`{community}` is not a community")))?;
+                if partition.insert(node, community).is_some() {
+                    return Err(invalid(index + 1, format!("node {node} is assigned twice")).into());
+                }
+            }
+            _ => {
+                return Err(invalid(
+                    index + 1,
+                    format!("expected `node community`, found {} fields", fields.len()),
+                )
+                .into())
+            }
+        }
+    }
+    Ok(partition)
+}
+
+fn community_sizes(labels: &[usize]) -> HashMap<usize, usize> {
+    let mut sizes = HashMap::new();
+    for &label in labels {
+        *sizes.entry(label).or_default() += 1;
+    }
+    sizes
+}
+
+/// `2·I(L; R) / (H(L) + H(R))`, or 1 when both partitions have one community.
+fn normalized_mutual_information(
+    pairs: &HashMap<(usize, usize), usize>,
+    left: &HashMap<usize, usize>,
+    right: &HashMap<usize, usize>,
+    n: usize,
+) -> f64 {
+    let n = n as f64;
+    let entropy = |sizes: &HashMap<usize, usize>| -> f64 {
+        sizes
+            .values()
+            .map(|&s| -(s as f64 / n) * (s as f64 / n).ln())
+            .sum()
+    };
+    let (h_left, h_right) = (entropy(left), entropy(right));
+    if h_left + h_right == 0.0 {
+        return 1.0;
+    }
+    let mutual: f64 = pairs
+        .iter()
+        .map(|(&(a, b), &count)| {
+            let p = count as f64 / n;
+            p * (p * n * n / (left[&a] as f64 * right[&b] as f64)).ln()
+        })
+        .sum();
+    2.0 * mutual / (h_left + h_right)
+}
+
+fn describe_dataset(dataset: &GraphDataset) -> String {
+    let mut report = String::new();
+    line(&mut report, "Dataset", &dataset.dataset_id);
+    line(&mut report, "Source", &dataset.source_uri);
+    line(&mut report, "Checksum", &dataset.checksum);
+    line(&mut report, "Nodes", dataset.node_count);
+    line(&mut report, "Edges", dataset.edge_count);
+    report
+}
+
+fn describe_run(report: &mut String, outcome: &RunOutcome, config: &RunConfig) {
+    let execution = &outcome.execution;
+    line(report, "Run ID", &execution.run_id);
+    line(report, "Mode", format!("{:?}", config.mode));
+    line(
+        report,
+        "Backend",
+        format!("{:?}", execution.graph_backend_resolved),
+    );
+    if let Some(reason) = &execution.fallback_reason {
+        line(report, "Fallback", reason);
+    }
+    if let Some(partition) = &outcome.partition {
+        line(report, "Communities", partition.community_count);
+        line(report, "Quality", partition.quality_score);
+        line(report, "Passes", partition.pass_count);
+        line(report, "Iterations", partition.iteration_count);
+        line(
+            report,
+            "Stop reason",
+            format!("{:?}", partition.stop_reason),
+        );
+    }
+}
+
+fn describe_version(report: &mut String, session: &IncrementalSession) {
+    let result = session.partition_result();
+    line(
+        report,
+        &format!("Version {}", session.version()),
+        format!(
+            "{} communities, quality {}",
+            result.community_count, result.quality_score
+        ),
+    );
+}
+
+fn line(report: &mut String, key: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(report, "{key}: {value}");
+}
+
+/// `error` with `context` prepended to its message.
+fn with_context(error: HitLeidenError, context: &str) -> HitLeidenError {
+    match error {
+        HitLeidenError::InvalidInput(m) => HitLeidenError::InvalidInput(format!("{context}: {m}")),
+        HitLeidenError::Backend(m) => HitLeidenError::Backend(format!("{context}: {m}")),
+        HitLeidenError::Acceleration(m) => HitLeidenError::Acceleration(format!("{context}: {m}")),
+        HitLeidenError::Checkpoint(m) => HitLeidenError::Checkpoint(format!("{context}: {m}")),
+    }
+}
+
+fn read_text(path: &Path) -> Result<String, CliError> {
+    std::fs::read_to_string(path)
+        .map_err(|e| HitLeidenError::InvalidInput(format!("{}: {e}", path.display())).into())
+}
+
+/// Standard stream an output falls back to when no file is named.
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+fn write_text(path: Option<&Path>, fallback: Stream, text: &str) -> Result<(), CliError> {
+    write_output(path, fallback, &|out| out.write_all(text.as_bytes()))
+}
+
+fn write_output(
+    path: Option<&Path>,
+    fallback: Stream,
+    write: &dyn Fn(&mut dyn std::io::Write) -> std::io::Result<()>,
+) -> Result<(), CliError> {
+    let (result, name) = match (path, fallback) {
+        (Some(path), _) => {
+            let result = std::fs::File::create(path).and_then(|file| {
+                let mut out = std::io::BufWriter::new(file);
+                write(&mut out)?;
+                out.flush()
+            });
+            (result, path.display().to_string())
+        }
+        (None, Stream::Stdout) => {
+            let mut out = std::io::stdout().lock();
+            (
+                write(&mut out).and_then(|_| out.flush()),
+                "stdout".to_string(),
+            )
+        }
+        (None, Stream::Stderr) => {
+            let mut out = std::io::stderr().lock();
+            (
+                write(&mut out).and_then(|_| out.flush()),
+                "stderr".to_string(),
+            )
+        }
+    };
+    result.map_err(|source| CliError::Io { path: name, source })
+}
@@ -0,0 +1,82 @@
+use crate::core::error::HitLeidenError;
+use crate::core::graph::delta::DeltaBatch;
+use crate::core::graph::edge_list::{parse_node_id, IdBase};
+
+/// Parse a delta script into the batches it commits.
+///
+/// Each line holds one change, `#` starts a comment and `commit` ends the
+/// current batch; changes after the last `commit` form a final batch.
+///
+/// ```text
+/// insert u v [weight]    add an edge, of weight 1 by default
+/// delete u v             remove an edge
+/// set u v weight         replace an edge's weight
+/// adjust u v delta       add to an edge's weight
+/// add-nodes count        append isolated nodes
+/// remove-node u          detach and retire a node
+/// node-weight u weight   set a node's weight
+/// ```
+pub fn parse_delta_script(text: &str, id_base: IdBase) -> Result<Vec<DeltaBatch>, HitLeidenError> {
+    let mut batches = Vec::new();
+    let mut batch = DeltaBatch::new();
+    let mut pending = false;
+    for (index, line) in text.lines().enumerate() {
+        let at_line = |e: String| HitLeidenError::InvalidInput(format!("line {}: {e}", index + 1));
+        let line = line.split('#').next().unwrap_or_default();
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let Some((&op, args)) = fields.split_first() else {
+            continue;
+        };
+        if op == "commit" {
+            batches.push(std::mem::take(&mut batch));
+            pending = false;
+            continue;
+        }
+
+        let arity = match op {
+            "insert" => 2..=3,
+            "delete" => 2..=2,
+            "set" | "adjust" => 3..=3,
+            "add-nodes" | "remove-node" => 1..=1,
+            "node-weight" => 2..=2,
+            _ => return Err(at_line(format!("unknown change `{op}`"))),
+        };
+        if !arity.contains(&args.len()) {
+            return Err(at_line(format!(
+                "`{op}` takes {} arguments, found {}",
+                arity.end(),
+                args.len()
+            )));
+        }
+        let node = |i: usize| parse_node_id(args[i], id_base).map_err(at_line);
+        let number = |i: usize| parse_number(args[i]).map_err(at_line);
+        batch = match op {
+            "insert" => {
+                let weight = if args.len() == 3 { number(2)? } else { 1.0 };
+                batch.insert(node(0)?, node(1)?, weight)
+            }
+            "delete" => batch.delete(node(0)?, node(1)?),
+            "set" => batch.set_weight(node(0)?, node(1)?, number(2)?),
+            "adjust" => batch.adjust_weight(node(0)?, node(1)?, number(2)?),
+            "add-nodes" => batch.add_nodes(
+                args[0]
+                    .parse()
+                    .map_err(|_| at_line(format!("`{}` is not a node count", args[0])))?,
+            ),
+            "remove-node" => batch.remove_node(node(0)?),
+            _ => batch.set_node_weight(node(0)?, number(1)?),
+        };
+        pending = true;
+    }
+    if pending {
+        batches.push(batch);
+    }
+    Ok(batches)
+}
+
+fn parse_number(field: &str) -> Result<f64, String> {
+    match field.parse::<f64>() {
+        Ok(x) if x.is_finite() => Ok(x),
+        _ => Err(format!("`{field}` is not a finite number")),
+    }
+}
@@ -0,0 +1,46 @@
+use crate::core::error::HitLeidenError;
+use thiserror::Error;
+
+/// Exit status of the `hit_leiden` binary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success = 0,
+    /// The command ran but its check did not pass: a validation failed or
+    /// compared partitions differ.
+    CheckFailed = 1,
+    /// The command line could not be parsed; reported by clap.
+    Usage = 2,
+    /// An input file or a setting was rejected.
+    InvalidInput = 3,
+    /// An output could not be written.
+    Io = 4,
+    /// The algorithm or a backend failed.
+    Failure = 5,
+}
+
+impl ExitStatus {
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error(transparent)]
+    Run(#[from] HitLeidenError),
+    #[error("{path}: {source}")]
+    Io {
+        path: String,
+        source: std::io::Error,
+    },
+}
+
+impl CliError {
+    pub fn exit_status(&self) -> ExitStatus {
+        match self {
+            CliError::Run(HitLeidenError::InvalidInput(_)) => ExitStatus::InvalidInput,
+            CliError::Run(_) => ExitStatus::Failure,
+            CliError::Io { .. } => ExitStatus::Io,
+        }
+    }
+}
@@ -1,3 +1,6 @@
 pub mod benchmark;
+pub mod commands;
+pub mod deltas;
+pub mod error;
 pub mod options;
 pub mod run;
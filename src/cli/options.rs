@@ -1,17 +1,216 @@
-use clap::{Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Clone, Debug, ValueEnum)]
 pub enum CliMode {
     Deterministic,
     Throughput,
+    DeterministicParallel,
 }
 
+#[derive(Clone, Debug, ValueEnum)]
+pub enum CliQuality {
+    Modularity,
+    Cpm,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum CliParallelEdges {
+    Sum,
+    Max,
+    Count,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum CliBackend {
+    InMemory,
+    Mmap,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum CliSource {
+    File,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum CliFormat {
+    /// CSR binary if the file starts like one, an edge list otherwise.
+    Auto,
+    EdgeList,
+    CsrBinary,
+}
+
+#[derive(Clone, Debug, ValueEnum)]
+pub enum CliSeparator {
+    Auto,
+    Whitespace,
+    Comma,
+    Tab,
+}
+
+/// Community detection on edge-list graphs with incremental updates.
 #[derive(Parser, Debug)]
-pub struct CliOptions {
+#[command(name = "hit_leiden", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Cluster a graph and write its partition.
+    Run(RunArgs),
+    /// Cluster a graph, apply a script of updates and write the final partition.
+    Update(UpdateArgs),
+    /// Check a mode against a deterministic reference run on the same graph.
+    Validate(ValidateArgs),
+    /// Compare two partition files; exits with 1 when they differ.
+    Compare(CompareArgs),
+    /// Summarise a graph.
+    Stats(StatsArgs),
+    /// Rewrite a graph in another layout, e.g. an edge list as CSR binary.
+    Convert(ConvertArgs),
+}
+
+/// Where a graph is read from and how its file is laid out.
+#[derive(Args, Debug)]
+pub struct GraphArgs {
+    #[arg(long, value_enum, default_value = "file")]
+    pub source: CliSource,
+    #[arg(long)]
+    pub path: PathBuf,
+    #[arg(long, value_enum, default_value = "auto")]
+    pub format: CliFormat,
+    #[arg(long, value_enum, default_value = "auto")]
+    pub separator: CliSeparator,
+    /// Node ids start at 1; applies to delta scripts and partitions as well.
+    #[arg(long)]
+    pub one_based: bool,
+    /// Skip the first non-comment line of the file.
+    #[arg(long)]
+    pub header: bool,
+    /// Node count, for graphs whose last nodes have no edges.
     #[arg(long)]
-    pub graph_source: String,
+    pub node_count: Option<usize>,
+}
+
+/// Every knob of `RunConfig`.
+#[derive(Args, Debug)]
+pub struct CliOptions {
     #[arg(long, value_enum, default_value = "deterministic")]
     pub mode: CliMode,
-    #[arg(long, default_value = "in-memory")]
-    pub backend: String,
+    #[arg(long, value_enum, default_value = "modularity")]
+    pub quality: CliQuality,
+    #[arg(long, default_value_t = 1.0)]
+    pub resolution: f64,
+    #[arg(long, default_value_t = 0.001)]
+    pub quality_tolerance: f64,
+    #[arg(long, default_value_t = 10)]
+    pub max_iterations: usize,
+    #[arg(long)]
+    pub seed: Option<u64>,
+    #[arg(long, default_value_t = 0.01)]
+    pub randomness: f64,
+    #[arg(long, conflicts_with = "max_community_weight")]
+    pub max_community_nodes: Option<usize>,
+    #[arg(long)]
+    pub max_community_weight: Option<f64>,
+    #[arg(long)]
+    pub directed: bool,
+    #[arg(long, value_enum, default_value = "sum")]
+    pub parallel_edges: CliParallelEdges,
+    #[arg(long, value_enum, default_value = "in-memory")]
+    pub backend: CliBackend,
+    #[arg(long)]
+    pub pinned_profile: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub graph: GraphArgs,
+    #[command(flatten)]
+    pub options: CliOptions,
+    /// Partition file; stdout if omitted.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+    /// Run report file; stderr if omitted.
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct UpdateArgs {
+    #[command(flatten)]
+    pub graph: GraphArgs,
+    #[command(flatten)]
+    pub options: CliOptions,
+    /// Delta script: one change per line, batches ended by `commit`.
+    #[arg(long)]
+    pub deltas: PathBuf,
+    /// Partition file; stdout if omitted.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+    /// Report with a line per batch; stderr if omitted.
+    #[arg(long)]
+    pub report: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ValidateArgs {
+    #[command(flatten)]
+    pub graph: GraphArgs,
+    #[command(flatten)]
+    pub options: CliOptions,
+    /// Validation report file; stdout if omitted.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    pub left: PathBuf,
+    pub right: PathBuf,
+    /// Node ids of both partitions start at 1.
+    #[arg(long)]
+    pub one_based: bool,
+    /// Comparison report file; stdout if omitted.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    pub graph: GraphArgs,
+    /// Count `(u, v)` and `(v, u)` as different edges.
+    #[arg(long)]
+    pub directed: bool,
+    /// Statistics file; stdout if omitted.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+    #[command(flatten)]
+    pub graph: GraphArgs,
+    /// Merge parallel edges before writing. CSR files are always merged, by
+    /// sum unless this says otherwise.
+    #[arg(long, value_enum)]
+    pub merge_parallel_edges: Option<CliParallelEdges>,
+    /// Keep `(u, v)` and `(v, u)` apart when merging; required for and
+    /// written into directed CSR files.
+    #[arg(long)]
+    pub directed: bool,
+    #[arg(long, value_enum, default_value = "edge-list")]
+    pub to: CliFormat,
+    #[arg(long, value_enum, default_value = "whitespace")]
+    pub to_separator: CliSeparator,
+    /// Write node ids starting at 1.
+    #[arg(long)]
+    pub to_one_based: bool,
+    /// Converted graph file; stdout if omitted.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
 }
@@ -1,33 +1,66 @@
-use crate::cli::options::{CliMode, CliOptions};
+use crate::cli::options::{CliBackend, CliMode, CliOptions, CliParallelEdges, CliQuality};
+use crate::core::algorithm::quality::QualityKind;
 use crate::core::backend::{AccelerationTarget, GraphBackend, GraphSource};
-use crate::core::config::{RunConfig, RunMode};
+use crate::core::config::{CommunitySizeLimit, ParallelEdges, RunConfig, RunMode};
 use crate::core::types::GraphInput;
 
 pub fn run_from_cli(
     options: &CliOptions,
     graph: &GraphInput,
 ) -> Result<crate::core::types::RunOutcome, crate::core::error::HitLeidenError> {
+    crate::run(graph, &config_from_cli(options))
+}
+
+/// The `RunConfig` described by command-line `options`.
+pub fn config_from_cli(options: &CliOptions) -> RunConfig {
     let mode = match options.mode {
         CliMode::Deterministic => RunMode::Deterministic,
         CliMode::Throughput => RunMode::Throughput,
+        CliMode::DeterministicParallel => RunMode::DeterministicParallel,
     };
 
-    let graph_backend = match options.backend.as_str() {
-        "mmap" => GraphBackend::Mmap,
-        _ => GraphBackend::InMemory,
+    let quality = match options.quality {
+        CliQuality::Modularity => QualityKind::Modularity,
+        CliQuality::Cpm => QualityKind::Cpm,
+    };
+
+    let parallel_edges = parallel_edges_from_cli(&options.parallel_edges);
+
+    let graph_backend = match options.backend {
+        CliBackend::InMemory => GraphBackend::InMemory,
+        CliBackend::Mmap => GraphBackend::Mmap,
     };
 
-    let config = RunConfig {
+    let max_community_size = match (options.max_community_nodes, options.max_community_weight) {
+        (Some(nodes), _) => Some(CommunitySizeLimit::Nodes(nodes)),
+        (None, Some(weight)) => Some(CommunitySizeLimit::Weight(weight)),
+        (None, None) => None,
+    };
+
+    RunConfig {
         mode,
-        graph_source: GraphSource::File, // Assuming file for now
+        graph_source: GraphSource::File,
         graph_backend,
         acceleration: AccelerationTarget::PureRust,
-        quality_tolerance: 0.001,
-        max_iterations: 10,
-        pinned_profile: None,
-    };
+        quality,
+        resolution: options.resolution,
+        quality_tolerance: options.quality_tolerance,
+        max_iterations: options.max_iterations,
+        seed: options.seed,
+        randomness: options.randomness,
+        max_community_size,
+        directed: options.directed,
+        parallel_edges,
+        pinned_profile: options.pinned_profile.clone(),
+    }
+}
 
-    crate::run(graph, &config)
+pub fn parallel_edges_from_cli(merge: &CliParallelEdges) -> ParallelEdges {
+    match merge {
+        CliParallelEdges::Sum => ParallelEdges::Sum,
+        CliParallelEdges::Max => ParallelEdges::Max,
+        CliParallelEdges::Count => ParallelEdges::Count,
+    }
 }
 
 pub fn run_default(
@@ -0,0 +1,29 @@
+use crate::core::algorithm::quality::Mass;
+use crate::core::algorithm::size_limit::SizeBudget;
+
+/// Running totals of the communities movement moves vertices between: their
+/// mass and, under a size limit, their budget.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Communities {
+    /// Mass, indexed by community label.
+    pub masses: Vec<Mass>,
+    pub budget: Option<SizeBudget>,
+}
+
+impl Communities {
+    /// Whether `node` fits in `community` under the size limit, if any.
+    pub fn admits(&self, node: usize, community: usize) -> bool {
+        self.budget
+            .as_ref()
+            .map_or(true, |budget| budget.admits(node, community))
+    }
+
+    /// Record that `node`, of mass `mass`, moved from community `from` to `to`.
+    pub fn transfer(&mut self, node: usize, from: usize, to: usize, mass: Mass) {
+        if let Some(budget) = self.budget.as_mut() {
+            budget.transfer(node, from, to);
+        }
+        self.masses[from] -= mass;
+        self.masses[to] += mass;
+    }
+}
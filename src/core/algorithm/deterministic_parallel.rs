@@ -0,0 +1,275 @@
+use crate::core::algorithm::communities::Communities;
+use crate::core::algorithm::deterministic::tie_break_gain;
+use crate::core::algorithm::quality::MoveCost;
+use crate::core::algorithm::randomized::RandomMerge;
+use crate::core::algorithm::subcommunities::Subcommunities;
+use crate::core::graph::in_memory::InMemoryGraph;
+use bitvec::prelude::*;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+
+/// Vertices per parallel work item. Fixed rather than derived from the thread
+/// count, so the work split, and with it every result, is the same on any pool.
+const CHUNK_SIZE: usize = 1024;
+
+/// Best community for `node` against the current assignment, if any improves
+/// on staying and has room for it. Candidates are scanned in label order with
+/// the shared tie-breaker.
+fn best_move(
+    graph: &InMemoryGraph,
+    node: usize,
+    node_to_community: &[usize],
+    communities: &Communities,
+    cost: MoveCost,
+) -> Option<usize> {
+    let current_community = node_to_community[node];
+    let mut weights: BTreeMap<usize, f64> = BTreeMap::new();
+    let mut weight_to_current_community = 0.0;
+    for (neighbor_node, w) in graph.neighbors(node) {
+        if neighbor_node == node {
+            continue;
+        }
+        let c = node_to_community[neighbor_node];
+        *weights.entry(c).or_insert(0.0) += w;
+        if c == current_community {
+            weight_to_current_community += w;
+        }
+    }
+
+    let mut best: Option<(f64, usize)> = None;
+    for (&candidate_community, &weight_to_candidate) in &weights {
+        if candidate_community == current_community
+            || !communities.admits(node, candidate_community)
+        {
+            continue;
+        }
+        let gain = cost.gain(
+            node,
+            weight_to_candidate,
+            weight_to_current_community,
+            communities.masses[current_community],
+            communities.masses[candidate_community],
+        );
+        if gain > 0.0 {
+            best = Some(match best {
+                None => (gain, candidate_community),
+                Some((best_gain, best_community)) => {
+                    tie_break_gain(best_gain, best_community, gain, candidate_community)
+                }
+            });
+        }
+    }
+    best.map(|(_, community)| community)
+}
+
+/// One movement round of `RunMode::DeterministicParallel`.
+///
+/// Every active vertex proposes its best move against a snapshot of the
+/// assignment, in parallel over fixed-size chunks. Proposals are then
+/// committed one by one in vertex order, each re-checked against the live
+/// assignment and size limit as in throughput mode, so the result depends
+/// only on the input.
+pub fn inc_movement_parallel(
+    graph: &InMemoryGraph,
+    active_nodes: &BitVec,
+    node_to_community: &mut [usize],
+    node_to_subcommunity: &[usize],
+    communities: &mut Communities,
+    cost: MoveCost,
+) -> (BitVec, BitVec, BitVec) {
+    let n = graph.node_count;
+    let active_nodes_vec: Vec<usize> = active_nodes.iter_ones().collect();
+
+    let snapshot_communities: &[usize] = node_to_community;
+    let snapshot: &Communities = communities;
+    let proposals: Vec<Vec<(usize, usize)>> = active_nodes_vec
+        .par_chunks(CHUNK_SIZE)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .filter_map(|&node| {
+                    best_move(graph, node, snapshot_communities, snapshot, cost)
+                        .map(|community| (node, community))
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut changed_nodes = bitvec![0; n];
+    let mut affected_nodes = bitvec![0; n];
+    let mut next_active_nodes = bitvec![0; n];
+
+    for (node, proposed_community) in proposals.into_iter().flatten() {
+        let current_community = node_to_community[node];
+        let mut weight_to_current_community = 0.0;
+        let mut weight_to_proposed_community = 0.0;
+        for (neighbor_node, w) in graph.neighbors(node) {
+            if neighbor_node == node {
+                continue;
+            }
+            let c = node_to_community[neighbor_node];
+            if c == current_community {
+                weight_to_current_community += w;
+            } else if c == proposed_community {
+                weight_to_proposed_community += w;
+            }
+        }
+
+        let node_mass = cost.node_masses[node];
+        let gain = cost.gain(
+            node,
+            weight_to_proposed_community,
+            weight_to_current_community,
+            communities.masses[current_community],
+            communities.masses[proposed_community],
+        );
+        if proposed_community == current_community
+            || gain <= 0.0
+            || !communities.admits(node, proposed_community)
+        {
+            continue;
+        }
+
+        node_to_community[node] = proposed_community;
+        communities.transfer(node, current_community, proposed_community, node_mass);
+        changed_nodes.set(node, true);
+        affected_nodes.set(node, true);
+
+        for (neighbor_node, _w) in graph.neighbors(node) {
+            if node_to_community[neighbor_node] != proposed_community {
+                next_active_nodes.set(neighbor_node, true);
+            }
+            if node_to_subcommunity[node] == node_to_subcommunity[neighbor_node] {
+                affected_nodes.set(neighbor_node, true);
+            }
+        }
+    }
+
+    (changed_nodes, affected_nodes, next_active_nodes)
+}
+
+/// Weights from `node` to the sub-communities of its neighbours inside its
+/// own community, in sub-community order, and to its own sub-community.
+fn subcommunity_weights(
+    graph: &InMemoryGraph,
+    node: usize,
+    node_to_community: &[usize],
+    node_to_subcommunity: &[usize],
+) -> (BTreeMap<usize, f64>, f64) {
+    let mut weights: BTreeMap<usize, f64> = BTreeMap::new();
+    let mut weight_to_current = 0.0;
+    for (neighbor_node, w) in graph.neighbors(node) {
+        if neighbor_node == node || node_to_community[neighbor_node] != node_to_community[node] {
+            continue;
+        }
+        let sc = node_to_subcommunity[neighbor_node];
+        *weights.entry(sc).or_insert(0.0) += w;
+        if sc == node_to_subcommunity[node] {
+            weight_to_current += w;
+        }
+    }
+    (weights, weight_to_current)
+}
+
+/// Refinement merging of `RunMode::DeterministicParallel`.
+///
+/// Singletons pick a target sub-community in parallel against a snapshot
+/// (greedily, or by `random` with one draw per position in
+/// `refined_nodes_sorted`); merges are then committed in that order, and only
+/// if the vertex is still a singleton and the merge still does not lower the
+/// objective.
+pub fn inc_refinement_parallel(
+    graph: &InMemoryGraph,
+    refined_nodes_sorted: &[usize],
+    node_to_community: &[usize],
+    node_to_subcommunity: &mut [usize],
+    subcommunities: &mut Subcommunities,
+    cost: MoveCost,
+    random: Option<(RandomMerge, &[f64])>,
+) {
+    let snapshot_subcommunities: &[usize] = node_to_subcommunity;
+    let snapshot: &Subcommunities = subcommunities;
+    let proposals: Vec<Vec<(usize, usize)>> = refined_nodes_sorted
+        .par_chunks(CHUNK_SIZE)
+        .enumerate()
+        .map(|(chunk_idx, chunk)| {
+            let mut local = Vec::new();
+            for (offset, &node) in chunk.iter().enumerate() {
+                let current = snapshot_subcommunities[node];
+                if snapshot.sizes[current] != 1 {
+                    continue;
+                }
+                let (weights, weight_to_current) =
+                    subcommunity_weights(graph, node, node_to_community, snapshot_subcommunities);
+
+                let mut best: Option<(f64, usize)> = None;
+                let mut candidates = vec![(current, 0.0)];
+                for (&candidate, &weight_to_candidate) in &weights {
+                    if candidate == current || !snapshot.admits(node, candidate) {
+                        continue;
+                    }
+                    let gain = cost.gain(
+                        node,
+                        weight_to_candidate,
+                        weight_to_current,
+                        snapshot.mass(current),
+                        snapshot.mass(candidate),
+                    );
+                    if random.is_some() && gain >= 0.0 {
+                        candidates.push((candidate, gain));
+                    }
+                    if gain > 0.0 {
+                        best = Some(match best {
+                            None => (gain, candidate),
+                            Some((best_gain, best_candidate)) => {
+                                tie_break_gain(best_gain, best_candidate, gain, candidate)
+                            }
+                        });
+                    }
+                }
+
+                let chosen = match random {
+                    Some((random, draws)) => {
+                        Some(random.choose(&mut candidates, draws[chunk_idx * CHUNK_SIZE + offset]))
+                    }
+                    None => best.map(|(_, candidate)| candidate),
+                };
+                if let Some(target) = chosen.filter(|&target| target != current) {
+                    local.push((node, target));
+                }
+            }
+            local
+        })
+        .collect();
+
+    for (node, target) in proposals.into_iter().flatten() {
+        let current = node_to_subcommunity[node];
+        // An earlier merge may have absorbed this vertex's singleton or emptied the target
+        if subcommunities.sizes[current] != 1 || subcommunities.sizes[target] == 0 {
+            continue;
+        }
+        let (weights, weight_to_current) =
+            subcommunity_weights(graph, node, node_to_community, node_to_subcommunity);
+        let Some(&weight_to_target) = weights.get(&target) else {
+            continue;
+        };
+        let gain = cost.gain(
+            node,
+            weight_to_target,
+            weight_to_current,
+            subcommunities.mass(current),
+            subcommunities.mass(target),
+        );
+        let accepted = if random.is_some() {
+            gain >= 0.0
+        } else {
+            gain > 0.0
+        };
+        if !accepted || !subcommunities.admits(node, target) {
+            continue;
+        }
+
+        node_to_subcommunity[node] = target;
+        subcommunities.transfer(node, current, target, cost.node_masses[node]);
+    }
+}
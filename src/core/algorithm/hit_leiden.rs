@@ -1,39 +1,52 @@
-use crate::core::config::RunConfig;
+use crate::core::algorithm::communities::Communities;
+use crate::core::algorithm::deterministic::{tie_break_community, tie_break_gain};
+use crate::core::algorithm::quality::{CommunityStats, Mass, MoveCost};
+use crate::core::algorithm::randomized::RandomMerge;
+use crate::core::algorithm::size_limit::{vertex_costs, SizeBudget};
+use crate::core::algorithm::subcommunities::Subcommunities;
+use crate::core::config::{RunConfig, RunMode};
 use crate::core::error::HitLeidenError;
 use crate::core::partition::state::PartitionState;
 use crate::core::runtime::orchestrator;
 use crate::core::types::{
-    BackendType, GraphInput, PartitionResult, RunExecution, RunOutcome, RunStatus,
+    BackendType, GraphInput, PartitionResult, RunExecution, RunOutcome, RunStatus, StopReason,
 };
 use bitvec::prelude::*;
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn run(graph: &GraphInput, config: &RunConfig) -> Result<RunOutcome, HitLeidenError> {
+    run_with_node_weights(graph, &vec![1.0; graph.node_count], config)
+}
+
+/// Like [`run`], with `node_weights[i]` as the weight of node `i`. Node
+/// weights are the vertex mass under CPM; modularity ignores them.
+pub fn run_with_node_weights(
+    graph: &GraphInput,
+    node_weights: &[f64],
+    config: &RunConfig,
+) -> Result<RunOutcome, HitLeidenError> {
     config
         .validate()
         .map_err(|e| HitLeidenError::InvalidInput(e.to_string()))?;
 
-    if graph
-        .edges
-        .iter()
-        .any(|(s, d, _)| *s >= graph.node_count || *d >= graph.node_count)
-    {
-        return Err(HitLeidenError::InvalidInput(
-            "edge endpoint exceeds node_count".to_string(),
-        ));
-    }
+    check_endpoints(graph, graph.node_count)?;
+    check_node_weights(node_weights, graph.node_count)?;
 
     let started_at = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
 
-    let mut partition_state = PartitionState::identity(graph.node_count);
     let resolution = orchestrator::resolve_with_fallback(config, true);
 
-    hit_leiden(&mut partition_state, graph, 1.0, config.mode);
+    let merged = graph.merge_parallel_edges(config.parallel_edges, config.directed);
+    let (partition_state, stats, summary) = optimize(
+        &merged,
+        config,
+        PartitionState::with_weights(graph.node_count, node_weights.to_vec()),
+    );
 
     let execution = RunExecution {
         run_id: format!("run:{}", graph.dataset_id),
@@ -66,13 +79,13 @@ pub fn run(graph: &GraphInput, config: &RunConfig) -> Result<RunOutcome, HitLeid
         fallback_reason: resolution.fallback_reason,
     };
 
-    let partition = PartitionResult {
-        run_id: execution.run_id.clone(),
-        node_to_community: partition_state.node_to_comm,
-        community_count: graph.node_count,
-        quality_score: 1.0,
-        iteration_count: 1,
-    };
+    let partition = partition_result(
+        execution.run_id.clone(),
+        &partition_state,
+        &stats,
+        &summary,
+        config,
+    );
 
     Ok(RunOutcome {
         execution,
@@ -81,66 +94,402 @@ pub fn run(graph: &GraphInput, config: &RunConfig) -> Result<RunOutcome, HitLeid
     })
 }
 
+/// Reject edges whose endpoints fall outside `0..node_count`.
+pub(crate) fn check_endpoints(graph: &GraphInput, node_count: usize) -> Result<(), HitLeidenError> {
+    if graph
+        .edges
+        .iter()
+        .any(|(s, d, _)| *s >= node_count || *d >= node_count)
+    {
+        return Err(HitLeidenError::InvalidInput(
+            "edge endpoint exceeds node_count".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Reject a node weight table that does not cover exactly `0..node_count` or
+/// holds a weight that is negative or not finite.
+pub(crate) fn check_node_weights(
+    node_weights: &[f64],
+    node_count: usize,
+) -> Result<(), HitLeidenError> {
+    if node_weights.len() != node_count {
+        return Err(HitLeidenError::InvalidInput(format!(
+            "node_weights has {} entries for node_count {node_count}",
+            node_weights.len()
+        )));
+    }
+    if let Some((node, weight)) = node_weights
+        .iter()
+        .enumerate()
+        .find(|(_, w)| !(w.is_finite() && **w >= 0.0))
+    {
+        return Err(HitLeidenError::InvalidInput(format!(
+            "weight {weight} of node {node} must be finite and non-negative"
+        )));
+    }
+    Ok(())
+}
+
+/// How an optimisation run went, for reporting in [`PartitionResult`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct PassSummary {
+    pub movement_rounds: usize,
+    pub passes: usize,
+    pub stop_reason: StopReason,
+}
+
+/// Cluster `graph` from scratch starting from the communities in `initial`.
+///
+/// The first pass builds the hierarchy; each further pass starts a fresh
+/// hierarchy from the previous level-0 partition, as in Leiden's outer loop.
+/// Passes stop once one improves the configured quality by less than
+/// `config.quality_tolerance` (or not at all), or after `config.max_iterations`
+/// passes. A pass that lowers quality is discarded. `graph` must already have
+/// its parallel edges merged as `config.parallel_edges` says; merging it again
+/// would, under `ParallelEdges::Count`, reset every weight to one.
+pub(crate) fn optimize(
+    graph: &GraphInput,
+    config: &RunConfig,
+    initial: PartitionState,
+) -> (PartitionState, CommunityStats, PassSummary) {
+    let random = |pass: u64| RandomMerge::from_config(config).map(|r| r.for_stream(pass));
+    let evaluate = |state: &PartitionState, labels: &[usize]| {
+        let stats = CommunityStats::from_partition(
+            &state.supergraphs[0],
+            labels,
+            &state.node_weights_per_level[0],
+            &state.removed_nodes,
+        );
+        let value = stats.quality(
+            config.quality.function(),
+            state.supergraphs[0].total_weight(),
+            config.resolution,
+        );
+        (stats, value)
+    };
+
+    let start_labels = initial.node_to_comm.clone();
+    let mut best = initial;
+    let (mut movement_rounds, _) = hit_leiden(&mut best, graph, &[], config, random(0));
+    let (mut stats, mut quality) = evaluate(&best, &best.node_to_comm);
+    let mut gain = quality - evaluate(&best, &start_labels).1;
+    let mut passes = 1;
+
+    let stop_reason = loop {
+        if gain <= 0.0 || gain < config.quality_tolerance {
+            break StopReason::Converged;
+        }
+        if passes >= config.max_iterations {
+            break StopReason::MaxIterations;
+        }
+
+        let mut next = restart_from(&best);
+        movement_rounds += hit_leiden(&mut next, graph, &[], config, random(passes as u64)).0;
+        passes += 1;
+        let (next_stats, next_quality) = evaluate(&next, &next.node_to_comm);
+        gain = next_quality - quality;
+        if gain > 0.0 {
+            best = next;
+            stats = next_stats;
+            quality = next_quality;
+        }
+    };
+
+    (
+        best,
+        stats,
+        PassSummary {
+            movement_rounds,
+            passes,
+            stop_reason,
+        },
+    )
+}
+
+/// A state with no hierarchy whose level-0 communities are those of `state`.
+pub(crate) fn restart_from(state: &PartitionState) -> PartitionState {
+    let n = state.node_to_comm.len();
+    let mut next = PartitionState::identity(n);
+    next.node_to_comm.clone_from(&state.node_to_comm);
+    next.community_mapping_per_level[0].clone_from(&state.node_to_comm);
+    next.removed_nodes.clone_from(&state.removed_nodes);
+    next.node_weights_per_level[0].clone_from(&state.node_weights_per_level[0]);
+    next
+}
+
+/// Summarise the current level-0 partition held in `state`, whose totals are
+/// kept in `stats`.
+pub(crate) fn partition_result(
+    run_id: String,
+    state: &PartitionState,
+    stats: &CommunityStats,
+    summary: &PassSummary,
+    config: &RunConfig,
+) -> PartitionResult {
+    PartitionResult {
+        run_id,
+        node_to_community: state.node_to_comm.clone(),
+        community_count: stats.community_count(),
+        quality_score: stats.quality(
+            config.quality.function(),
+            state.supergraphs[0].total_weight(),
+            config.resolution,
+        ),
+        iteration_count: summary.movement_rounds,
+        pass_count: summary.passes,
+        stop_reason: summary.stop_reason,
+    }
+}
+
+/// Marks a vertex that has not been placed in a level-(p+1) supervertex yet.
+/// Only brand-new vertices carry it, and only until the next refinement at their level.
+const UNASSIGNED: usize = usize::MAX;
+
 // Algorithm 6: HIT-Leiden
+//
+// On the first call (no supergraphs yet) `delta_g` is the whole graph and the
+// hierarchy is built Leiden-style: move, refine, then aggregate the refined
+// partition into the next supergraph until refinement stops shrinking it. Later
+// calls treat `delta_g` as ΔG and propagate it through every existing level.
+// `reweights` sets the weight of level-0 nodes, existing or appended by ΔG.
+// The objective, run mode and size limit come from `config`, and
+// `config.directed` reads the edges of the first call as arcs; later calls
+// follow the direction of the existing graph. With `random`, refinement
+// merges singletons Leiden-style at random.
+// Returns the number of movement rounds performed, summed over all levels,
+// and the level-0 nodes the call touched: those whose community or
+// supervertex at some level may have changed, the endpoints of ΔG and the
+// reweighted nodes. On the first call every node is touched.
 pub fn hit_leiden(
     state: &mut PartitionState,
     delta_g: &GraphInput,
-    gamma: f64,
-    mode: crate::core::config::RunMode,
-) {
+    reweights: &[(usize, f64)],
+    config: &RunConfig,
+    random: Option<RandomMerge>,
+) -> (usize, BitVec) {
     use crate::core::graph::in_memory::InMemoryGraph;
 
-    if state.supergraphs.is_empty() {
-        state.supergraphs.push(InMemoryGraph::from(delta_g));
+    let initial = state.supergraphs.is_empty();
+    let mut current_delta: Cow<GraphInput> = if initial {
+        state.supergraphs.push(if config.directed {
+            InMemoryGraph::directed(delta_g)
+        } else {
+            InMemoryGraph::from(delta_g)
+        });
+        state.levels = 1;
+        state.current_subcommunity_mapping_per_level[0].fill(UNASSIGNED);
+        state.previous_subcommunity_mapping_per_level[0].fill(UNASSIGNED);
+        Cow::Owned(GraphInput::empty(delta_g.dataset_id.clone()))
+    } else {
+        // Use Cow to avoid cloning delta_g at level 0; only own when aggregation produces a new delta
+        Cow::Borrowed(delta_g)
+    };
+
+    // Nodes appended by ΔG start as singleton communities labelled by their id
+    let previous_n_0 = state.supergraphs[0].node_count;
+    let n_0 = previous_n_0.max(delta_g.node_count);
+    if n_0 > previous_n_0 {
+        grow_level(state, 0, n_0 - previous_n_0);
+        for v in previous_n_0..n_0 {
+            state.community_mapping_per_level[0][v] = v;
+            state.refined_community_mapping_per_level[0][v] = v;
+            state.node_weights_per_level[0][v] = 1.0;
+        }
+    }
+    if state.node_to_comm.len() < n_0 {
+        state.grow(n_0 - state.node_to_comm.len());
     }
 
-    let p_max = state.levels;
-    // Use Cow to avoid cloning delta_g at level 0; only own when aggregation produces a new delta
-    let mut current_delta: Cow<GraphInput> = Cow::Borrowed(delta_g);
+    // A vertex whose weight changed, and its neighbours, are re-evaluated at
+    // every level it belongs to
+    let mut reweighted_per_level: Vec<Vec<usize>> = vec![Vec::new(); state.levels];
+    for &(node, weight) in reweights {
+        if node >= previous_n_0 {
+            state.node_weights_per_level[0][node] = weight;
+            continue;
+        }
+        state.set_node_weight(node, weight);
+        let mut vertex = node;
+        for (p, vertices) in reweighted_per_level.iter_mut().enumerate() {
+            vertices.push(vertex);
+            vertex = state.current_subcommunity_mapping_per_level[p][vertex];
+        }
+    }
+
+    let mut seed_active = bitvec![usize::from(initial); n_0];
+    seed_active[previous_n_0..].fill(true);
+    let mut new_nodes = seed_active.clone();
+    // Vertices whose label was carried up from a member that changed community
+    let mut relabelled_nodes = bitvec![0; n_0];
+    // Weight before this update of the vertices at the current level whose members moved
+    let mut old_weights: BTreeMap<usize, f64> = BTreeMap::new();
+
+    let mut changed_nodes_per_level: Vec<BitVec> = Vec::with_capacity(state.levels);
+    let mut refined_nodes_per_level: Vec<BitVec> = Vec::with_capacity(state.levels);
+
+    let mut movement_rounds = 0;
+
+    // 2 for p from 1 to P do
+    let mut p = 0;
+    loop {
+        if !initial {
+            // 3 G_p \leftarrow G_p \oplus \Delta G_p
+            state.supergraphs[p].apply_delta(&current_delta);
+        }
+        let n = state.supergraphs[p].node_count;
+        let is_top = p + 1 == state.levels;
+        for &v in reweighted_per_level.get(p).into_iter().flatten() {
+            seed_active.set(v, true);
+            for (neighbor, _) in state.supergraphs[p].neighbors(v) {
+                seed_active.set(neighbor, true);
+            }
+        }
+
+        let first_new_id = next_subcommunity_id(state, p);
+        let idle = current_delta.edges.is_empty() && !seed_active.any() && !new_nodes.any();
+        let (mut b_p, r_p) = if idle {
+            (bitvec![0; n], bitvec![0; n])
+        } else {
+            let (b_p, k, level_rounds) =
+                inc_movement(state, p, &current_delta, seed_active, config);
+            movement_rounds += level_rounds;
+
+            let r_p = inc_refinement(
+                state,
+                p,
+                &k,
+                &new_nodes,
+                first_new_id,
+                config,
+                random.map(|r| r.for_level(p)),
+            );
+            (b_p, r_p)
+        };
+        // A relabelled vertex changed community too: its label must reach the
+        // level above, and def-update must hand the final label to its members
+        b_p |= &relabelled_nodes;
+
+        let created = compact_new_subcommunities(
+            &mut state.current_subcommunity_mapping_per_level[p],
+            &r_p,
+            first_new_id,
+        );
+        // Vertices new at this level have no previous supervertex to move edges away from
+        for v in new_nodes.iter_ones() {
+            state.previous_subcommunity_mapping_per_level[p][v] =
+                state.current_subcommunity_mapping_per_level[p][v];
+        }
+
+        if is_top {
+            let subcommunity_count = first_new_id + created;
+            if initial && subcommunity_count < n {
+                // Leiden aggregation: the refined partition becomes the next supergraph
+                let next_graph = aggregate(
+                    &state.supergraphs[p],
+                    &state.current_subcommunity_mapping_per_level[p],
+                    subcommunity_count,
+                );
+                push_level(state, p, next_graph);
+                changed_nodes_per_level.push(b_p);
+                refined_nodes_per_level.push(r_p);
+                seed_active = bitvec![1; subcommunity_count];
+                new_nodes = seed_active.clone();
+                relabelled_nodes = bitvec![0; subcommunity_count];
+                p += 1;
+                continue;
+            }
+            changed_nodes_per_level.push(b_p);
+            refined_nodes_per_level.push(r_p);
+            break;
+        }
 
-    let mut changed_nodes_per_level: Vec<BitVec> = vec![bitvec![0; delta_g.node_count]; p_max];
-    let mut refined_nodes_per_level: Vec<BitVec> = vec![bitvec![0; delta_g.node_count]; p_max];
+        // New supervertices created by refinement join level p+1 as unplaced vertices
+        let next_n = state.supergraphs[p + 1].node_count;
+        grow_level(state, p + 1, created);
+        let mut next_seed = bitvec![0; next_n + created];
+        next_seed[next_n..].fill(true);
+        let next_new = next_seed.clone();
+        let mut next_relabelled = bitvec![0; next_n + created];
+
+        // Move the weight of re-assigned vertices between supervertices, and
+        // pass on the change in weight of vertices whose members moved
+        let (lower, upper) = state.node_weights_per_level.split_at_mut(p + 1);
+        let (weights_p, weights_next) = (&lower[p], &mut upper[0]);
+        let mut next_old_weights = BTreeMap::new();
+        let resized = old_weights.keys().copied().filter(|&v| !r_p[v]);
+        for v in r_p.iter_ones().chain(resized) {
+            let previous = state.previous_subcommunity_mapping_per_level[p][v];
+            let current = state.current_subcommunity_mapping_per_level[p][v];
+            let old = old_weights.get(&v).copied().unwrap_or(weights_p[v]);
+            if new_nodes[v] {
+                next_old_weights
+                    .entry(current)
+                    .or_insert(weights_next[current]);
+                weights_next[current] += weights_p[v];
+            } else if previous != current || old != weights_p[v] {
+                for supervertex in [previous, current] {
+                    next_old_weights
+                        .entry(supervertex)
+                        .or_insert(weights_next[supervertex]);
+                }
+                weights_next[previous] -= old;
+                weights_next[current] += weights_p[v];
+            }
+        }
 
-    for p in 0..p_max {
-        let (b_p, k) = inc_movement(
+        // Keep f_{p+1} consistent with the memberships that changed at level p
+        let s_cur = &state.current_subcommunity_mapping_per_level[p];
+        let (lower, upper) = state.community_mapping_per_level.split_at_mut(p + 1);
+        let (f_p, f_next) = (&lower[p], &mut upper[0]);
+        for v in b_p.iter_ones().chain(r_p.iter_ones()) {
+            let supervertex = s_cur[v];
+            if f_next[supervertex] != f_p[v] {
+                f_next[supervertex] = f_p[v];
+                next_seed.set(supervertex, true);
+                next_relabelled.set(supervertex, true);
+            }
+        }
+
+        // 6 if p < P then
+        // 7 \Delta G_{p+1}, s_p^{pre} \leftarrow inc-aggregation(...)
+        let next_delta = inc_aggregation(
             &state.supergraphs[p],
             &current_delta,
-            &mut state.community_mapping_per_level[p],
+            &mut state.previous_subcommunity_mapping_per_level[p],
             &state.current_subcommunity_mapping_per_level[p],
-            gamma,
-            mode,
+            &r_p,
+            next_n + created,
         );
-        changed_nodes_per_level[p] = b_p;
+        current_delta = Cow::Owned(next_delta);
+
+        changed_nodes_per_level.push(b_p);
+        refined_nodes_per_level.push(r_p);
+        seed_active = next_seed;
+        new_nodes = next_new;
+        relabelled_nodes = next_relabelled;
+        old_weights = next_old_weights;
+        p += 1;
+    }
 
-        let r_p = inc_refinement(
-            &state.supergraphs[p],
-            &state.community_mapping_per_level[p],
-            &mut state.current_subcommunity_mapping_per_level[p],
-            &k,
-            gamma,
-            mode,
-        );
-        refined_nodes_per_level[p] = r_p.clone();
-
-        if p < p_max - 1 {
-            let (next_delta, next_s_pre) = inc_aggregation(
-                &state.supergraphs[p],
-                &current_delta,
-                &state.previous_subcommunity_mapping_per_level[p],
-                &state.current_subcommunity_mapping_per_level[p],
-                &r_p,
-            );
-            current_delta = Cow::Owned(next_delta);
-            state.previous_subcommunity_mapping_per_level[p] = next_s_pre;
-        }
+    let p_max = state.levels;
+
+    // The top level's refined mapping is its own sub-community assignment
+    let top = p_max - 1;
+    for v in refined_nodes_per_level[top].iter_ones() {
+        state.refined_community_mapping_per_level[top][v] =
+            state.current_subcommunity_mapping_per_level[top][v];
     }
 
+    // 8 {f_P} \leftarrow def-update({f_P}, {s_P^{cur}}, {B_P}, P)
     def_update(
         &mut state.community_mapping_per_level,
         &state.current_subcommunity_mapping_per_level,
         &mut changed_nodes_per_level,
         p_max,
     );
+    // 9 {g_P} \leftarrow def-update({g_P}, {s_P^{cur}}, {R_P}, P)
     def_update(
         &mut state.refined_community_mapping_per_level,
         &state.current_subcommunity_mapping_per_level,
@@ -148,18 +497,170 @@ pub fn hit_leiden(
         p_max,
     );
     state.node_to_comm = state.community_mapping_per_level[0].clone();
+
+    let mut touched = std::mem::take(&mut changed_nodes_per_level[0]);
+    touched |= &refined_nodes_per_level[0];
+    if initial {
+        touched.fill(true);
+    }
+    for &(u, v, _) in &delta_g.edges {
+        touched.set(u, true);
+        touched.set(v, true);
+    }
+    for &(node, _) in reweights {
+        touched.set(node, true);
+    }
+    (movement_rounds, touched)
 }
 
-fn inc_movement(
+/// First id available for a new sub-community at level `p`.
+///
+/// Below the top, sub-community ids are level-(p+1) vertex ids, so new ones are
+/// appended after the existing supervertices. At the top they are plain labels.
+fn next_subcommunity_id(state: &PartitionState, p: usize) -> usize {
+    if p + 1 < state.levels {
+        state.supergraphs[p + 1].node_count
+    } else {
+        state.current_subcommunity_mapping_per_level[p]
+            .iter()
+            .filter(|&&s| s != UNASSIGNED)
+            .max()
+            .map_or(0, |&s| s + 1)
+    }
+}
+
+/// Renumber the fresh sub-community ids handed out during refinement so that
+/// the ones still in use are contiguous from `first_new_id`, in vertex order.
+/// Returns how many new sub-communities remain.
+fn compact_new_subcommunities(
+    node_to_subcommunity: &mut [usize],
+    refined_nodes: &BitVec,
+    first_new_id: usize,
+) -> usize {
+    let mut renumbered: HashMap<usize, usize> = HashMap::new();
+    for v in refined_nodes.iter_ones() {
+        let sc = node_to_subcommunity[v];
+        if sc >= first_new_id {
+            let next = first_new_id + renumbered.len();
+            node_to_subcommunity[v] = *renumbered.entry(sc).or_insert(next);
+        }
+    }
+    renumbered.len()
+}
+
+/// Collapse `graph` by `node_to_subcommunity` into a supergraph with
+/// `subcommunity_count` vertices. Intra-sub-community edges become self-loops;
+/// a directed graph collapses into a directed supergraph.
+fn aggregate(
     graph: &crate::core::graph::in_memory::InMemoryGraph,
-    delta_graph: &GraphInput,
-    node_to_community: &mut Vec<usize>,
     node_to_subcommunity: &[usize],
-    resolution_parameter: f64,
-    mode: crate::core::config::RunMode,
-) -> (BitVec, BitVec) {
+    subcommunity_count: usize,
+) -> crate::core::graph::in_memory::InMemoryGraph {
+    let mut superedges: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+    for u in 0..graph.node_count {
+        if graph.is_directed() {
+            for (v, w) in graph.out_neighbors(u) {
+                let key = (node_to_subcommunity[u], node_to_subcommunity[v]);
+                *superedges.entry(key).or_insert(0.0) += w;
+            }
+            continue;
+        }
+        for (v, w) in graph.neighbors(u) {
+            // Self-loops are stored twice in the owning vertex's adjacency
+            let weight = match u.cmp(&v) {
+                std::cmp::Ordering::Less => w,
+                std::cmp::Ordering::Equal => w / 2.0,
+                std::cmp::Ordering::Greater => continue,
+            };
+            let (a, b) = (node_to_subcommunity[u], node_to_subcommunity[v]);
+            let key = if a <= b { (a, b) } else { (b, a) };
+            *superedges.entry(key).or_insert(0.0) += weight;
+        }
+    }
+
+    let supergraph = GraphInput {
+        dataset_id: String::new(),
+        node_count: subcommunity_count,
+        edges: superedges
+            .into_iter()
+            .map(|((a, b), w)| (a, b, Some(w)))
+            .collect(),
+    };
+    if graph.is_directed() {
+        crate::core::graph::in_memory::InMemoryGraph::directed(&supergraph)
+    } else {
+        crate::core::graph::in_memory::InMemoryGraph::from(&supergraph)
+    }
+}
+
+/// Append `next_graph` as level `p + 1`, seeding its community mapping from level `p`.
+fn push_level(
+    state: &mut PartitionState,
+    p: usize,
+    next_graph: crate::core::graph::in_memory::InMemoryGraph,
+) {
+    let n = next_graph.node_count;
+    let mut next_communities = vec![0; n];
+    let s_cur = &state.current_subcommunity_mapping_per_level[p];
+    for (v, &community) in state.community_mapping_per_level[p].iter().enumerate() {
+        next_communities[s_cur[v]] = community;
+    }
+    let mut next_weights = vec![0.0; n];
+    for (v, &weight) in state.node_weights_per_level[p].iter().enumerate() {
+        next_weights[s_cur[v]] += weight;
+    }
+    state.previous_subcommunity_mapping_per_level[p] = s_cur.clone();
+
+    state.supergraphs.push(next_graph);
+    state.node_weights_per_level.push(next_weights);
+    state.community_mapping_per_level.push(next_communities);
+    state
+        .refined_community_mapping_per_level
+        .push((0..n).collect());
+    state
+        .previous_subcommunity_mapping_per_level
+        .push(vec![UNASSIGNED; n]);
+    state
+        .current_subcommunity_mapping_per_level
+        .push(vec![UNASSIGNED; n]);
+    state.levels += 1;
+}
+
+/// Add `count` unplaced vertices to level `p`. Their community is filled in by
+/// the caller and their sub-community by the next refinement at this level.
+fn grow_level(state: &mut PartitionState, p: usize, count: usize) {
+    if count == 0 {
+        return;
+    }
+    let n = state.supergraphs[p].node_count + count;
+    state.supergraphs[p].add_nodes(count);
+    state.community_mapping_per_level[p].resize(n, 0);
+    state.refined_community_mapping_per_level[p].resize(n, 0);
+    state.previous_subcommunity_mapping_per_level[p].resize(n, UNASSIGNED);
+    state.current_subcommunity_mapping_per_level[p].resize(n, UNASSIGNED);
+    state.node_weights_per_level[p].resize(n, 0.0);
+}
+
+/// Movement phase at level `p`, starting from `active_nodes` and the
+/// endpoints of `delta_graph`. Returns B (vertices that changed community), K
+/// (vertices whose sub-community must be re-checked) and the rounds taken.
+/// Under a size limit a vertex only joins a community it fits in.
+fn inc_movement(
+    state: &mut PartitionState,
+    p: usize,
+    delta_graph: &GraphInput,
+    mut active_nodes: BitVec,
+    config: &RunConfig,
+) -> (BitVec, BitVec, usize) {
+    let size_limit = config
+        .max_community_size
+        .map(|limit| (limit, vertex_costs(state, p, limit)));
+    let graph = &state.supergraphs[p];
+    let node_to_community = &mut state.community_mapping_per_level[p];
+    let node_to_subcommunity = &state.current_subcommunity_mapping_per_level[p];
+    let node_weights = &state.node_weights_per_level[p];
+    let quality = config.quality.function();
     let n = graph.node_count;
-    let mut active_nodes = bitvec![0; n];
     let mut changed_nodes = bitvec![0; n];
     let mut affected_nodes_for_refinement = bitvec![0; n];
 
@@ -180,141 +681,245 @@ fn inc_movement(
         }
     }
 
-    // If delta_graph is empty (initial run), activate all nodes
-    if delta_graph.edges.is_empty() {
-        active_nodes.fill(true);
+    // Community labels are shared across levels, so they may exceed this level's vertex count
+    let community_capacity = node_to_community.iter().max().map_or(0, |&c| c + 1).max(n);
+
+    // Communities over the size budget, e.g. after a reweight, start over from
+    // singletons under unused labels; the smallest vertex keeps the label.
+    // Higher levels hold the same level-0 nodes, so they stay within budget too.
+    let budget = size_limit.map(|(limit, node_costs)| {
+        let budget = SizeBudget::new(limit, &node_costs, node_to_community, community_capacity);
+        if p > 0 {
+            return budget;
+        }
+        let used: HashSet<usize> = node_to_community.iter().copied().collect();
+        let mut unused = (0..n).filter(|c| !used.contains(c));
+        let mut kept = HashSet::new();
+        for v in 0..n {
+            let c = node_to_community[v];
+            if !budget.is_over(c) || kept.insert(c) {
+                continue;
+            }
+            // Every label in use has a member of its own, so one is free per evicted vertex
+            node_to_community[v] = unused.next().expect("a free label per node");
+            changed_nodes.set(v, true);
+            affected_nodes_for_refinement.set(v, true);
+            active_nodes.set(v, true);
+            for (neighbor_node, _w) in graph.neighbors(v) {
+                active_nodes.set(neighbor_node, true);
+                if node_to_subcommunity[v] == node_to_subcommunity[neighbor_node] {
+                    affected_nodes_for_refinement.set(neighbor_node, true);
+                }
+            }
+        }
+        SizeBudget::new(limit, &node_costs, node_to_community, community_capacity)
+    });
+
+    let total_weight = graph.total_weight();
+    if total_weight <= 0.0 {
+        return (changed_nodes, affected_nodes_for_refinement, 0);
     }
+    let penalty = config.resolution * quality.penalty_scale(total_weight);
 
-    let twice_total_weight = graph.total_weight() * 2.0;
-    let mut community_degrees = vec![0.0; n];
-    let mut node_degrees = vec![0.0; n];
+    let mut communities = Communities {
+        masses: vec![Mass::default(); community_capacity],
+        budget,
+    };
+    let mut node_masses = vec![Mass::default(); n];
     for i in 0..n {
-        let d_i: f64 = graph.neighbors(i).map(|(_, w)| w).sum();
-        node_degrees[i] = d_i;
-        community_degrees[node_to_community[i]] += d_i;
+        let (out_strength, in_strength) = graph.arc_strengths(i);
+        node_masses[i] = quality.node_mass(out_strength, in_strength, node_weights[i]);
+        communities.masses[node_to_community[i]] += node_masses[i];
     }
+    let cost = MoveCost {
+        node_masses: &node_masses,
+        penalty,
+    };
 
-    if mode == crate::core::config::RunMode::Throughput {
+    if config.mode == RunMode::Throughput {
         let mut current_active_nodes = active_nodes;
         // Create buffer pool once for reuse across multiple inc_movement_parallel calls
-        let buffer_pool =
-            crate::core::algorithm::throughput::BufferPool::new(n, rayon::current_num_threads());
+        let buffer_pool = crate::core::algorithm::throughput::BufferPool::new(
+            community_capacity,
+            rayon::current_num_threads(),
+        );
+        let mut rounds = 0;
         while current_active_nodes.any() {
+            rounds += 1;
             let (new_changed, new_affected, next_active) =
                 crate::core::algorithm::throughput::inc_movement_parallel(
                     graph,
                     &current_active_nodes,
                     node_to_community,
                     node_to_subcommunity,
-                    &mut community_degrees,
-                    &node_degrees,
-                    twice_total_weight,
-                    resolution_parameter,
+                    &mut communities,
+                    cost,
                     &buffer_pool,
                 );
             changed_nodes |= new_changed;
             affected_nodes_for_refinement |= new_affected;
             current_active_nodes = next_active;
         }
-        return (changed_nodes, affected_nodes_for_refinement);
+        return (changed_nodes, affected_nodes_for_refinement, rounds);
+    }
+
+    if config.mode == RunMode::DeterministicParallel {
+        let mut rounds = 0;
+        while active_nodes.any() {
+            rounds += 1;
+            let (new_changed, new_affected, next_active) =
+                crate::core::algorithm::deterministic_parallel::inc_movement_parallel(
+                    graph,
+                    &active_nodes,
+                    node_to_community,
+                    node_to_subcommunity,
+                    &mut communities,
+                    cost,
+                );
+            changed_nodes |= new_changed;
+            affected_nodes_for_refinement |= new_affected;
+            active_nodes = next_active;
+        }
+        return (changed_nodes, affected_nodes_for_refinement, rounds);
     }
 
     // 9 for A \neq \emptyset do (deterministic mode)
+    // Each round sweeps the current frontier in vertex order; vertices woken up
+    // by a move are visited in the same round if the sweep has not reached them
+    // yet, otherwise in the next one.
+    let mut rounds = 0;
     while active_nodes.any() {
-        let current_node = active_nodes.iter_ones().next().unwrap();
-        active_nodes.set(current_node, false);
-
-        let mut best_community = node_to_community[current_node];
-        let mut best_modularity_gain = 0.0;
-
-        let mut neighbor_communities: HashMap<usize, f64> = HashMap::new();
-        let mut weight_to_current_community = 0.0;
-        let current_node_degree = node_degrees[current_node];
+        rounds += 1;
+        let frontier = std::mem::replace(&mut active_nodes, bitvec![0; n]);
+        for current_node in frontier.iter_ones() {
+            // Candidates are visited in label order and near-equal gains go to
+            // the smaller label, so the outcome does not depend on hashing
+            let mut best: Option<(f64, usize)> = None;
 
-        for (neighbor_node, w) in graph.neighbors(current_node) {
-            let c = node_to_community[neighbor_node];
-            *neighbor_communities.entry(c).or_insert(0.0) += w;
-            if c == node_to_community[current_node] {
-                weight_to_current_community += w;
-            }
-        }
+            let mut neighbor_communities: BTreeMap<usize, f64> = BTreeMap::new();
+            let mut weight_to_current_community = 0.0;
+            let current_node_mass = node_masses[current_node];
 
-        for (&candidate_community, &weight_to_candidate_community) in &neighbor_communities {
-            if candidate_community == node_to_community[current_node] {
-                continue;
+            for (neighbor_node, w) in graph.neighbors(current_node) {
+                // A self-loop moves with the vertex, so it never favours staying put
+                if neighbor_node == current_node {
+                    continue;
+                }
+                let c = node_to_community[neighbor_node];
+                *neighbor_communities.entry(c).or_insert(0.0) += w;
+                if c == node_to_community[current_node] {
+                    weight_to_current_community += w;
+                }
             }
 
-            let current_community_degree = community_degrees[node_to_community[current_node]];
-            let candidate_community_degree = community_degrees[candidate_community];
+            for (&candidate_community, &weight_to_candidate_community) in &neighbor_communities {
+                if candidate_community == node_to_community[current_node]
+                    || !communities.admits(current_node, candidate_community)
+                {
+                    continue;
+                }
 
-            let modularity_gain = (weight_to_candidate_community - weight_to_current_community)
-                / twice_total_weight
-                + resolution_parameter
-                    * current_node_degree
-                    * (current_community_degree - current_node_degree - candidate_community_degree)
-                    / (twice_total_weight * twice_total_weight);
+                let gain = cost.gain(
+                    current_node,
+                    weight_to_candidate_community,
+                    weight_to_current_community,
+                    communities.masses[node_to_community[current_node]],
+                    communities.masses[candidate_community],
+                );
 
-            if modularity_gain > best_modularity_gain {
-                best_modularity_gain = modularity_gain;
-                best_community = candidate_community;
+                if gain > 0.0 {
+                    best = Some(match best {
+                        None => (gain, candidate_community),
+                        Some((best_gain, best_community)) => {
+                            tie_break_gain(best_gain, best_community, gain, candidate_community)
+                        }
+                    });
+                }
             }
-        }
 
-        if best_modularity_gain > 0.0 {
-            let old_community = node_to_community[current_node];
-            node_to_community[current_node] = best_community;
-            changed_nodes.set(current_node, true);
-            community_degrees[old_community] -= current_node_degree;
-            community_degrees[best_community] += current_node_degree;
+            if let Some((_, best_community)) = best {
+                let old_community = node_to_community[current_node];
+                node_to_community[current_node] = best_community;
+                changed_nodes.set(current_node, true);
+                // Its sub-community no longer fits inside one community and must be re-checked
+                affected_nodes_for_refinement.set(current_node, true);
+                communities.transfer(
+                    current_node,
+                    old_community,
+                    best_community,
+                    current_node_mass,
+                );
 
-            for (neighbor_node, _w) in graph.neighbors(current_node) {
-                if node_to_community[neighbor_node] != best_community {
-                    active_nodes.set(neighbor_node, true);
-                }
-                if node_to_subcommunity[current_node] == node_to_subcommunity[neighbor_node] {
-                    affected_nodes_for_refinement.set(current_node, true);
-                    affected_nodes_for_refinement.set(neighbor_node, true);
+                for (neighbor_node, _w) in graph.neighbors(current_node) {
+                    let pending = neighbor_node > current_node && frontier[neighbor_node];
+                    if node_to_community[neighbor_node] != best_community && !pending {
+                        active_nodes.set(neighbor_node, true);
+                    }
+                    if node_to_subcommunity[current_node] == node_to_subcommunity[neighbor_node] {
+                        affected_nodes_for_refinement.set(neighbor_node, true);
+                    }
                 }
             }
         }
     }
 
-    (changed_nodes, affected_nodes_for_refinement)
+    (changed_nodes, affected_nodes_for_refinement, rounds)
 }
 
+/// Refinement phase at level `p` over the sub-communities of `affected_nodes`.
+///
+/// Returns R: every vertex whose sub-community may have changed, including all
+/// vertices in `new_nodes`, which are placed in fresh singleton sub-communities
+/// numbered from `first_new_id` before any merging happens.
 fn inc_refinement(
-    graph: &crate::core::graph::in_memory::InMemoryGraph,
-    node_to_community: &[usize],
-    node_to_subcommunity: &mut Vec<usize>,
+    state: &mut PartitionState,
+    p: usize,
     affected_nodes: &BitVec,
-    resolution_parameter: f64,
-    mode: crate::core::config::RunMode,
+    new_nodes: &BitVec,
+    first_new_id: usize,
+    config: &RunConfig,
+    random: Option<RandomMerge>,
 ) -> BitVec {
+    let size_limit = config
+        .max_community_size
+        .filter(|_| p == 0)
+        .map(|limit| (limit, vertex_costs(state, p, limit)));
+    let graph = &state.supergraphs[p];
+    let node_to_community = &state.community_mapping_per_level[p];
+    let node_to_subcommunity = &mut state.current_subcommunity_mapping_per_level[p];
+    let node_weights = &state.node_weights_per_level[p];
+    let quality = config.quality.function();
     let n = graph.node_count;
-    let mut refined_nodes = bitvec![0; n];
+    let mut refined_nodes = new_nodes.clone();
+    let mut next_subcommunity_id = first_new_id;
+
+    for v in new_nodes.iter_ones() {
+        node_to_subcommunity[v] = next_subcommunity_id;
+        next_subcommunity_id += 1;
+    }
 
     // Build inverted index: subcommunity -> nodes (only for affected subcommunities)
     let mut affected_subcommunities: HashSet<usize> = HashSet::new();
     for v in affected_nodes.iter_ones() {
-        affected_subcommunities.insert(node_to_subcommunity[v]);
+        if !new_nodes[v] {
+            affected_subcommunities.insert(node_to_subcommunity[v]);
+        }
     }
 
-    // Build node lists per affected subcommunity in a single O(n) pass
-    let mut subcomm_nodes: HashMap<usize, Vec<usize>> = HashMap::new();
-    for i in 0..n {
-        let sc = node_to_subcommunity[i];
+    // Build node lists per affected subcommunity in a single O(n) pass, kept
+    // in sub-community order so split-off parts are numbered reproducibly
+    let mut subcomm_nodes: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (i, &sc) in node_to_subcommunity.iter().enumerate() {
         if affected_subcommunities.contains(&sc) {
             subcomm_nodes.entry(sc).or_default().push(i);
         }
     }
 
-    let mut next_subcommunity_id = node_to_subcommunity.iter().max().copied().unwrap_or(0) + 1;
-
     // Reusable visited bitvec across subcommunities
     let mut visited = bitvec![0; n];
 
     // 2 for v_i \in K do — connected component splitting
-    for (_subcommunity, vertices) in &subcomm_nodes {
+    for vertices in subcomm_nodes.values() {
         if vertices.is_empty() {
             continue;
         }
@@ -333,8 +938,12 @@ fn inc_refinement(
             while let Some(current_node) = queue.pop_front() {
                 comp.push(current_node);
                 let current_sc = node_to_subcommunity[current_node];
+                let current_c = node_to_community[current_node];
+                // A sub-community must stay inside a single community
                 for (neighbor_node, _w) in graph.neighbors(current_node) {
-                    if node_to_subcommunity[neighbor_node] == current_sc && !visited[neighbor_node]
+                    if node_to_subcommunity[neighbor_node] == current_sc
+                        && node_to_community[neighbor_node] == current_c
+                        && !visited[neighbor_node]
                     {
                         visited.set(neighbor_node, true);
                         queue.push_back(neighbor_node);
@@ -350,12 +959,17 @@ fn inc_refinement(
         }
 
         if components.len() > 1 {
-            let largest_idx = components
-                .iter()
-                .enumerate()
-                .max_by_key(|(_, c)| c.len())
-                .map(|(i, _)| i)
-                .unwrap();
+            // The largest part keeps the id; among equals, the one with the smallest vertex
+            let mut largest_idx = 0;
+            for (i, comp) in components.iter().enumerate().skip(1) {
+                let largest = &components[largest_idx];
+                if comp.len() > largest.len()
+                    || (comp.len() == largest.len()
+                        && tie_break_community(comp[0], largest[0]) == comp[0])
+                {
+                    largest_idx = i;
+                }
+            }
 
             for (i, comp) in components.iter().enumerate() {
                 if i != largest_idx {
@@ -370,61 +984,102 @@ fn inc_refinement(
         }
     }
 
-    let is_initial = node_to_subcommunity
-        .iter()
-        .enumerate()
-        .all(|(i, &c)| i == c);
-    if is_initial {
-        refined_nodes.fill(true);
+    // Sub-communities over the size budget, e.g. after a reweight, start over
+    // from singletons; the smallest vertex keeps the id
+    let budget = size_limit.map(|(limit, node_costs)| {
+        let capacity = next_subcommunity_id.max(n);
+        let budget = SizeBudget::new(limit, &node_costs, node_to_subcommunity, capacity);
+        let mut kept = HashSet::new();
+        for (v, sc) in node_to_subcommunity.iter_mut().enumerate() {
+            if budget.is_over(*sc) && !kept.insert(*sc) {
+                *sc = next_subcommunity_id;
+                next_subcommunity_id += 1;
+                refined_nodes.set(v, true);
+            }
+        }
+        let capacity = next_subcommunity_id.max(n);
+        SizeBudget::new(limit, &node_costs, node_to_subcommunity, capacity)
+    });
+
+    let total_weight = graph.total_weight();
+    if total_weight <= 0.0 {
+        return refined_nodes;
     }
+    let penalty = config.resolution * quality.penalty_scale(total_weight);
 
     // Pre-compute subcommunity sizes for O(1) singleton check
-    let max_subcomm = node_to_subcommunity.iter().max().copied().unwrap_or(0);
-    let mut subcommunity_sizes = vec![0usize; max_subcomm + 1];
+    let mut subcommunities = Subcommunities {
+        sizes: vec![0usize; next_subcommunity_id.max(n)],
+        budget,
+        ..Subcommunities::default()
+    };
     for &sc in node_to_subcommunity.iter() {
-        subcommunity_sizes[sc] += 1;
+        subcommunities.sizes[sc] += 1;
     }
 
-    let twice_total_weight = graph.total_weight() * 2.0;
-    let mut subcommunity_degrees: HashMap<usize, f64> = HashMap::new();
     let mut node_degrees = vec![0.0; n];
+    let mut node_masses = vec![Mass::default(); n];
     for i in 0..n {
-        let d_i: f64 = graph.neighbors(i).map(|(_, w)| w).sum();
-        node_degrees[i] = d_i;
-        *subcommunity_degrees
+        let (out_strength, in_strength) = graph.arc_strengths(i);
+        node_degrees[i] = out_strength + in_strength;
+        node_masses[i] = quality.node_mass(out_strength, in_strength, node_weights[i]);
+        *subcommunities
+            .masses
             .entry(node_to_subcommunity[i])
-            .or_insert(0.0) += d_i;
+            .or_default() += node_masses[i];
     }
+    let cost = MoveCost {
+        node_masses: &node_masses,
+        penalty,
+    };
 
     let mut refined_nodes_sorted: Vec<usize> = refined_nodes.iter_ones().collect();
-    refined_nodes_sorted.sort_by(|&a, &b| node_degrees[a].partial_cmp(&node_degrees[b]).unwrap());
+    refined_nodes_sorted
+        .sort_by(|&a, &b| node_degrees[a].total_cmp(&node_degrees[b]).then(a.cmp(&b)));
+
+    // One draw per refined vertex, taken up front in sorted order
+    let draws = random.map(|r| r.draws(refined_nodes_sorted.len()));
+    let random = random.zip(draws.as_deref());
 
-    if mode == crate::core::config::RunMode::Throughput {
+    if config.mode == RunMode::Throughput {
         crate::core::algorithm::throughput::inc_refinement_parallel(
             graph,
             &refined_nodes_sorted,
             node_to_community,
             node_to_subcommunity,
-            &mut subcommunity_degrees,
-            &subcommunity_sizes,
-            &node_degrees,
-            twice_total_weight,
-            resolution_parameter,
+            &mut subcommunities,
+            cost,
+            random,
+        );
+        return refined_nodes;
+    }
+
+    if config.mode == RunMode::DeterministicParallel {
+        crate::core::algorithm::deterministic_parallel::inc_refinement_parallel(
+            graph,
+            &refined_nodes_sorted,
+            node_to_community,
+            node_to_subcommunity,
+            &mut subcommunities,
+            cost,
+            random,
         );
         return refined_nodes;
     }
 
     // 5 for v_i \in R do (deterministic refinement merging)
-    for &current_node in &refined_nodes_sorted {
+    for (position, &current_node) in refined_nodes_sorted.iter().enumerate() {
         // O(1) singleton check
-        let is_singleton = subcommunity_sizes[node_to_subcommunity[current_node]] == 1;
+        let is_singleton = subcommunities.sizes[node_to_subcommunity[current_node]] == 1;
 
         if is_singleton {
-            let mut neighbor_subcommunities: HashMap<usize, f64> = HashMap::new();
+            let mut neighbor_subcommunities: BTreeMap<usize, f64> = BTreeMap::new();
             let mut weight_to_current_subcommunity = 0.0;
-            let current_node_degree = node_degrees[current_node];
 
             for (neighbor_node, w) in graph.neighbors(current_node) {
+                if neighbor_node == current_node {
+                    continue;
+                }
                 if node_to_community[neighbor_node] == node_to_community[current_node] {
                     let neighbor_subcommunity = node_to_subcommunity[neighbor_node];
                     *neighbor_subcommunities
@@ -436,47 +1091,57 @@ fn inc_refinement(
                 }
             }
 
-            let mut best_subcommunity = node_to_subcommunity[current_node];
-            let mut best_modularity_gain = 0.0;
+            let mut best: Option<(f64, usize)> = None;
+            // Randomised merging may also keep the vertex where it is
+            let mut candidates = vec![(node_to_subcommunity[current_node], 0.0)];
 
             for (&candidate_subcommunity, &weight_to_candidate_subcommunity) in
                 &neighbor_subcommunities
             {
-                if candidate_subcommunity == node_to_subcommunity[current_node] {
+                if candidate_subcommunity == node_to_subcommunity[current_node]
+                    || !subcommunities.admits(current_node, candidate_subcommunity)
+                {
                     continue;
                 }
 
-                let current_subcommunity_degree = *subcommunity_degrees
-                    .get(&node_to_subcommunity[current_node])
-                    .unwrap_or(&0.0);
-                let candidate_subcommunity_degree = *subcommunity_degrees
-                    .get(&candidate_subcommunity)
-                    .unwrap_or(&0.0);
-
-                let modularity_gain = (weight_to_candidate_subcommunity
-                    - weight_to_current_subcommunity)
-                    / twice_total_weight
-                    + resolution_parameter
-                        * current_node_degree
-                        * (current_subcommunity_degree
-                            - current_node_degree
-                            - candidate_subcommunity_degree)
-                        / (twice_total_weight * twice_total_weight);
-
-                if modularity_gain > best_modularity_gain {
-                    best_modularity_gain = modularity_gain;
-                    best_subcommunity = candidate_subcommunity;
+                let gain = cost.gain(
+                    current_node,
+                    weight_to_candidate_subcommunity,
+                    weight_to_current_subcommunity,
+                    subcommunities.mass(node_to_subcommunity[current_node]),
+                    subcommunities.mass(candidate_subcommunity),
+                );
+
+                if random.is_some() && gain >= 0.0 {
+                    candidates.push((candidate_subcommunity, gain));
+                }
+                if gain > 0.0 {
+                    best = Some(match best {
+                        None => (gain, candidate_subcommunity),
+                        Some((best_gain, best_subcommunity)) => tie_break_gain(
+                            best_gain,
+                            best_subcommunity,
+                            gain,
+                            candidate_subcommunity,
+                        ),
+                    });
                 }
             }
 
-            if best_modularity_gain > 0.0 {
+            let chosen = match random {
+                Some((random, draws)) => Some(random.choose(&mut candidates, draws[position]))
+                    .filter(|&c| c != node_to_subcommunity[current_node]),
+                None => best.map(|(_, c)| c),
+            };
+            if let Some(best_subcommunity) = chosen {
                 let old_subcommunity = node_to_subcommunity[current_node];
                 node_to_subcommunity[current_node] = best_subcommunity;
-                subcommunity_sizes[old_subcommunity] -= 1;
-                subcommunity_sizes[best_subcommunity] += 1;
-                *subcommunity_degrees.entry(old_subcommunity).or_insert(0.0) -= current_node_degree;
-                *subcommunity_degrees.entry(best_subcommunity).or_insert(0.0) +=
-                    current_node_degree;
+                subcommunities.transfer(
+                    current_node,
+                    old_subcommunity,
+                    best_subcommunity,
+                    node_masses[current_node],
+                );
             }
         }
     }
@@ -484,82 +1149,107 @@ fn inc_refinement(
     refined_nodes
 }
 
+/// Computes ΔG_{p+1} and advances s_p^{pre} to s_p^{cur} for the vertices that
+/// changed sub-community. `next_node_count` is the size of level p+1.
 fn inc_aggregation(
     graph: &crate::core::graph::in_memory::InMemoryGraph,
     delta_graph: &GraphInput,
-    previous_node_to_subcommunity: &[usize],
+    previous_node_to_subcommunity: &mut [usize],
     current_node_to_subcommunity: &[usize],
     refined_nodes: &BitVec,
-) -> (GraphInput, Vec<usize>) {
+    next_node_count: usize,
+) -> GraphInput {
     let mut delta_supergraph = Vec::new();
-    // Mutate in-place instead of to_vec() — start from previous, update refined nodes
-    let mut next_previous_node_to_subcommunity = previous_node_to_subcommunity.to_vec();
 
     // 2 for (v_i, v_j, \alpha) \in \Delta G do
     for &(u, v, w) in &delta_graph.edges {
         let alpha = w.unwrap_or(1.0);
         let subcommunity_u = previous_node_to_subcommunity[u];
         let subcommunity_v = previous_node_to_subcommunity[v];
-        delta_supergraph.push((subcommunity_u, subcommunity_v, Some(alpha)));
+        delta_supergraph.push((subcommunity_u, subcommunity_v, alpha));
     }
 
+    let moved = |v: usize| previous_node_to_subcommunity[v] != current_node_to_subcommunity[v];
+
     // 5 for v_i \in R do
-    for current_node in refined_nodes.iter_ones() {
-        for (neighbor_node, w) in graph.neighbors(current_node) {
-            if current_node_to_subcommunity[neighbor_node]
-                == previous_node_to_subcommunity[neighbor_node]
-                || current_node < neighbor_node
-            {
+    for current_node in refined_nodes.iter_ones().filter(|&v| moved(v)) {
+        if graph.is_directed() {
+            // Each arc once: from its moved endpoint, or the smaller one if both moved
+            let handled_here = |other: usize| !moved(other) || current_node < other;
+            let arcs = graph
+                .out_neighbors(current_node)
+                .filter(|&(v, _)| v == current_node || handled_here(v))
+                .map(|(v, w)| (current_node, v, w))
+                .chain(
+                    graph
+                        .in_neighbors(current_node)
+                        .filter(|&(u, _)| u != current_node && handled_here(u))
+                        .map(|(u, w)| (u, current_node, w)),
+                );
+            for (u, v, w) in arcs {
                 delta_supergraph.push((
-                    previous_node_to_subcommunity[current_node],
-                    previous_node_to_subcommunity[neighbor_node],
-                    Some(-w),
+                    previous_node_to_subcommunity[u],
+                    previous_node_to_subcommunity[v],
+                    -w,
                 ));
                 delta_supergraph.push((
-                    current_node_to_subcommunity[current_node],
-                    current_node_to_subcommunity[neighbor_node],
-                    Some(w),
+                    current_node_to_subcommunity[u],
+                    current_node_to_subcommunity[v],
+                    w,
                 ));
             }
+            continue;
+        }
+        for (neighbor_node, w) in graph.neighbors(current_node) {
+            // Edges between two moved vertices are handled once, from the smaller endpoint.
+            // Self-loops are stored twice in the owning vertex's adjacency.
+            let weight = if neighbor_node == current_node {
+                w / 2.0
+            } else if !moved(neighbor_node) || current_node < neighbor_node {
+                w
+            } else {
+                continue;
+            };
+            delta_supergraph.push((
+                previous_node_to_subcommunity[current_node],
+                previous_node_to_subcommunity[neighbor_node],
+                -weight,
+            ));
+            delta_supergraph.push((
+                current_node_to_subcommunity[current_node],
+                current_node_to_subcommunity[neighbor_node],
+                weight,
+            ));
         }
     }
 
     // 12 for v_i \in R do
     for current_node in refined_nodes.iter_ones() {
-        next_previous_node_to_subcommunity[current_node] =
-            current_node_to_subcommunity[current_node];
+        previous_node_to_subcommunity[current_node] = current_node_to_subcommunity[current_node];
     }
 
-    // 14 Compress(\Delta H) — use HashMap instead of BTreeMap
-    let mut compressed_supergraph: HashMap<(usize, usize), f64> = HashMap::new();
-    for (u, v, w) in delta_supergraph {
-        let weight = w.unwrap_or(1.0);
-        let (min_u, max_v) = if u <= v { (u, v) } else { (v, u) };
+    // 14 Compress(\Delta H)
+    let mut compressed_supergraph: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+    for (u, v, weight) in delta_supergraph {
+        let (min_u, max_v) = if u <= v || graph.is_directed() {
+            (u, v)
+        } else {
+            (v, u)
+        };
         *compressed_supergraph.entry((min_u, max_v)).or_insert(0.0) += weight;
     }
 
-    let mut final_delta_supergraph = Vec::new();
-    for ((u, v), w) in compressed_supergraph {
-        if w.abs() > 1e-9 {
-            final_delta_supergraph.push((u, v, Some(w)));
-        }
-    }
-
-    let max_subcommunity = current_node_to_subcommunity
-        .iter()
-        .chain(previous_node_to_subcommunity.iter())
-        .copied()
-        .max()
-        .unwrap_or(0);
-    let next_node_count = max_subcommunity + 1;
+    let final_delta_supergraph = compressed_supergraph
+        .into_iter()
+        .filter(|&(_, w)| w.abs() > 1e-9)
+        .map(|((u, v), w)| (u, v, Some(w)))
+        .collect();
 
-    let next_delta_graph = GraphInput {
+    GraphInput {
         dataset_id: delta_graph.dataset_id.clone(),
         node_count: next_node_count,
         edges: final_delta_supergraph,
-    };
-
-    (next_delta_graph, next_previous_node_to_subcommunity)
+    }
 }
 
 fn def_update(
@@ -583,14 +1273,15 @@ fn def_update(
         // 5 if p \neq 1 then
         if p > 0 {
             // 6 for v_i^p \in B_p do
-            let changed_nodes_at_p: Vec<usize> = changed_nodes_per_level[p].iter_ones().collect();
-            for current_node in changed_nodes_at_p {
-                // 7 B_{p-1}.add(s_p^{-1}(v_i^p))
+            // 7 B_{p-1}.add(s_p^{-1}(v_i^p)) — one pass over level p-1 instead of per-vertex scans
+            let (lower, upper) = changed_nodes_per_level.split_at_mut(p);
+            let changed_at_p = &upper[0];
+            if changed_at_p.any() {
                 for (previous_level_node, &subcommunity_value) in
                     node_to_subcommunity_per_level[p - 1].iter().enumerate()
                 {
-                    if subcommunity_value == current_node {
-                        changed_nodes_per_level[p - 1].set(previous_level_node, true);
+                    if changed_at_p[subcommunity_value] {
+                        lower[p - 1].set(previous_level_node, true);
                     }
                 }
             }
@@ -1,4 +1,10 @@
+pub mod communities;
 pub mod deterministic;
+pub mod deterministic_parallel;
 pub mod hit_leiden;
 pub mod parallel_frontier;
+pub mod quality;
+pub mod randomized;
+pub mod size_limit;
+pub mod subcommunities;
 pub mod throughput;
@@ -1,3 +1,5 @@
+use crate::core::algorithm::communities::Communities;
+use crate::core::algorithm::quality::MoveCost;
 use crate::core::graph::in_memory::InMemoryGraph;
 use bitvec::prelude::*;
 use smallvec::SmallVec;
@@ -86,18 +88,18 @@ impl SharedBitVec {
     }
 }
 
-/// Per-shard results that must be applied sequentially after all threads join.
+/// Per-shard move proposals that must be committed sequentially after all threads join.
 pub struct ShardResult {
     pub node_to_community_updates: Vec<(usize, usize)>,
-    pub community_degree_updates: Vec<(usize, f64)>,
 }
 
 /// Execute one shard of the incremental movement step.
 ///
-/// Bits for `changed_nodes`, `affected_nodes`, and `next_active_nodes` are
-/// written directly into shared atomic bitvecs (zero-copy merge).
-/// Only the community-assignment and degree-delta updates are returned for
-/// sequential application.
+/// Every vertex in the shard is evaluated against the same snapshot of
+/// `node_to_community`, so the returned moves are only proposals: two
+/// neighbouring vertices may both want to join each other's community. The
+/// caller re-checks each proposal against the live assignment before applying it.
+/// Under a size limit only communities with room for the vertex are proposed.
 ///
 /// `neighbor_weight_buf` and `dirty_communities` are thread-local scratch
 /// buffers reused across all nodes in the shard to avoid per-node allocation.
@@ -105,35 +107,30 @@ pub fn execute_shard(
     graph: &InMemoryGraph,
     shard: &[usize],
     node_to_community: &[usize],
-    node_to_subcommunity: &[usize],
-    community_degrees: &[f64],
-    node_degrees: &[f64],
-    twice_total_weight: f64,
-    resolution_parameter: f64,
-    changed_nodes: &SharedBitVec,
-    affected_nodes: &SharedBitVec,
-    next_active_nodes: &SharedBitVec,
+    communities: &Communities,
+    cost: MoveCost,
     neighbor_weight_buf: &mut [f64],
     dirty_communities: &mut Vec<usize>,
 ) -> ShardResult {
-    // Pre-allocate result Vecs to avoid growth reallocations during execution.
+    // Pre-allocate to avoid growth reallocations during execution.
     // Estimate ~15% of nodes will change community (conservative for modularity optimization).
-    // community_degree_updates needs 2x capacity (old and new community per change).
     let estimated_changes = (shard.len() * 15) / 100;
     let mut result = ShardResult {
         node_to_community_updates: Vec::with_capacity(estimated_changes),
-        community_degree_updates: Vec::with_capacity(estimated_changes * 2),
     };
 
     for &current_node in shard {
         let current_community = node_to_community[current_node];
-        let current_node_degree = node_degrees[current_node];
         let mut best_community = current_community;
-        let mut best_modularity_gain = 0.0;
+        let mut best_gain = 0.0;
         let mut weight_to_current_community = 0.0;
 
         // Accumulate neighbor weights by community in flat buffer (O(degree))
         for (neighbor_node, w) in graph.neighbors(current_node) {
+            // A self-loop moves with the vertex, so it never favours staying put
+            if neighbor_node == current_node {
+                continue;
+            }
             let c = node_to_community[neighbor_node];
             if neighbor_weight_buf[c] == 0.0 {
                 dirty_communities.push(c);
@@ -146,23 +143,23 @@ pub fn execute_shard(
 
         // Evaluate each neighbor community
         for &candidate_community in dirty_communities.iter() {
-            if candidate_community == current_community {
+            if candidate_community == current_community
+                || !communities.admits(current_node, candidate_community)
+            {
                 continue;
             }
 
             let weight_to_candidate = neighbor_weight_buf[candidate_community];
-            let current_community_degree = community_degrees[current_community];
-            let candidate_community_degree = community_degrees[candidate_community];
-
-            let modularity_gain = (weight_to_candidate - weight_to_current_community)
-                / twice_total_weight
-                + resolution_parameter
-                    * current_node_degree
-                    * (current_community_degree - current_node_degree - candidate_community_degree)
-                    / (twice_total_weight * twice_total_weight);
-
-            if modularity_gain > best_modularity_gain {
-                best_modularity_gain = modularity_gain;
+            let gain = cost.gain(
+                current_node,
+                weight_to_candidate,
+                weight_to_current_community,
+                communities.masses[current_community],
+                communities.masses[candidate_community],
+            );
+
+            if gain > best_gain {
+                best_gain = gain;
                 best_community = candidate_community;
             }
         }
@@ -174,32 +171,12 @@ pub fn execute_shard(
         dirty_communities.clear();
 
         // Record move if beneficial
-        if best_modularity_gain > 0.0 {
+        if best_gain > 0.0 {
             result
                 .node_to_community_updates
                 .push((current_node, best_community));
-            result
-                .community_degree_updates
-                .push((current_community, -current_node_degree));
-            result
-                .community_degree_updates
-                .push((best_community, current_node_degree));
-
-            // Write directly to shared bitvecs (atomic OR, no per-shard alloc)
-            changed_nodes.set(current_node);
-
-            for (neighbor_node, _w) in graph.neighbors(current_node) {
-                if node_to_community[neighbor_node] != best_community {
-                    next_active_nodes.set(neighbor_node);
-                }
-                if node_to_subcommunity[current_node] == node_to_subcommunity[neighbor_node] {
-                    affected_nodes.set(current_node);
-                    affected_nodes.set(neighbor_node);
-                }
-            }
         }
     }
 
     result
 }
-
@@ -0,0 +1,444 @@
+use crate::core::graph::in_memory::InMemoryGraph;
+use bitvec::prelude::*;
+use std::collections::BTreeSet;
+use std::ops::{Add, AddAssign, Sub, SubAssign};
+
+/// Objective optimised by every phase of HIT-Leiden.
+///
+/// All supported objectives share the shape `Σ_c in_c − γ·penalty(A_c)`, where
+/// `in_c` is the internal edge weight of community `c` and `A_c` the summed
+/// *mass* of its members, so moving a vertex of mass `a` from community C to
+/// community D changes the objective in proportion to
+///
+/// `(w_D − w_C) − γ·s·a·(A_D − A_C + a)`
+///
+/// where `w_X` is the vertex's edge weight into X (self-loops excluded), `A_C`
+/// still includes the vertex and `s` is [`QualityFunction::penalty_scale`].
+/// On directed graphs masses have an outgoing and an incoming part and the
+/// products pair one with the other, as in Leicht–Newman modularity; see
+/// [`Mass`] and [`move_gain`].
+pub trait QualityFunction: std::fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Mass of a vertex with the given outgoing and incoming strength (see
+    /// [`InMemoryGraph::arc_strengths`]) and node weight.
+    fn node_mass(&self, out_strength: f64, in_strength: f64, node_weight: f64) -> Mass;
+
+    /// Factor converting mass products into edge-weight units on a graph with
+    /// total edge weight `total_weight`.
+    fn penalty_scale(&self, total_weight: f64) -> f64;
+
+    /// Value of the objective for a partition summarised by `totals`.
+    fn evaluate(&self, totals: &PartitionTotals, gamma: f64) -> f64;
+}
+
+/// Mass of a vertex or community, split into the part that pairs with
+/// incoming mass and the part that pairs with outgoing mass. Both parts are
+/// equal on undirected graphs and under CPM.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mass {
+    pub out: f64,
+    pub incoming: f64,
+}
+
+impl Mass {
+    /// A mass whose two parts are both `value`.
+    pub fn symmetric(value: f64) -> Self {
+        Self {
+            out: value,
+            incoming: value,
+        }
+    }
+
+    /// `(a.out·b.in + a.in·b.out) / 2`, which is `a·b` for symmetric masses.
+    #[inline]
+    pub fn product(self, other: Self) -> f64 {
+        (self.out * other.incoming + self.incoming * other.out) / 2.0
+    }
+}
+
+impl Add for Mass {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            out: self.out + other.out,
+            incoming: self.incoming + other.incoming,
+        }
+    }
+}
+
+impl Sub for Mass {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            out: self.out - other.out,
+            incoming: self.incoming - other.incoming,
+        }
+    }
+}
+
+impl AddAssign for Mass {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Mass {
+    fn sub_assign(&mut self, other: Self) {
+        *self = *self - other;
+    }
+}
+
+/// Whole-partition sums an objective is evaluated from.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PartitionTotals {
+    pub total_weight: f64,
+    /// Σ_c in_c
+    pub internal: f64,
+    /// Σ_c 4·K_c^out·K_c^in over the outgoing and incoming strength of each
+    /// community, which is Σ_c (Σ_{v∈c} strength_v)² on undirected graphs
+    pub strength_sq: f64,
+    /// Σ_c (Σ_{v∈c} weight_v)²
+    pub node_weight_sq: f64,
+    /// Σ_v weight_v
+    pub node_weight: f64,
+}
+
+/// Newman–Girvan modularity; the mass of a vertex is its strength. On
+/// directed graphs this is Leicht–Newman modularity,
+/// `Σ_c in_c / m − γ·Σ_c K_c^out·K_c^in / m²`, and the two parts of a vertex's
+/// mass are twice its outgoing and incoming strength.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modularity;
+
+impl QualityFunction for Modularity {
+    fn name(&self) -> &'static str {
+        "modularity"
+    }
+
+    fn node_mass(&self, out_strength: f64, in_strength: f64, _node_weight: f64) -> Mass {
+        Mass {
+            out: 2.0 * out_strength,
+            incoming: 2.0 * in_strength,
+        }
+    }
+
+    fn penalty_scale(&self, total_weight: f64) -> f64 {
+        if total_weight > 0.0 {
+            1.0 / (2.0 * total_weight)
+        } else {
+            0.0
+        }
+    }
+
+    fn evaluate(&self, totals: &PartitionTotals, gamma: f64) -> f64 {
+        let m = totals.total_weight;
+        if m <= 0.0 {
+            return 0.0;
+        }
+        totals.internal / m - gamma * totals.strength_sq / (4.0 * m * m)
+    }
+}
+
+/// Constant Potts Model, `Σ_c in_c − γ·A_c(A_c − 1)/2`; the mass of a vertex
+/// is its node weight (1 for every level-0 node unless weights are given).
+/// Edge direction does not enter the objective.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConstantPotts;
+
+impl QualityFunction for ConstantPotts {
+    fn name(&self) -> &'static str {
+        "cpm"
+    }
+
+    fn node_mass(&self, _out_strength: f64, _in_strength: f64, node_weight: f64) -> Mass {
+        Mass::symmetric(node_weight)
+    }
+
+    fn penalty_scale(&self, _total_weight: f64) -> f64 {
+        1.0
+    }
+
+    fn evaluate(&self, totals: &PartitionTotals, gamma: f64) -> f64 {
+        totals.internal - gamma * (totals.node_weight_sq - totals.node_weight) / 2.0
+    }
+}
+
+/// Selects the [`QualityFunction`] used by a run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QualityKind {
+    #[default]
+    Modularity,
+    Cpm,
+}
+
+impl QualityKind {
+    pub fn function(self) -> &'static dyn QualityFunction {
+        match self {
+            QualityKind::Modularity => &Modularity,
+            QualityKind::Cpm => &ConstantPotts,
+        }
+    }
+}
+
+/// Change of the objective (in edge-weight units) from moving a vertex of mass
+/// `node_mass` out of a community of mass `current_mass` (which includes it)
+/// into one of mass `candidate_mass`. `penalty` is `γ·penalty_scale`.
+/// Masses multiply by [`Mass::product`].
+#[inline]
+pub fn move_gain(
+    weight_to_candidate: f64,
+    weight_to_current: f64,
+    node_mass: Mass,
+    current_mass: Mass,
+    candidate_mass: Mass,
+    penalty: f64,
+) -> f64 {
+    weight_to_candidate
+        - weight_to_current
+        - penalty * node_mass.product(candidate_mass - current_mass + node_mass)
+}
+
+/// What moves are scored with at one level: the mass of every vertex and the
+/// penalty `γ·penalty_scale`.
+#[derive(Clone, Copy, Debug)]
+pub struct MoveCost<'a> {
+    pub node_masses: &'a [Mass],
+    pub penalty: f64,
+}
+
+impl MoveCost<'_> {
+    /// [`move_gain`] of moving `node` out of a community of mass
+    /// `current_mass` into one of mass `candidate_mass`.
+    #[inline]
+    pub fn gain(
+        &self,
+        node: usize,
+        weight_to_candidate: f64,
+        weight_to_current: f64,
+        current_mass: Mass,
+        candidate_mass: Mass,
+    ) -> f64 {
+        move_gain(
+            weight_to_candidate,
+            weight_to_current,
+            self.node_masses[node],
+            current_mass,
+            candidate_mass,
+            self.penalty,
+        )
+    }
+}
+
+/// Per-community totals of the level-0 partition, kept so that the objective
+/// and the community count can be refreshed after an incremental update by
+/// revisiting only the communities it touched.
+///
+/// Community labels are level-0 node ids, so every array is indexed by label
+/// and sized to the node count.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommunityStats {
+    /// Label of every node as of the last refresh.
+    labels: Vec<usize>,
+    /// Every node carrying the label, removed ones included.
+    nodes: Vec<Vec<usize>>,
+    /// Position of each node in `nodes[labels[v]]`.
+    slots: Vec<usize>,
+    /// Sum of member strengths (Σ_tot), self-loops counted twice.
+    strength: Vec<f64>,
+    /// Part of `strength` on arcs leaving the members; directed graphs only.
+    out_strength: Vec<f64>,
+    /// Weight of edges with both endpoints inside (Σ_in), each edge once.
+    internal: Vec<f64>,
+    /// Sum of member node weights.
+    node_weight: Vec<f64>,
+    /// Live (non-removed) members.
+    members: Vec<usize>,
+    community_count: usize,
+    sum_internal: f64,
+    sum_strength_sq: f64,
+    sum_node_weight_sq: f64,
+    sum_node_weight: f64,
+}
+
+impl CommunityStats {
+    /// Compute the totals of `node_to_comm` on `graph` from scratch in O(n + m).
+    pub fn from_partition(
+        graph: &InMemoryGraph,
+        node_to_comm: &[usize],
+        node_weights: &[f64],
+        removed: &BitVec,
+    ) -> Self {
+        let mut stats = Self::default();
+        stats.relabel(node_to_comm, 0..node_to_comm.len());
+        stats.refresh(
+            graph,
+            node_to_comm,
+            node_weights,
+            removed,
+            0..node_to_comm.len(),
+        );
+        stats
+    }
+
+    /// Bring the totals up to date after `node_to_comm` and `graph` changed.
+    ///
+    /// `touched` must hold every node that changed label, weight or removal
+    /// status, and both endpoints of every changed edge; nodes appended since
+    /// the last update are always included. Only the communities those nodes
+    /// left or are in are recomputed, in time proportional to their volume.
+    pub fn update(
+        &mut self,
+        graph: &InMemoryGraph,
+        node_to_comm: &[usize],
+        node_weights: &[f64],
+        removed: &BitVec,
+        touched: &BitVec,
+    ) {
+        let previous_n = self.labels.len();
+        let nodes: BTreeSet<usize> = touched
+            .iter_ones()
+            .chain(previous_n..node_to_comm.len())
+            .collect();
+        let mut selected: BTreeSet<usize> = nodes
+            .iter()
+            .filter(|&&v| v < previous_n)
+            .map(|&v| self.labels[v])
+            .collect();
+        selected.extend(nodes.iter().map(|&v| node_to_comm[v]));
+        self.relabel(node_to_comm, nodes.into_iter());
+        self.refresh(graph, node_to_comm, node_weights, removed, selected);
+    }
+
+    /// Number of communities with at least one live member.
+    pub fn community_count(&self) -> usize {
+        self.community_count
+    }
+
+    pub fn totals(&self, total_weight: f64) -> PartitionTotals {
+        PartitionTotals {
+            total_weight,
+            internal: self.sum_internal,
+            strength_sq: self.sum_strength_sq,
+            node_weight_sq: self.sum_node_weight_sq,
+            node_weight: self.sum_node_weight,
+        }
+    }
+
+    /// Value of `quality` with resolution `gamma`.
+    pub fn quality(&self, quality: &dyn QualityFunction, total_weight: f64, gamma: f64) -> f64 {
+        quality.evaluate(&self.totals(total_weight), gamma)
+    }
+
+    /// Move each of `nodes` into the member list of its label in `node_to_comm`.
+    fn relabel(&mut self, node_to_comm: &[usize], nodes: impl Iterator<Item = usize>) {
+        self.nodes.resize_with(node_to_comm.len(), Vec::new);
+        for v in nodes {
+            let label = node_to_comm[v];
+            if v < self.labels.len() {
+                let old = self.labels[v];
+                if old == label {
+                    continue;
+                }
+                let slot = self.slots[v];
+                self.nodes[old].swap_remove(slot);
+                if let Some(&moved) = self.nodes[old].get(slot) {
+                    self.slots[moved] = slot;
+                }
+                self.labels[v] = label;
+                self.slots[v] = self.nodes[label].len();
+            } else {
+                // Appended nodes arrive in id order
+                self.labels.push(label);
+                self.slots.push(self.nodes[label].len());
+            }
+            self.nodes[label].push(v);
+        }
+    }
+
+    /// Recompute the communities in `labels` from their member lists.
+    fn refresh(
+        &mut self,
+        graph: &InMemoryGraph,
+        node_to_comm: &[usize],
+        node_weights: &[f64],
+        removed: &BitVec,
+        labels: impl IntoIterator<Item = usize>,
+    ) {
+        let n = node_to_comm.len();
+        for array in [
+            &mut self.strength,
+            &mut self.out_strength,
+            &mut self.internal,
+            &mut self.node_weight,
+        ] {
+            array.resize(n, 0.0);
+        }
+        self.members.resize(n, 0);
+        // 4·K^out·K^in, which is the squared strength when undirected
+        let directed = graph.is_directed();
+        let strength_sq = |strength: f64, out: f64| {
+            if directed {
+                4.0 * out * (strength - out)
+            } else {
+                strength * strength
+            }
+        };
+
+        for label in labels {
+            self.sum_internal -= self.internal[label];
+            self.sum_strength_sq -= strength_sq(self.strength[label], self.out_strength[label]);
+            self.sum_node_weight_sq -= self.node_weight[label] * self.node_weight[label];
+            self.sum_node_weight -= self.node_weight[label];
+            if self.members[label] > 0 {
+                self.community_count -= 1;
+            }
+
+            let (mut strength, mut out_strength, mut internal, mut node_weight) =
+                (0.0, 0.0, 0.0, 0.0);
+            let mut members = 0;
+            for &v in &self.nodes[label] {
+                if !removed[v] {
+                    members += 1;
+                }
+                node_weight += node_weights[v];
+                if directed {
+                    out_strength += graph.arc_strengths(v).0;
+                }
+                for (neighbor, w) in graph.neighbors(v) {
+                    strength += w;
+                    if node_to_comm[neighbor] == label {
+                        // Seen from both endpoints; self-loops are stored twice
+                        internal += w / 2.0;
+                    }
+                }
+            }
+            self.strength[label] = strength;
+            self.out_strength[label] = out_strength;
+            self.internal[label] = internal;
+            self.node_weight[label] = node_weight;
+            self.members[label] = members;
+
+            self.sum_internal += internal;
+            self.sum_strength_sq += strength_sq(strength, out_strength);
+            self.sum_node_weight_sq += node_weight * node_weight;
+            self.sum_node_weight += node_weight;
+            if members > 0 {
+                self.community_count += 1;
+            }
+        }
+    }
+}
+
+/// Newman–Girvan modularity of `node_to_comm` on `graph`, computed from
+/// scratch; Leicht–Newman modularity if `graph` is directed.
+pub fn modularity(graph: &InMemoryGraph, node_to_comm: &[usize], gamma: f64) -> f64 {
+    let n = node_to_comm.len();
+    CommunityStats::from_partition(graph, node_to_comm, &vec![1.0; n], &bitvec![0; n]).quality(
+        &Modularity,
+        graph.total_weight(),
+        gamma,
+    )
+}
@@ -0,0 +1,79 @@
+use crate::core::config::RunConfig;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Leiden's randomised refinement merge: a singleton joins one of the
+/// sub-communities it does not make worse (staying put included) with
+/// probability proportional to `exp(gain / theta)`, instead of always taking
+/// the best one.
+///
+/// Draws come from a [`StdRng`] seeded from the run seed, a stream number
+/// (one per pass or incremental update) and the level, and are taken once per
+/// refinement in a fixed vertex order before any merging, so the outcome for a
+/// given seed does not depend on the run mode's scheduling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RandomMerge {
+    seed: u64,
+    theta: f64,
+}
+
+impl RandomMerge {
+    /// `None` unless `config.seed` is set, in which case refinement is greedy.
+    pub fn from_config(config: &RunConfig) -> Option<Self> {
+        config.seed.map(|seed| Self {
+            seed,
+            theta: config.randomness,
+        })
+    }
+
+    /// Independent draws for stream `stream` (e.g. a pass or update number).
+    pub fn for_stream(self, stream: u64) -> Self {
+        Self {
+            seed: mix(self.seed ^ mix(stream)),
+            ..self
+        }
+    }
+
+    /// Independent draws for hierarchy level `level`.
+    pub fn for_level(self, level: usize) -> Self {
+        self.for_stream(level as u64 ^ 0x6c65_7665_6c00_0000)
+    }
+
+    /// `count` uniform draws in `[0, 1)`, one per vertex to refine.
+    pub fn draws(&self, count: usize) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        (0..count).map(|_| rng.gen::<f64>()).collect()
+    }
+
+    /// Pick a candidate `(sub-community, gain)` by `exp(gain / theta)` using
+    /// the uniform `draw`. Candidates are ordered by id first, so the result
+    /// does not depend on how they were collected. Gains must be non-negative
+    /// and `candidates` non-empty.
+    pub fn choose(&self, candidates: &mut [(usize, f64)], draw: f64) -> usize {
+        candidates.sort_by_key(|&(id, _)| id);
+        let best = candidates
+            .iter()
+            .map(|&(_, gain)| gain)
+            .fold(f64::NEG_INFINITY, f64::max);
+        // Shift by the best gain so the largest weight is 1 and nothing overflows
+        let weight = |gain: f64| ((gain - best) / self.theta).exp();
+        let total: f64 = candidates.iter().map(|&(_, gain)| weight(gain)).sum();
+        let mut target = draw * total;
+        for &(id, gain) in candidates.iter() {
+            let w = weight(gain);
+            if target < w {
+                return id;
+            }
+            target -= w;
+        }
+        candidates[candidates.len() - 1].0
+    }
+}
+
+/// SplitMix64 finaliser, used to decorrelate derived seeds.
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
@@ -0,0 +1,72 @@
+use crate::core::config::CommunitySizeLimit;
+use crate::core::partition::state::PartitionState;
+
+/// Running size of every community or sub-community under a [`CommunitySizeLimit`].
+///
+/// A level-0 node costs one under a node-count limit and its weight under a
+/// weight limit; a supervertex costs the total of the live nodes it holds.
+/// Movement and refinement only let a vertex join a group it still fits in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SizeBudget {
+    limit: f64,
+    node_costs: Vec<f64>,
+    group_costs: Vec<f64>,
+}
+
+impl SizeBudget {
+    /// Budget for the groups in `node_to_group`, whose ids are below
+    /// `capacity`, with `node_costs` from [`vertex_costs`].
+    pub fn new(
+        limit: CommunitySizeLimit,
+        node_costs: &[f64],
+        node_to_group: &[usize],
+        capacity: usize,
+    ) -> Self {
+        let limit = match limit {
+            CommunitySizeLimit::Nodes(count) => count as f64,
+            CommunitySizeLimit::Weight(weight) => weight,
+        };
+        let mut group_costs = vec![0.0; capacity];
+        for (node, &group) in node_to_group.iter().enumerate() {
+            group_costs[group] += node_costs[node];
+        }
+        Self {
+            limit,
+            node_costs: node_costs.to_vec(),
+            group_costs,
+        }
+    }
+
+    /// Whether `node` fits in `group` on top of its current members.
+    pub fn admits(&self, node: usize, group: usize) -> bool {
+        self.group_costs[group] + self.node_costs[node] <= self.limit
+    }
+
+    pub fn is_over(&self, group: usize) -> bool {
+        self.group_costs[group] > self.limit
+    }
+
+    /// Record that `node` moved from group `from` to `to`.
+    pub fn transfer(&mut self, node: usize, from: usize, to: usize) {
+        self.group_costs[from] -= self.node_costs[node];
+        self.group_costs[to] += self.node_costs[node];
+    }
+}
+
+/// Cost under `limit` of every vertex at level `p` of `state`: the number of
+/// live level-0 nodes it holds, or their total weight.
+pub fn vertex_costs(state: &PartitionState, p: usize, limit: CommunitySizeLimit) -> Vec<f64> {
+    match limit {
+        CommunitySizeLimit::Weight(_) => state.node_weights_per_level[p].clone(),
+        CommunitySizeLimit::Nodes(_) => {
+            let mut costs = vec![0.0; state.node_weights_per_level[p].len()];
+            for v in (0..state.node_to_comm.len()).filter(|&v| !state.removed_nodes[v]) {
+                let vertex = state.current_subcommunity_mapping_per_level[..p]
+                    .iter()
+                    .fold(v, |vertex, mapping| mapping[vertex]);
+                costs[vertex] += 1.0;
+            }
+            costs
+        }
+    }
+}
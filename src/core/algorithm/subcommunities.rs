@@ -0,0 +1,37 @@
+use crate::core::algorithm::quality::Mass;
+use crate::core::algorithm::size_limit::SizeBudget;
+use std::collections::HashMap;
+
+/// Running totals of the sub-communities refinement merges singletons into:
+/// their mass, their member count and, under a size limit, their budget.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Subcommunities {
+    pub masses: HashMap<usize, Mass>,
+    /// Member count, indexed by sub-community id.
+    pub sizes: Vec<usize>,
+    pub budget: Option<SizeBudget>,
+}
+
+impl Subcommunities {
+    pub fn mass(&self, subcommunity: usize) -> Mass {
+        self.masses.get(&subcommunity).copied().unwrap_or_default()
+    }
+
+    /// Whether `node` fits in `subcommunity` under the size limit, if any.
+    pub fn admits(&self, node: usize, subcommunity: usize) -> bool {
+        self.budget
+            .as_ref()
+            .map_or(true, |budget| budget.admits(node, subcommunity))
+    }
+
+    /// Record that `node`, of mass `mass`, moved from sub-community `from` to `to`.
+    pub fn transfer(&mut self, node: usize, from: usize, to: usize, mass: Mass) {
+        self.sizes[from] -= 1;
+        self.sizes[to] += 1;
+        if let Some(budget) = self.budget.as_mut() {
+            budget.transfer(node, from, to);
+        }
+        *self.masses.entry(from).or_default() -= mass;
+        *self.masses.entry(to).or_default() += mass;
+    }
+}
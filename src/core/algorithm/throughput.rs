@@ -1,4 +1,8 @@
-use crate::core::algorithm::parallel_frontier::{execute_shard, ShardResult, SharedBitVec};
+use crate::core::algorithm::communities::Communities;
+use crate::core::algorithm::parallel_frontier::{execute_shard, ShardResult};
+use crate::core::algorithm::quality::MoveCost;
+use crate::core::algorithm::randomized::RandomMerge;
+use crate::core::algorithm::subcommunities::Subcommunities;
 use crate::core::graph::in_memory::InMemoryGraph;
 use bitvec::prelude::*;
 use rayon::prelude::*;
@@ -32,11 +36,12 @@ pub struct BufferPool {
 }
 
 impl BufferPool {
-    /// Create a new buffer pool for `num_threads` workers processing graphs with `node_count` nodes.
+    /// Create a new buffer pool for `num_threads` workers whose community labels are
+    /// below `community_capacity`.
     /// Pre-allocates full-size neighbor weight buffers to avoid growth reallocations.
-    pub fn new(node_count: usize, num_threads: usize) -> Self {
+    pub fn new(community_capacity: usize, num_threads: usize) -> Self {
         let buffers: Vec<_> = (0..num_threads)
-            .map(|_| SyncUnsafeCell::new((vec![0.0; node_count], Vec::with_capacity(4096))))
+            .map(|_| SyncUnsafeCell::new((vec![0.0; community_capacity], Vec::with_capacity(4096))))
             .collect();
         BufferPool { buffers }
     }
@@ -64,22 +69,14 @@ pub fn inc_movement_parallel(
     active_nodes: &BitVec,
     node_to_community: &mut Vec<usize>,
     node_to_subcommunity: &[usize],
-    community_degrees: &mut Vec<f64>,
-    node_degrees: &[f64],
-    twice_total_weight: f64,
-    resolution_parameter: f64,
+    communities: &mut Communities,
+    cost: MoveCost,
     buffer_pool: &BufferPool,
 ) -> (BitVec, BitVec, BitVec) {
     let active_nodes_vec: Vec<usize> = active_nodes.iter_ones().collect();
 
     let n = graph.node_count;
 
-    // Shared atomic bitvecs — rayon worker threads write directly via fetch_or.
-    // Rayon maintains a persistent thread pool so there is no spawn/join churn.
-    let shared_changed = SharedBitVec::new(n);
-    let shared_affected = SharedBitVec::new(n);
-    let shared_next_active = SharedBitVec::new(n);
-
     // Reset buffer pool for reuse (keeps allocations, clears data)
     buffer_pool.reset();
     let buffers = buffer_pool.as_ref();
@@ -87,7 +84,7 @@ pub fn inc_movement_parallel(
 
     // Create immutable views for parallel access
     let node_to_community_view: &[usize] = node_to_community;
-    let community_degrees_view: &[f64] = community_degrees;
+    let communities_view: &Communities = communities;
 
     // Pre-chunk work by thread count for load balancing
     let chunk_size = (active_nodes_vec.len() + num_threads - 1) / num_threads;
@@ -108,9 +105,6 @@ pub fn inc_movement_parallel(
         for (chunk_idx, chunk) in chunks.into_iter().enumerate() {
             // Capture references for borrowing in the move closure
             let buffers = &buffers;
-            let shared_changed = &shared_changed;
-            let shared_affected = &shared_affected;
-            let shared_next_active = &shared_next_active;
             let results = &results;
 
             s.spawn(move |_| {
@@ -124,14 +118,8 @@ pub fn inc_movement_parallel(
                     graph,
                     chunk,
                     node_to_community_view,
-                    node_to_subcommunity,
-                    community_degrees_view,
-                    node_degrees,
-                    twice_total_weight,
-                    resolution_parameter,
-                    shared_changed,
-                    shared_affected,
-                    shared_next_active,
+                    communities_view,
+                    cost,
                     neighbor_buf,
                     dirty_buf,
                 );
@@ -148,49 +136,99 @@ pub fn inc_movement_parallel(
         .map(|cell| unsafe { (*cell.get()).take().unwrap() })
         .collect();
 
-    // Apply sequential updates (nodes are disjoint across shards — no conflicts)
+    let mut changed_nodes = bitvec![0; n];
+    let mut affected_nodes = bitvec![0; n];
+    let mut next_active_nodes = bitvec![0; n];
+
+    // Commit proposals in shard order. Proposals were computed against a shared
+    // snapshot, so each one is re-checked against the live assignment; only moves
+    // that still improve the objective and fit the budget are applied, which
+    // guarantees termination.
     for result in results {
-        for (node, new_comm) in result.node_to_community_updates {
-            node_to_community[node] = new_comm;
-        }
-        for (comm, delta) in result.community_degree_updates {
-            community_degrees[comm] += delta;
+        for (node, proposed_community) in result.node_to_community_updates {
+            let current_community = node_to_community[node];
+            let mut weight_to_current_community = 0.0;
+            let mut weight_to_proposed_community = 0.0;
+            for (neighbor_node, w) in graph.neighbors(node) {
+                if neighbor_node == node {
+                    continue;
+                }
+                let c = node_to_community[neighbor_node];
+                if c == current_community {
+                    weight_to_current_community += w;
+                } else if c == proposed_community {
+                    weight_to_proposed_community += w;
+                }
+            }
+
+            let node_mass = cost.node_masses[node];
+            let gain = cost.gain(
+                node,
+                weight_to_proposed_community,
+                weight_to_current_community,
+                communities.masses[current_community],
+                communities.masses[proposed_community],
+            );
+            if proposed_community == current_community
+                || gain <= 0.0
+                || !communities.admits(node, proposed_community)
+            {
+                continue;
+            }
+
+            node_to_community[node] = proposed_community;
+            communities.transfer(node, current_community, proposed_community, node_mass);
+            changed_nodes.set(node, true);
+            affected_nodes.set(node, true);
+
+            for (neighbor_node, _w) in graph.neighbors(node) {
+                if node_to_community[neighbor_node] != proposed_community {
+                    next_active_nodes.set(neighbor_node, true);
+                }
+                if node_to_subcommunity[node] == node_to_subcommunity[neighbor_node] {
+                    affected_nodes.set(neighbor_node, true);
+                }
+            }
         }
     }
 
-    (
-        shared_changed.into_bitvec(),
-        shared_affected.into_bitvec(),
-        shared_next_active.into_bitvec(),
-    )
+    (changed_nodes, affected_nodes, next_active_nodes)
 }
 
+/// Refinement merging of `RunMode::Throughput`.
+///
+/// Singletons pick a target sub-community in parallel against a snapshot;
+/// merges are then committed in proposal order, and only if the vertex is
+/// still a singleton, the target still has members and the merge still does
+/// not lower the objective.
 pub fn inc_refinement_parallel(
     graph: &InMemoryGraph,
     refined_nodes_sorted: &[usize],
     node_to_community: &[usize],
     node_to_subcommunity: &mut Vec<usize>,
-    subcommunity_degrees: &mut HashMap<usize, f64>,
-    subcommunity_sizes: &[usize],
-    node_degrees: &[f64],
-    twice_total_weight: f64,
-    resolution_parameter: f64,
+    subcommunities: &mut Subcommunities,
+    cost: MoveCost,
+    random: Option<(RandomMerge, &[f64])>,
 ) {
+    let snapshot: &Subcommunities = subcommunities;
     let chunk_size = (refined_nodes_sorted.len() / rayon::current_num_threads()).max(1);
-    let states: Vec<Vec<(usize, usize, usize, f64)>> = refined_nodes_sorted
+    let proposals: Vec<Vec<(usize, usize)>> = refined_nodes_sorted
         .par_chunks(chunk_size)
-        .map(|shard| {
+        .enumerate()
+        .map(|(chunk_idx, shard)| {
             let mut local_updates = Vec::new();
-            for &current_node in shard {
+            for (offset, &current_node) in shard.iter().enumerate() {
                 // O(1) singleton check via pre-computed sizes
-                let is_singleton = subcommunity_sizes[node_to_subcommunity[current_node]] == 1;
+                let is_singleton = snapshot.sizes[node_to_subcommunity[current_node]] == 1;
 
                 if is_singleton {
                     let mut neighbor_subcommunities: HashMap<usize, f64> = HashMap::new();
                     let mut weight_to_current_subcommunity = 0.0;
-                    let current_node_degree = node_degrees[current_node];
 
                     for (neighbor_node, w) in graph.neighbors(current_node) {
+                        if neighbor_node == current_node {
+                            continue;
+                        }
                         if node_to_community[neighbor_node] == node_to_community[current_node] {
                             let neighbor_subcommunity = node_to_subcommunity[neighbor_node];
                             *neighbor_subcommunities
@@ -203,45 +241,42 @@ pub fn inc_refinement_parallel(
                     }
 
                     let mut best_subcommunity = node_to_subcommunity[current_node];
-                    let mut best_modularity_gain = 0.0;
+                    let mut best_gain = 0.0;
+                    let mut candidates = vec![(node_to_subcommunity[current_node], 0.0)];
 
                     for (&candidate_subcommunity, &weight_to_candidate_subcommunity) in
                         &neighbor_subcommunities
                     {
-                        if candidate_subcommunity == node_to_subcommunity[current_node] {
+                        if candidate_subcommunity == node_to_subcommunity[current_node]
+                            || !snapshot.admits(current_node, candidate_subcommunity)
+                        {
                             continue;
                         }
 
-                        let current_subcommunity_degree = *subcommunity_degrees
-                            .get(&node_to_subcommunity[current_node])
-                            .unwrap_or(&0.0);
-                        let candidate_subcommunity_degree = *subcommunity_degrees
-                            .get(&candidate_subcommunity)
-                            .unwrap_or(&0.0);
-
-                        let modularity_gain = (weight_to_candidate_subcommunity
-                            - weight_to_current_subcommunity)
-                            / twice_total_weight
-                            + resolution_parameter
-                                * current_node_degree
-                                * (current_subcommunity_degree
-                                    - current_node_degree
-                                    - candidate_subcommunity_degree)
-                                / (twice_total_weight * twice_total_weight);
-
-                        if modularity_gain > best_modularity_gain {
-                            best_modularity_gain = modularity_gain;
+                        let gain = cost.gain(
+                            current_node,
+                            weight_to_candidate_subcommunity,
+                            weight_to_current_subcommunity,
+                            snapshot.mass(node_to_subcommunity[current_node]),
+                            snapshot.mass(candidate_subcommunity),
+                        );
+
+                        if random.is_some() && gain >= 0.0 {
+                            candidates.push((candidate_subcommunity, gain));
+                        }
+                        if gain > best_gain {
+                            best_gain = gain;
                             best_subcommunity = candidate_subcommunity;
                         }
                     }
 
-                    if best_modularity_gain > 0.0 {
-                        local_updates.push((
-                            current_node,
-                            node_to_subcommunity[current_node],
-                            best_subcommunity,
-                            current_node_degree,
-                        ));
+                    // Draws are indexed by position in the sorted order, not by thread
+                    if let Some((random, draws)) = random {
+                        let draw = draws[chunk_idx * chunk_size + offset];
+                        best_subcommunity = random.choose(&mut candidates, draw);
+                    }
+                    if best_subcommunity != node_to_subcommunity[current_node] {
+                        local_updates.push((current_node, best_subcommunity));
                     }
                 }
             }
@@ -249,11 +284,46 @@ pub fn inc_refinement_parallel(
         })
         .collect();
 
-    for state in states {
-        for (node, old_subcomm, new_subcomm, degree) in state {
-            node_to_subcommunity[node] = new_subcomm;
-            *subcommunity_degrees.entry(old_subcomm).or_insert(0.0) -= degree;
-            *subcommunity_degrees.entry(new_subcomm).or_insert(0.0) += degree;
+    for (node, target) in proposals.into_iter().flatten() {
+        let current = node_to_subcommunity[node];
+        // An earlier merge may have absorbed this vertex's singleton or emptied the target
+        if subcommunities.sizes[current] != 1 || subcommunities.sizes[target] == 0 {
+            continue;
+        }
+        let mut weight_to_target = None;
+        let mut weight_to_current = 0.0;
+        for (neighbor_node, w) in graph.neighbors(node) {
+            if neighbor_node == node || node_to_community[neighbor_node] != node_to_community[node]
+            {
+                continue;
+            }
+            if node_to_subcommunity[neighbor_node] == target {
+                *weight_to_target.get_or_insert(0.0) += w;
+            } else if node_to_subcommunity[neighbor_node] == current {
+                weight_to_current += w;
+            }
+        }
+        let Some(weight_to_target) = weight_to_target else {
+            continue;
+        };
+        // Masses may have changed since the snapshot the merge was scored against
+        let gain = cost.gain(
+            node,
+            weight_to_target,
+            weight_to_current,
+            subcommunities.mass(current),
+            subcommunities.mass(target),
+        );
+        let accepted = if random.is_some() {
+            gain >= 0.0
+        } else {
+            gain > 0.0
+        };
+        // Merges proposed in parallel may together overflow a sub-community
+        if !accepted || !subcommunities.admits(node, target) {
+            continue;
         }
+        node_to_subcommunity[node] = target;
+        subcommunities.transfer(node, current, target, cost.node_masses[node]);
     }
 }
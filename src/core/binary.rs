@@ -0,0 +1,177 @@
+//! Little-endian encoding and crash-safe file replacement shared by the
+//! on-disk formats.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// FNV-1a 64-bit hash, used as the checksum of on-disk payloads.
+pub fn fnv1a64(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Appends values to a byte buffer.
+#[derive(Default)]
+pub(crate) struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn f64(&mut self, value: f64) {
+        self.u64(value.to_bits());
+    }
+
+    /// `usize::MAX` is kept as `u64::MAX` so markers survive any pointer width.
+    pub fn usize(&mut self, value: usize) {
+        self.u64(if value == usize::MAX {
+            u64::MAX
+        } else {
+            value as u64
+        });
+    }
+
+    pub fn usizes(&mut self, values: &[usize]) {
+        self.usize(values.len());
+        for &value in values {
+            self.usize(value);
+        }
+    }
+
+    pub fn f64s(&mut self, values: &[f64]) {
+        self.usize(values.len());
+        for &value in values {
+            self.f64(value);
+        }
+    }
+}
+
+/// Reads values back from a byte slice, failing on truncation.
+pub(crate) struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    pub fn bytes(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(count)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| format!("truncated at byte {}", self.position))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, String> {
+        let bytes = self.bytes(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, String> {
+        self.u64().map(f64::from_bits)
+    }
+
+    pub fn usize(&mut self) -> Result<usize, String> {
+        let position = self.position;
+        match self.u64()? {
+            u64::MAX => Ok(usize::MAX),
+            value => usize::try_from(value)
+                .map_err(|_| format!("value {value} at byte {position} does not fit in usize")),
+        }
+    }
+
+    /// Length prefix of a sequence whose items take at least `item_size` bytes,
+    /// checked against the bytes left so corrupt lengths cannot trigger huge
+    /// allocations.
+    pub fn count(&mut self, item_size: usize) -> Result<usize, String> {
+        let position = self.position;
+        let len = self.usize()?;
+        let remaining = self.bytes.len() - self.position;
+        if len
+            .checked_mul(item_size)
+            .map_or(true, |size| size > remaining)
+        {
+            return Err(format!("length {len} at byte {position} exceeds the data"));
+        }
+        Ok(len)
+    }
+
+    pub fn usizes(&mut self) -> Result<Vec<usize>, String> {
+        let len = self.count(8)?;
+        (0..len).map(|_| self.usize()).collect()
+    }
+
+    pub fn f64s(&mut self) -> Result<Vec<f64>, String> {
+        let len = self.count(8)?;
+        (0..len).map(|_| self.f64()).collect()
+    }
+}
+
+/// Replace the contents of `path` with `bytes` so that a crash leaves either
+/// the old or the new contents. The staging file is flushed before it is
+/// renamed over `path`, and the rename is flushed before returning, so steps
+/// that rely on the new contents — deleting what they supersede — can follow.
+pub(crate) fn replace_file(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(".tmp");
+    let mut file = File::create(&staging)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&staging, path)?;
+    sync_parent(path)
+}
+
+/// Flush the entry of `path` in its directory after it was created or renamed.
+#[cfg(unix)]
+pub(crate) fn sync_parent(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    File::open(dir)?.sync_all()
+}
+
+/// Directories cannot be opened for flushing here; renames are left to the
+/// file system.
+#[cfg(not(unix))]
+pub(crate) fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
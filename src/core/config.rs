@@ -1,9 +1,36 @@
+use crate::core::algorithm::quality::QualityKind;
 use crate::core::backend::{AccelerationTarget, GraphBackend, GraphSource};
+use crate::core::binary::{fnv1a64, Encoder};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RunMode {
     Deterministic,
     Throughput,
+    /// Parallel, yet the partition is the same for every thread count. It
+    /// commits moves in its own order, so it need not match `Deterministic`.
+    DeterministicParallel,
+}
+
+/// Budget on the size of every leaf community, the finest level of the hierarchy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CommunitySizeLimit {
+    /// At most this many nodes.
+    Nodes(usize),
+    /// Node weights summing to at most this much.
+    Weight(f64),
+}
+
+/// How parallel edges, repeats of the same `(u, v)` in an input graph, are
+/// merged into one edge.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParallelEdges {
+    /// The merged edge weighs the sum of the parallel weights.
+    #[default]
+    Sum,
+    /// The merged edge weighs the largest parallel weight.
+    Max,
+    /// Every edge weighs the number of parallel copies, ignoring their weights.
+    Count,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -12,8 +39,26 @@ pub struct RunConfig {
     pub graph_source: GraphSource,
     pub graph_backend: GraphBackend,
     pub acceleration: AccelerationTarget,
+    /// Objective optimised by every phase of the algorithm.
+    pub quality: QualityKind,
+    /// Resolution γ of the objective; larger values favour smaller communities.
+    pub resolution: f64,
     pub quality_tolerance: f64,
     pub max_iterations: usize,
+    /// Seed for Leiden's randomised refinement; `None` merges greedily.
+    pub seed: Option<u64>,
+    /// Temperature θ of the randomised refinement, used only with a seed.
+    pub randomness: f64,
+    /// Size budget of the reported communities (`node_to_comm`), and so of
+    /// every community nested in them down to the leaves; `None` leaves them
+    /// unbounded. A single node over the budget still forms its own community.
+    pub max_community_size: Option<CommunitySizeLimit>,
+    /// Read each edge `(u, v)` as an arc `u → v`; modularity then becomes
+    /// Leicht–Newman directed modularity.
+    pub directed: bool,
+    /// Merging of parallel edges in the input graph; edges added by later
+    /// deltas are validated against the merged graph instead.
+    pub parallel_edges: ParallelEdges,
     pub pinned_profile: Option<String>,
 }
 
@@ -24,8 +69,15 @@ impl Default for RunConfig {
             graph_source: GraphSource::File,
             graph_backend: GraphBackend::InMemory,
             acceleration: AccelerationTarget::PureRust,
+            quality: QualityKind::default(),
+            resolution: 1.0,
             quality_tolerance: 0.001,
             max_iterations: 10,
+            seed: None,
+            randomness: 0.01,
+            max_community_size: None,
+            directed: false,
+            parallel_edges: ParallelEdges::default(),
             pinned_profile: None,
         }
     }
@@ -36,9 +88,58 @@ impl RunConfig {
         if self.max_iterations == 0 {
             return Err("max_iterations must be > 0".to_string());
         }
+        if !(self.resolution.is_finite() && self.resolution > 0.0) {
+            return Err("resolution must be finite and > 0".to_string());
+        }
+        if !(self.randomness.is_finite() && self.randomness > 0.0) {
+            return Err("randomness must be finite and > 0".to_string());
+        }
+        match self.max_community_size {
+            Some(CommunitySizeLimit::Nodes(0)) => {
+                return Err("max_community_size must be at least one node".to_string());
+            }
+            Some(CommunitySizeLimit::Weight(w)) if !(w.is_finite() && w > 0.0) => {
+                return Err("max_community_size weight must be finite and > 0".to_string());
+            }
+            _ => {}
+        }
         if self.quality_tolerance < 0.0 {
             return Err("quality_tolerance must be >= 0".to_string());
         }
         Ok(())
     }
+
+    /// Checksum of the settings that shape the partition: all but the graph
+    /// source, backend, acceleration target and pinned profile. Replaying a
+    /// session's deltas reproduces it only under the same fingerprint.
+    pub fn fingerprint(&self) -> u64 {
+        let mut out = Encoder::default();
+        out.u32(self.mode as u32);
+        out.u32(self.quality as u32);
+        out.f64(self.resolution);
+        out.f64(self.quality_tolerance);
+        out.usize(self.max_iterations);
+        match self.seed {
+            Some(seed) => {
+                out.u32(1);
+                out.u64(seed);
+            }
+            None => out.u32(0),
+        }
+        out.f64(self.randomness);
+        match self.max_community_size {
+            Some(CommunitySizeLimit::Nodes(count)) => {
+                out.u32(1);
+                out.usize(count);
+            }
+            Some(CommunitySizeLimit::Weight(weight)) => {
+                out.u32(2);
+                out.f64(weight);
+            }
+            None => out.u32(0),
+        }
+        out.u32(u32::from(self.directed));
+        out.u32(self.parallel_edges as u32);
+        fnv1a64(&out.into_bytes())
+    }
 }
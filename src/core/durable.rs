@@ -0,0 +1,170 @@
+use crate::core::binary::replace_file;
+use crate::core::config::RunConfig;
+use crate::core::error::HitLeidenError;
+use crate::core::graph::delta::DeltaBatch;
+use crate::core::graph::delta_log::DeltaLog;
+use crate::core::session::IncrementalSession;
+use crate::core::types::GraphInput;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LOG_FILE: &str = "deltas.log";
+const CONFIG_FILE: &str = "config.fingerprint";
+
+/// An [`IncrementalSession`] that survives crashes.
+///
+/// The session lives in a directory holding a checkpoint of the session, a
+/// [`DeltaLog`] and the [`RunConfig::fingerprint`] it was created with. Every
+/// batch is validated, durably logged under the next sequence number and only
+/// then applied. [`DurableSession::checkpoint`] saves the session — state,
+/// version, community identifiers and lineage — and drops the log records it
+/// covers; [`DurableSession::open`] loads the checkpoint and replays the
+/// records logged after it, which is exact because it only accepts the
+/// configuration the session was created with.
+#[derive(Debug)]
+pub struct DurableSession {
+    dir: PathBuf,
+    session: IncrementalSession,
+    log: DeltaLog,
+    /// Sequence number of the last logged batch.
+    sequence: u64,
+}
+
+impl DurableSession {
+    /// Cluster `initial` and start a durable session in `dir`, which must not
+    /// hold one already.
+    pub fn create(
+        dir: impl AsRef<Path>,
+        initial: &GraphInput,
+        config: RunConfig,
+    ) -> Result<Self, HitLeidenError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(io_error(&dir))?;
+        if latest_checkpoint(&dir)?.is_some() || dir.join(LOG_FILE).exists() {
+            return Err(HitLeidenError::InvalidInput(format!(
+                "{} already holds a session",
+                dir.display()
+            )));
+        }
+        let session = IncrementalSession::new(initial, config)?;
+        let fingerprint = format!("{:016x}\n", session.config().fingerprint());
+        let path = dir.join(CONFIG_FILE);
+        replace_file(&path, fingerprint.as_bytes()).map_err(io_error(&path))?;
+        let mut durable = Self {
+            log: DeltaLog::open(dir.join(LOG_FILE))?,
+            dir,
+            session,
+            sequence: 0,
+        };
+        durable.checkpoint()?;
+        Ok(durable)
+    }
+
+    /// Recover the session in `dir`: load its checkpoint and replay the
+    /// batches logged since. `config` must have the fingerprint of the
+    /// configuration the session was created with.
+    pub fn open(dir: impl AsRef<Path>, config: RunConfig) -> Result<Self, HitLeidenError> {
+        let dir = dir.as_ref().to_path_buf();
+        let path = dir.join(CONFIG_FILE);
+        let stored = fs::read_to_string(&path).map_err(io_error(&path))?;
+        let fingerprint = format!("{:016x}", config.fingerprint());
+        if stored.trim_end() != fingerprint {
+            return Err(HitLeidenError::InvalidInput(format!(
+                "{}: the session was created with another configuration \
+                 (fingerprint {}, this one is {fingerprint})",
+                dir.display(),
+                stored.trim_end()
+            )));
+        }
+        let (path, sequence) = latest_checkpoint(&dir)?.ok_or_else(|| {
+            HitLeidenError::Checkpoint(format!("{}: no checkpoint found", dir.display()))
+        })?;
+        let mut session = IncrementalSession::load(path, config)?;
+
+        let log = DeltaLog::open(dir.join(LOG_FILE))?;
+        let mut last = sequence;
+        for (record, batch) in log.entries_after(sequence)? {
+            session.apply_delta(&batch).map_err(|e| {
+                HitLeidenError::Checkpoint(format!("replaying delta log record {record}: {e}"))
+            })?;
+            last = record;
+        }
+        Ok(Self {
+            dir,
+            session,
+            log,
+            sequence: last,
+        })
+    }
+
+    /// Log `batch` durably, then apply it. A batch the session would reject
+    /// is rejected before anything is logged.
+    pub fn apply_delta(&mut self, batch: &DeltaBatch) -> Result<&[usize], HitLeidenError> {
+        self.session.check_delta(batch)?;
+        self.log.append(self.sequence + 1, batch)?;
+        self.sequence += 1;
+        self.session.apply_delta(batch)
+    }
+
+    /// Save the current state and drop the log records it covers. Older
+    /// checkpoints are removed once the new one is durably in place; a crash
+    /// at any step leaves a directory [`DurableSession::open`] recovers from.
+    pub fn checkpoint(&mut self) -> Result<(), HitLeidenError> {
+        let name = checkpoint_name(self.sequence, self.session.version());
+        // Saving flushes the checkpoint and its directory entry, so a crash
+        // past this point finds it even though what it covers is gone
+        self.session.save(self.dir.join(&name))?;
+        for entry in fs::read_dir(&self.dir).map_err(io_error(&self.dir))? {
+            let entry = entry.map_err(io_error(&self.dir))?;
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            if parse_checkpoint_name(&file_name).is_some() && file_name != name {
+                fs::remove_file(entry.path()).map_err(io_error(&entry.path()))?;
+            }
+        }
+        self.log.truncate_through(self.sequence)
+    }
+
+    pub fn session(&self) -> &IncrementalSession {
+        &self.session
+    }
+
+    /// Sequence number of the last logged batch; zero before the first one.
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub fn log(&self) -> &DeltaLog {
+        &self.log
+    }
+}
+
+fn checkpoint_name(sequence: u64, version: u64) -> String {
+    format!("checkpoint-{sequence:020}-{version:020}.ckpt")
+}
+
+/// Sequence number and session version encoded in a checkpoint file name.
+fn parse_checkpoint_name(name: &str) -> Option<(u64, u64)> {
+    let stem = name.strip_prefix("checkpoint-")?.strip_suffix(".ckpt")?;
+    let (sequence, version) = stem.split_once('-')?;
+    Some((sequence.parse().ok()?, version.parse().ok()?))
+}
+
+/// The checkpoint in `dir` with the highest sequence number, and that number.
+fn latest_checkpoint(dir: &Path) -> Result<Option<(PathBuf, u64)>, HitLeidenError> {
+    let mut latest: Option<(PathBuf, u64)> = None;
+    for entry in fs::read_dir(dir).map_err(io_error(dir))? {
+        let entry = entry.map_err(io_error(dir))?;
+        if let Some((sequence, _)) = parse_checkpoint_name(&entry.file_name().to_string_lossy()) {
+            if latest.as_ref().map_or(true, |&(_, s)| sequence > s) {
+                latest = Some((entry.path(), sequence));
+            }
+        }
+    }
+    Ok(latest)
+}
+
+fn io_error(path: &Path) -> impl Fn(std::io::Error) -> HitLeidenError {
+    let path = path.display().to_string();
+    move |e| HitLeidenError::Checkpoint(format!("{path}: {e}"))
+}
@@ -8,4 +8,6 @@ pub enum HitLeidenError {
     Backend(String),
     #[error("acceleration error: {0}")]
     Acceleration(String),
+    #[error("checkpoint error: {0}")]
+    Checkpoint(String),
 }
@@ -0,0 +1,370 @@
+//! Binary CSR graphs, which load straight into an [`InMemoryGraph`] without
+//! parsing text.
+//!
+//! A file is a 48-byte header followed by the little-endian body:
+//!
+//! ```text
+//! magic      8 bytes  "HLCSR\0\0\0"
+//! version    u32      format version it was written with
+//! flags      u32      bit 0: weighted, bit 1: directed
+//! nodes      u64
+//! edges      u64      edges, each undirected edge and self-loop once; arcs when directed
+//! entries    u64      length of the neighbour array
+//! checksum   u64      FNV-1a 64 of the body
+//! offsets    (nodes + 1) × u64
+//! neighbors  entries × u64
+//! weights    entries × f64, only when weighted; every weight is 1 otherwise
+//! ```
+//!
+//! The arrays are those of a freshly built [`InMemoryGraph`]: an undirected
+//! edge is listed under both endpoints and a self-loop twice under its node.
+//! A directed graph lists its out-adjacency in the arrays and follows them
+//! with offsets, neighbours and weights of its in-adjacency, of the same
+//! length. Every array is 8-byte aligned, so the file can be mapped as is.
+//!
+//! Loading checks the arrays as well as the checksum: an undirected edge must
+//! be listed under both endpoints with one weight, an in-adjacency must list
+//! exactly the reversed arcs, and no node may list a neighbour twice but for
+//! an undirected self-loop.
+
+use crate::core::binary::{fnv1a64, replace_file, Decoder, Encoder};
+use crate::core::config::ParallelEdges;
+use crate::core::error::HitLeidenError;
+use crate::core::graph::edge_list::{load_edge_list, EdgeListOptions};
+use crate::core::graph::in_memory::InMemoryGraph;
+use crate::core::types::{GraphDataset, GraphFormat, GraphSourceType};
+use std::io::Write;
+use std::path::Path;
+
+pub const MAGIC: &[u8; 8] = b"HLCSR\0\0\0";
+
+/// Format version written by this build.
+pub const FORMAT_VERSION: u32 = 1;
+
+const HEADER_LEN: usize = 8 + 4 + 4 + 8 + 8 + 8 + 8;
+const WEIGHTED: u32 = 1;
+const DIRECTED: u32 = 2;
+
+/// Compact `(offsets, neighbors, weights)` arrays of one adjacency.
+type Adjacency = (Vec<usize>, Vec<usize>, Vec<f64>);
+
+/// Header fields of a CSR file.
+struct Header {
+    flags: u32,
+    node_count: usize,
+    edge_count: usize,
+    entries: usize,
+    checksum: u64,
+}
+
+/// Write `graph` in CSR binary format. Slack left by in-place edits is
+/// dropped, and weights are only stored if one differs from 1.
+pub fn write_csr(graph: &InMemoryGraph, mut out: impl Write) -> std::io::Result<()> {
+    let mut adjacencies = vec![adjacency(graph, false)];
+    if graph.is_directed() {
+        adjacencies.push(adjacency(graph, true));
+    }
+    let weighted = adjacencies[0].2.iter().any(|&w| w != 1.0);
+    let entries = adjacencies[0].1.len();
+
+    let mut body = Encoder::default();
+    for (offsets, neighbors, weights) in &adjacencies {
+        for &offset in offsets {
+            body.usize(offset);
+        }
+        for &neighbor in neighbors {
+            body.usize(neighbor);
+        }
+        if weighted {
+            for &weight in weights {
+                body.f64(weight);
+            }
+        }
+    }
+    let body = body.into_bytes();
+
+    let mut header = Encoder::default();
+    header.bytes(MAGIC);
+    header.u32(FORMAT_VERSION);
+    let mut flags = 0;
+    if weighted {
+        flags |= WEIGHTED;
+    }
+    if graph.is_directed() {
+        flags |= DIRECTED;
+    }
+    header.u32(flags);
+    header.usize(graph.node_count);
+    header.usize(if graph.is_directed() {
+        entries
+    } else {
+        entries / 2
+    });
+    header.usize(entries);
+    header.u64(fnv1a64(&body));
+    out.write_all(&header.into_bytes())?;
+    out.write_all(&body)?;
+    out.flush()
+}
+
+/// Read a graph written by [`write_csr`].
+pub fn parse_csr(bytes: &[u8]) -> Result<InMemoryGraph, HitLeidenError> {
+    parse(bytes)
+        .map(|(graph, _)| graph)
+        .map_err(HitLeidenError::InvalidInput)
+}
+
+/// Read the CSR file at `path`, describing it in a [`GraphDataset`] whose id
+/// is the file stem and whose checksum is the one stored in the header.
+pub fn load_csr(path: impl AsRef<Path>) -> Result<(InMemoryGraph, GraphDataset), HitLeidenError> {
+    let path = path.as_ref();
+    let invalid = |e: String| HitLeidenError::InvalidInput(format!("{}: {e}", path.display()));
+    let bytes = std::fs::read(path).map_err(|e| invalid(e.to_string()))?;
+    let (graph, header) = parse(&bytes).map_err(invalid)?;
+    Ok((graph, describe(path, &header)))
+}
+
+/// Whether `bytes`, e.g. the start of a file, begin like a CSR file.
+pub fn is_csr(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Convert the edge list at `source` into a CSR file at `target`, merging
+/// parallel edges by summing them, and describe the file written. An existing
+/// `target` is replaced only once the new file is complete.
+pub fn convert_edge_list(
+    source: impl AsRef<Path>,
+    options: &EdgeListOptions,
+    directed: bool,
+    target: impl AsRef<Path>,
+) -> Result<GraphDataset, HitLeidenError> {
+    let (input, _) = load_edge_list(source, options)?;
+    let input = input.merge_parallel_edges(ParallelEdges::Sum, directed);
+    let graph = if directed {
+        InMemoryGraph::directed(&input)
+    } else {
+        InMemoryGraph::from(&*input)
+    };
+    let target = target.as_ref();
+    let mut bytes = Vec::new();
+    write_csr(&graph, &mut bytes).expect("writing to memory cannot fail");
+    replace_file(target, &bytes)
+        .map_err(|e| HitLeidenError::Backend(format!("{}: {e}", target.display())))?;
+    let header = read_header(&bytes).expect("a header was just written");
+    Ok(describe(target, &header))
+}
+
+/// The out- or in-adjacency of `graph` without slack.
+fn adjacency(graph: &InMemoryGraph, incoming: bool) -> Adjacency {
+    let mut offsets = Vec::with_capacity(graph.node_count + 1);
+    let mut neighbors = Vec::new();
+    let mut weights = Vec::new();
+    offsets.push(0);
+    for node in 0..graph.node_count {
+        let entries: Box<dyn Iterator<Item = (usize, f64)>> = if incoming {
+            Box::new(graph.in_neighbors(node))
+        } else {
+            Box::new(graph.out_neighbors(node))
+        };
+        for (neighbor, weight) in entries {
+            neighbors.push(neighbor);
+            weights.push(weight);
+        }
+        offsets.push(neighbors.len());
+    }
+    (offsets, neighbors, weights)
+}
+
+fn read_header(bytes: &[u8]) -> Result<Header, String> {
+    if bytes.len() < HEADER_LEN || !is_csr(bytes) {
+        return Err("not a CSR graph file".to_string());
+    }
+    let mut input = Decoder::new(&bytes[MAGIC.len()..]);
+    let version = input.u32()?;
+    if version > FORMAT_VERSION {
+        return Err(format!(
+            "format version {version} is newer than this build reads ({FORMAT_VERSION})"
+        ));
+    }
+    let header = Header {
+        flags: input.u32()?,
+        node_count: input.usize()?,
+        edge_count: input.usize()?,
+        entries: input.usize()?,
+        checksum: input.u64()?,
+    };
+    if header.flags & !(WEIGHTED | DIRECTED) != 0 {
+        return Err(format!("unknown flags {:#x}", header.flags));
+    }
+    Ok(header)
+}
+
+fn parse(bytes: &[u8]) -> Result<(InMemoryGraph, Header), String> {
+    let header = read_header(bytes)?;
+
+    let weighted = header.flags & WEIGHTED != 0;
+    let directed = header.flags & DIRECTED != 0;
+    let words_per_entry = if weighted { 2 } else { 1 };
+    let body_len = header
+        .node_count
+        .checked_add(1)
+        .and_then(|offsets| offsets.checked_add(header.entries.checked_mul(words_per_entry)?))
+        .and_then(|words| words.checked_mul(if directed { 16 } else { 8 }));
+    if body_len != Some(bytes.len() - HEADER_LEN) {
+        return Err(format!(
+            "body holds {} bytes, not the size of {} nodes and {} entries",
+            bytes.len() - HEADER_LEN,
+            header.node_count,
+            header.entries
+        ));
+    }
+    let body = &bytes[HEADER_LEN..];
+    let computed = fnv1a64(body);
+    if computed != header.checksum {
+        return Err(format!(
+            "checksum mismatch: stored {:016x}, computed {computed:016x}",
+            header.checksum
+        ));
+    }
+    let expected_edges = if directed {
+        header.entries
+    } else {
+        header.entries / 2
+    };
+    if header.edge_count != expected_edges || (!directed && header.entries % 2 != 0) {
+        return Err(format!(
+            "{} edges do not match {} entries",
+            header.edge_count, header.entries
+        ));
+    }
+
+    let mut body = Decoder::new(body);
+    let outgoing = read_adjacency(&mut body, header.node_count, header.entries, weighted)?;
+    let incoming = if directed {
+        Some(read_adjacency(
+            &mut body,
+            header.node_count,
+            header.entries,
+            weighted,
+        )?)
+    } else {
+        None
+    };
+    check_mirrored(&outgoing, incoming.as_ref())?;
+    let (offsets, neighbors, weights) = outgoing;
+    let graph = InMemoryGraph::from_csr(offsets, neighbors, weights, incoming);
+    Ok((graph, header))
+}
+
+/// Check that `incoming`, or `outgoing` itself when undirected, lists every
+/// entry of `outgoing` reversed with the same weight, and that no node lists
+/// a neighbour twice, an undirected self-loop aside.
+fn check_mirrored(outgoing: &Adjacency, incoming: Option<&Adjacency>) -> Result<(), String> {
+    let listed = sorted_entries(outgoing);
+    let mut reversed: Vec<_> = listed.iter().map(|&(u, v, w)| (v, u, w)).collect();
+    reversed.sort_unstable();
+    match incoming {
+        Some(incoming) if sorted_entries(incoming) != reversed => {
+            return Err("in-adjacency does not list the reversed arcs".to_string());
+        }
+        None if listed != reversed => {
+            return Err("adjacency is not symmetric".to_string());
+        }
+        _ => {}
+    }
+
+    let mut start = 0;
+    while start < listed.len() {
+        let (node, neighbor, weight) = listed[start];
+        let times = listed[start..]
+            .iter()
+            .take_while(|&&(u, v, _)| (u, v) == (node, neighbor))
+            .count();
+        let expected = if incoming.is_none() && node == neighbor {
+            2
+        } else {
+            1
+        };
+        if times != expected {
+            return Err(format!(
+                "node {node} lists neighbour {neighbor} {times} times"
+            ));
+        }
+        if listed[start..start + times]
+            .iter()
+            .any(|&(_, _, w)| w != weight)
+        {
+            return Err(format!("self-loop of node {node} has two weights"));
+        }
+        start += times;
+    }
+    Ok(())
+}
+
+/// `(node, neighbour, weight bits)` of every entry of `adjacency`, sorted.
+fn sorted_entries((offsets, neighbors, weights): &Adjacency) -> Vec<(usize, usize, u64)> {
+    let mut entries: Vec<_> = offsets
+        .windows(2)
+        .enumerate()
+        .flat_map(|(node, range)| {
+            (range[0]..range[1]).map(move |i| (node, neighbors[i], weights[i].to_bits()))
+        })
+        .collect();
+    entries.sort_unstable();
+    entries
+}
+
+/// Read one adjacency, checking that offsets rise from 0 to `entries`,
+/// neighbours are nodes and weights are finite and positive.
+fn read_adjacency(
+    input: &mut Decoder,
+    node_count: usize,
+    entries: usize,
+    weighted: bool,
+) -> Result<Adjacency, String> {
+    let offsets = (0..=node_count)
+        .map(|_| input.usize())
+        .collect::<Result<Vec<_>, _>>()?;
+    if offsets[0] != 0 || offsets[node_count] != entries || offsets.windows(2).any(|w| w[0] > w[1])
+    {
+        return Err("offsets do not rise from 0 to the entry count".to_string());
+    }
+    let neighbors = (0..entries)
+        .map(|_| input.usize())
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(neighbor) = neighbors.iter().find(|&&v| v >= node_count) {
+        return Err(format!(
+            "neighbour {neighbor} is not one of {node_count} nodes"
+        ));
+    }
+    let weights = if weighted {
+        (0..entries)
+            .map(|_| input.f64())
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![1.0; entries]
+    };
+    if let Some(weight) = weights.iter().find(|w| !(w.is_finite() && **w > 0.0)) {
+        return Err(format!("weight {weight} is not finite and > 0"));
+    }
+    Ok((offsets, neighbors, weights))
+}
+
+fn describe(path: &Path, header: &Header) -> GraphDataset {
+    GraphDataset {
+        dataset_id: path.file_stem().map_or_else(
+            || path.display().to_string(),
+            |s| s.to_string_lossy().into_owned(),
+        ),
+        source_uri: path.display().to_string(),
+        is_weighted: header.flags & WEIGHTED != 0,
+        node_count: header.node_count,
+        edge_count: header.edge_count,
+        checksum: format!("fnv1a64:{:016x}", header.checksum),
+        format: GraphFormat::CsrBinary,
+        mmap_compatible: true,
+        mmap_path: Some(path.display().to_string()),
+        source_type: GraphSourceType::File,
+        source_snapshot_id: None,
+    }
+}
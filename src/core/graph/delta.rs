@@ -0,0 +1,346 @@
+use crate::core::binary::{Decoder, Encoder};
+use crate::core::error::HitLeidenError;
+use crate::core::graph::in_memory::InMemoryGraph;
+use crate::core::types::GraphInput;
+use bitvec::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// One change to an undirected edge `(u, v)`, or to the arc `u → v` of a
+/// directed graph.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EdgeDelta {
+    /// Add an edge that is not in the graph. `weight` must be positive.
+    Insert { u: usize, v: usize, weight: f64 },
+    /// Remove an edge that is in the graph.
+    Delete { u: usize, v: usize },
+    /// Replace the weight of an edge that is in the graph. `weight` must be positive.
+    SetWeight { u: usize, v: usize, weight: f64 },
+    /// Add `delta` to the weight of an edge, creating it if absent. The edge is
+    /// removed if its weight drops to zero; it may not drop below zero.
+    AdjustWeight { u: usize, v: usize, delta: f64 },
+}
+
+impl EdgeDelta {
+    pub fn endpoints(&self) -> (usize, usize) {
+        match *self {
+            EdgeDelta::Insert { u, v, .. }
+            | EdgeDelta::Delete { u, v }
+            | EdgeDelta::SetWeight { u, v, .. }
+            | EdgeDelta::AdjustWeight { u, v, .. } => (u, v),
+        }
+    }
+}
+
+/// Ordered batch of graph changes applied to a session as one update.
+///
+/// The batch first appends `added_nodes` isolated nodes, numbered from the
+/// current node count, so edge operations may refer to them. Edge operations
+/// are then validated in order against the graph as modified by the earlier
+/// operations of the same batch, so a batch may insert an edge and then
+/// reweight or delete it. `node_weights` then sets the weight of existing or
+/// added nodes (1.0 unless set). Finally every node in `removed_nodes` is
+/// detached from all its edges and tombstoned: its id stays allocated but may
+/// not be used again.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeltaBatch {
+    pub deltas: Vec<EdgeDelta>,
+    pub added_nodes: usize,
+    pub node_weights: Vec<(usize, f64)>,
+    pub removed_nodes: Vec<usize>,
+}
+
+impl DeltaBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat every edge of `graph` as an [`EdgeDelta::AdjustWeight`] by its
+    /// weight (1.0 when unweighted). Useful for replaying edge-list streams.
+    pub fn adjustments(graph: &GraphInput) -> Self {
+        Self {
+            deltas: graph
+                .edges
+                .iter()
+                .map(|&(u, v, w)| EdgeDelta::AdjustWeight {
+                    u,
+                    v,
+                    delta: w.unwrap_or(1.0),
+                })
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Append `count` isolated nodes to the graph.
+    pub fn add_nodes(mut self, count: usize) -> Self {
+        self.added_nodes += count;
+        self
+    }
+
+    /// Set the weight of `node`, used as its size by CPM.
+    pub fn set_node_weight(mut self, node: usize, weight: f64) -> Self {
+        self.node_weights.push((node, weight));
+        self
+    }
+
+    /// Detach `node` from all its edges and retire its id.
+    pub fn remove_node(mut self, node: usize) -> Self {
+        self.removed_nodes.push(node);
+        self
+    }
+
+    pub fn insert(mut self, u: usize, v: usize, weight: f64) -> Self {
+        self.deltas.push(EdgeDelta::Insert { u, v, weight });
+        self
+    }
+
+    pub fn delete(mut self, u: usize, v: usize) -> Self {
+        self.deltas.push(EdgeDelta::Delete { u, v });
+        self
+    }
+
+    pub fn set_weight(mut self, u: usize, v: usize, weight: f64) -> Self {
+        self.deltas.push(EdgeDelta::SetWeight { u, v, weight });
+        self
+    }
+
+    pub fn adjust_weight(mut self, u: usize, v: usize, delta: f64) -> Self {
+        self.deltas.push(EdgeDelta::AdjustWeight { u, v, delta });
+        self
+    }
+
+    pub fn push(&mut self, delta: EdgeDelta) {
+        self.deltas.push(delta);
+    }
+
+    /// Number of changes in the batch: edge operations, added nodes, node
+    /// weight assignments and node removals. Zero exactly when [`Self::is_empty`].
+    pub fn len(&self) -> usize {
+        self.deltas.len() + self.added_nodes + self.node_weights.len() + self.removed_nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+            && self.added_nodes == 0
+            && self.node_weights.is_empty()
+            && self.removed_nodes.is_empty()
+    }
+
+    /// Validate the batch against `graph` and net it into the signed ΔG the
+    /// incremental algorithm consumes: one entry per touched edge carrying
+    /// `new_weight - old_weight`, over the grown node count. Edges whose weight
+    /// ends where it started are omitted. `removed` marks tombstoned nodes.
+    /// On a directed `graph`, `(u, v)` and `(v, u)` are different arcs.
+    pub fn resolve(
+        &self,
+        graph: &InMemoryGraph,
+        removed: &BitVec,
+        dataset_id: &str,
+    ) -> Result<GraphInput, HitLeidenError> {
+        let node_count = graph.node_count + self.added_nodes;
+        let is_removed = |node: usize| node < removed.len() && removed[node];
+        // (u, v) -> (weight before the batch, weight so far)
+        let mut touched: BTreeMap<(usize, usize), (f64, f64)> = BTreeMap::new();
+        let key = |u: usize, v: usize| {
+            if graph.is_directed() {
+                (u, v)
+            } else {
+                (u.min(v), u.max(v))
+            }
+        };
+        let weight_in_graph = |u: usize, v: usize| {
+            if u < graph.node_count && v < graph.node_count {
+                graph.edge_weight(u, v).unwrap_or(0.0)
+            } else {
+                0.0
+            }
+        };
+
+        for (index, delta) in self.deltas.iter().enumerate() {
+            let (u, v) = delta.endpoints();
+            let invalid = |reason: String| {
+                HitLeidenError::InvalidInput(format!("delta {index} on edge ({u}, {v}): {reason}"))
+            };
+            if u >= node_count || v >= node_count {
+                return Err(invalid(format!(
+                    "endpoint out of range for node_count {node_count}"
+                )));
+            }
+            if is_removed(u) || is_removed(v) {
+                return Err(invalid("endpoint was removed".to_string()));
+            }
+
+            let key = key(u, v);
+            let (_, current) = *touched.entry(key).or_insert_with(|| {
+                let weight = weight_in_graph(u, v);
+                (weight, weight)
+            });
+            let exists = current > 0.0;
+
+            let next = match *delta {
+                EdgeDelta::Insert { weight, .. } => {
+                    check_weight(weight).map_err(&invalid)?;
+                    if exists {
+                        return Err(invalid(
+                            "insert of an existing edge; use SetWeight or AdjustWeight".to_string(),
+                        ));
+                    }
+                    weight
+                }
+                EdgeDelta::Delete { .. } => {
+                    if !exists {
+                        return Err(invalid("delete of a missing edge".to_string()));
+                    }
+                    0.0
+                }
+                EdgeDelta::SetWeight { weight, .. } => {
+                    check_weight(weight).map_err(&invalid)?;
+                    if !exists {
+                        return Err(invalid("reweight of a missing edge".to_string()));
+                    }
+                    weight
+                }
+                EdgeDelta::AdjustWeight { delta, .. } => {
+                    if !delta.is_finite() {
+                        return Err(invalid(format!("adjustment {delta} is not finite")));
+                    }
+                    let next = current + delta;
+                    if next < -1e-9 {
+                        return Err(invalid(format!(
+                            "adjustment {delta} would make weight {current} negative"
+                        )));
+                    }
+                    next.max(0.0)
+                }
+            };
+            touched.get_mut(&key).expect("entry inserted above").1 = next;
+        }
+
+        for (index, &(node, weight)) in self.node_weights.iter().enumerate() {
+            let invalid = |reason: String| {
+                HitLeidenError::InvalidInput(format!(
+                    "node weight {index} of node {node}: {reason}"
+                ))
+            };
+            if node >= node_count {
+                return Err(invalid("node out of range".to_string()));
+            }
+            if is_removed(node) || self.removed_nodes.contains(&node) {
+                return Err(invalid("node was removed".to_string()));
+            }
+            if !(weight.is_finite() && weight >= 0.0) {
+                return Err(invalid(format!(
+                    "weight {weight} must be finite and non-negative"
+                )));
+            }
+        }
+
+        let mut seen = BTreeSet::new();
+        for (index, &node) in self.removed_nodes.iter().enumerate() {
+            let invalid = |reason: &str| {
+                HitLeidenError::InvalidInput(format!("removal {index} of node {node}: {reason}"))
+            };
+            if node >= node_count {
+                return Err(invalid("node out of range"));
+            }
+            if is_removed(node) || !seen.insert(node) {
+                return Err(invalid("node was already removed"));
+            }
+
+            // Detach edges already in the graph and any added earlier in this batch
+            if node < graph.node_count {
+                let arcs = graph
+                    .out_neighbors(node)
+                    .map(|(neighbor, weight)| (key(node, neighbor), weight))
+                    .chain(
+                        graph
+                            .in_neighbors(node)
+                            .map(|(neighbor, weight)| (key(neighbor, node), weight)),
+                    );
+                for (key, weight) in arcs {
+                    touched.entry(key).or_insert((weight, weight)).1 = 0.0;
+                }
+            }
+            for (&(a, b), entry) in touched.iter_mut() {
+                if a == node || b == node {
+                    entry.1 = 0.0;
+                }
+            }
+        }
+
+        Ok(GraphInput {
+            dataset_id: dataset_id.to_string(),
+            node_count,
+            edges: touched
+                .into_iter()
+                .filter(|&(_, (before, after))| (after - before).abs() > 1e-9)
+                .map(|((u, v), (before, after))| (u, v, Some(after - before)))
+                .collect(),
+        })
+    }
+}
+
+impl DeltaBatch {
+    pub(crate) fn encode(&self, out: &mut Encoder) {
+        out.usize(self.deltas.len());
+        for delta in &self.deltas {
+            let (tag, weight) = match *delta {
+                EdgeDelta::Insert { weight, .. } => (0, weight),
+                EdgeDelta::Delete { .. } => (1, 0.0),
+                EdgeDelta::SetWeight { weight, .. } => (2, weight),
+                EdgeDelta::AdjustWeight { delta, .. } => (3, delta),
+            };
+            let (u, v) = delta.endpoints();
+            out.u32(tag);
+            out.usize(u);
+            out.usize(v);
+            out.f64(weight);
+        }
+        out.usize(self.added_nodes);
+        out.usize(self.node_weights.len());
+        for &(node, weight) in &self.node_weights {
+            out.usize(node);
+            out.f64(weight);
+        }
+        out.usizes(&self.removed_nodes);
+    }
+
+    pub(crate) fn decode(input: &mut Decoder) -> Result<Self, String> {
+        let count = input.count(28)?;
+        let mut deltas = Vec::with_capacity(count);
+        for _ in 0..count {
+            let tag = input.u32()?;
+            let (u, v, weight) = (input.usize()?, input.usize()?, input.f64()?);
+            deltas.push(match tag {
+                0 => EdgeDelta::Insert { u, v, weight },
+                1 => EdgeDelta::Delete { u, v },
+                2 => EdgeDelta::SetWeight { u, v, weight },
+                3 => EdgeDelta::AdjustWeight {
+                    u,
+                    v,
+                    delta: weight,
+                },
+                _ => return Err(format!("unknown edge operation {tag}")),
+            });
+        }
+        let added_nodes = input.usize()?;
+        let count = input.count(16)?;
+        let node_weights = (0..count)
+            .map(|_| Ok((input.usize()?, input.f64()?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self {
+            deltas,
+            added_nodes,
+            node_weights,
+            removed_nodes: input.usizes()?,
+        })
+    }
+}
+
+fn check_weight(weight: f64) -> Result<(), String> {
+    if weight.is_finite() && weight > 0.0 {
+        Ok(())
+    } else {
+        Err(format!("weight {weight} must be finite and positive"))
+    }
+}
@@ -0,0 +1,230 @@
+use crate::core::binary::{fnv1a64, replace_file, sync_parent, Decoder, Encoder};
+use crate::core::error::HitLeidenError;
+use crate::core::graph::delta::DeltaBatch;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 8] = b"HLDLOG\0\0";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: usize = 8 + 4;
+/// Sequence number and payload length preceding every record's payload.
+const RECORD_HEADER_LEN: usize = 8 + 8;
+
+/// Append-only log of the delta batches applied to a session.
+///
+/// Each record holds a sequence number, the encoded batch and an FNV-1a 64
+/// checksum over both, and is flushed to disk before [`DeltaLog::append`]
+/// returns. A record cut short by a crash is dropped when the log is opened;
+/// a damaged record followed by further data is reported as corruption.
+#[derive(Debug)]
+pub struct DeltaLog {
+    path: PathBuf,
+    file: File,
+    last_sequence: Option<u64>,
+}
+
+impl DeltaLog {
+    /// Open the log at `path`, creating it if missing.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, HitLeidenError> {
+        let path = path.as_ref().to_path_buf();
+        let io_error = io_error(&path);
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)
+            .map_err(&io_error)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).map_err(&io_error)?;
+
+        if bytes.is_empty() {
+            let mut header = Encoder::default();
+            header.bytes(MAGIC);
+            header.u32(FORMAT_VERSION);
+            file.write_all(&header.into_bytes()).map_err(&io_error)?;
+            file.sync_data().map_err(&io_error)?;
+            sync_parent(&path).map_err(&io_error)?;
+            return Ok(Self {
+                path,
+                file,
+                last_sequence: None,
+            });
+        }
+
+        let (records, valid_len) = scan(&path, &bytes)?;
+        if valid_len < bytes.len() {
+            file.set_len(valid_len as u64).map_err(&io_error)?;
+            file.sync_data().map_err(&io_error)?;
+        }
+        Ok(Self {
+            path,
+            file,
+            last_sequence: records.last().map(|&(sequence, _)| sequence),
+        })
+    }
+
+    /// Sequence number of the last record, if any.
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.last_sequence
+    }
+
+    /// Durably append `batch` as record `sequence`, which must be larger than
+    /// every sequence number in the log.
+    pub fn append(&mut self, sequence: u64, batch: &DeltaBatch) -> Result<(), HitLeidenError> {
+        if self.last_sequence.is_some_and(|last| sequence <= last) {
+            return Err(HitLeidenError::InvalidInput(format!(
+                "sequence {sequence} does not follow {}",
+                self.last_sequence.unwrap()
+            )));
+        }
+        let mut payload = Encoder::default();
+        batch.encode(&mut payload);
+        let payload = payload.into_bytes();
+
+        let mut record = Encoder::default();
+        record.u64(sequence);
+        record.usize(payload.len());
+        record.bytes(&payload);
+        record.u64(record_checksum(sequence, &payload));
+
+        let io_error = io_error(&self.path);
+        self.file
+            .write_all(&record.into_bytes())
+            .map_err(&io_error)?;
+        self.file.sync_data().map_err(&io_error)?;
+        self.last_sequence = Some(sequence);
+        Ok(())
+    }
+
+    /// Every record in the log, in sequence order.
+    pub fn entries(&self) -> Result<Vec<(u64, DeltaBatch)>, HitLeidenError> {
+        let bytes = fs::read(&self.path).map_err(io_error(&self.path))?;
+        let (records, _) = scan(&self.path, &bytes)?;
+        records
+            .into_iter()
+            .map(|(sequence, range)| {
+                DeltaBatch::decode(&mut Decoder::new(&bytes[range]))
+                    .map(|batch| (sequence, batch))
+                    .map_err(|reason| corrupt(&self.path, &format!("record {sequence}: {reason}")))
+            })
+            .collect()
+    }
+
+    /// Records with a sequence number above `sequence`.
+    pub fn entries_after(&self, sequence: u64) -> Result<Vec<(u64, DeltaBatch)>, HitLeidenError> {
+        let mut entries = self.entries()?;
+        entries.retain(|&(s, _)| s > sequence);
+        Ok(entries)
+    }
+
+    /// Drop every record up to and including `sequence`, typically once a
+    /// checkpoint covers them. The log is rewritten and swapped in by rename.
+    pub fn truncate_through(&mut self, sequence: u64) -> Result<(), HitLeidenError> {
+        let bytes = fs::read(&self.path).map_err(io_error(&self.path))?;
+        let (records, _) = scan(&self.path, &bytes)?;
+        let mut kept = bytes[..HEADER_LEN].to_vec();
+        for (s, range) in records {
+            if s > sequence {
+                kept.extend_from_slice(&bytes[range.start - RECORD_HEADER_LEN..range.end + 8]);
+            }
+        }
+
+        let io_error = io_error(&self.path);
+        replace_file(&self.path, &kept).map_err(&io_error)?;
+        self.file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .map_err(&io_error)?;
+        Ok(())
+    }
+}
+
+/// Sequence number and payload byte range of each record.
+type Records = Vec<(u64, Range<usize>)>;
+
+fn record_checksum(sequence: u64, payload: &[u8]) -> u64 {
+    let mut bytes = sequence.to_le_bytes().to_vec();
+    bytes.extend_from_slice(payload);
+    fnv1a64(&bytes)
+}
+
+fn io_error(path: &Path) -> impl Fn(std::io::Error) -> HitLeidenError {
+    let path = path.display().to_string();
+    move |e| HitLeidenError::Checkpoint(format!("{path}: {e}"))
+}
+
+fn corrupt(path: &Path, reason: &str) -> HitLeidenError {
+    HitLeidenError::Checkpoint(format!("{}: corrupt delta log: {reason}", path.display()))
+}
+
+/// Sequence number and payload range of every intact record, and the length
+/// of the intact prefix. A damaged record at the very end is a torn write and
+/// ends the prefix; one followed by more data is an error.
+fn scan(path: &Path, bytes: &[u8]) -> Result<(Records, usize), HitLeidenError> {
+    if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+        return Err(corrupt(path, "not a delta log"));
+    }
+    let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+    if version != FORMAT_VERSION {
+        return Err(HitLeidenError::Checkpoint(format!(
+            "{}: delta log format version {version} is not supported; \
+             this build reads version {FORMAT_VERSION}",
+            path.display()
+        )));
+    }
+
+    let mut records = Vec::new();
+    let mut position = HEADER_LEN;
+    let mut last_sequence = None;
+    while position < bytes.len() {
+        let mut input = Decoder::new(&bytes[position..]);
+        let record = (|| {
+            let sequence = input.u64()?;
+            let len = input.usize()?;
+            let payload = input.bytes(len)?;
+            let stored = input.u64()?;
+            if stored != record_checksum(sequence, payload) {
+                return Err("checksum mismatch".to_string());
+            }
+            Ok((sequence, len))
+        })();
+        let (sequence, len) = match record {
+            Ok(record) => record,
+            // Nothing intact can follow a record that runs to the end of the file
+            Err(_) if !is_followed_by_data(bytes, position) => break,
+            Err(reason) => {
+                return Err(corrupt(
+                    path,
+                    &format!("record at byte {position}: {reason}"),
+                ))
+            }
+        };
+        if last_sequence.is_some_and(|last| sequence <= last) {
+            return Err(corrupt(
+                path,
+                &format!("record {sequence} at byte {position} is out of order"),
+            ));
+        }
+        last_sequence = Some(sequence);
+        let start = position + RECORD_HEADER_LEN;
+        records.push((sequence, start..start + len));
+        position += input.position();
+    }
+    Ok((records, position.min(bytes.len())))
+}
+
+/// Whether the damaged record at `position` declares an end before the end
+/// of the file, i.e. it is not simply the last write cut short.
+fn is_followed_by_data(bytes: &[u8], position: usize) -> bool {
+    let mut input = Decoder::new(&bytes[position..]);
+    let declared_end = input
+        .u64()
+        .and_then(|_| input.usize())
+        .ok()
+        .and_then(|len| (position + RECORD_HEADER_LEN).checked_add(len))
+        .and_then(|end| end.checked_add(8));
+    declared_end.is_some_and(|end| end < bytes.len())
+}
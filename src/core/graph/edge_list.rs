@@ -0,0 +1,216 @@
+//! Text edge lists: one `src dst [weight]` edge per line.
+
+use crate::core::binary::fnv1a64;
+use crate::core::error::HitLeidenError;
+use crate::core::types::{GraphDataset, GraphFormat, GraphInput, GraphSourceType};
+use std::io::Write;
+use std::path::Path;
+
+/// How the fields of an edge-list line are separated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Separator {
+    /// Commas on lines that contain one, whitespace otherwise.
+    #[default]
+    Auto,
+    /// Runs of spaces and tabs.
+    Whitespace,
+    /// Commas, as in CSV.
+    Comma,
+    /// Single tabs, as in TSV.
+    Tab,
+}
+
+/// Id of the first node in an edge list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IdBase {
+    #[default]
+    Zero,
+    /// Ids start at 1, as in Matrix Market and many published datasets;
+    /// node `i` of the file becomes node `i - 1` of the graph.
+    One,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EdgeListOptions {
+    pub separator: Separator,
+    pub id_base: IdBase,
+    /// Characters that start a comment, running to the end of the line.
+    pub comment_chars: Vec<char>,
+    /// Skip the first line that is not blank or a comment, e.g. a CSV header.
+    pub header: bool,
+    /// Node count of the graph; `None` takes one more than the largest id, so
+    /// trailing isolated nodes need it to be set.
+    pub node_count: Option<usize>,
+}
+
+impl Default for EdgeListOptions {
+    fn default() -> Self {
+        Self {
+            separator: Separator::Auto,
+            id_base: IdBase::Zero,
+            comment_chars: vec!['#', '%'],
+            header: false,
+            node_count: None,
+        }
+    }
+}
+
+/// Parse the edge list in `text` into a graph named `dataset_id`.
+///
+/// Every line that is not blank or a comment must hold two node ids and an
+/// optional weight, which must be finite and positive; an edge without one
+/// weighs 1. Errors name the offending line.
+pub fn parse_edge_list(
+    text: &str,
+    dataset_id: &str,
+    options: &EdgeListOptions,
+) -> Result<GraphInput, HitLeidenError> {
+    parse(text, dataset_id, options).map_err(HitLeidenError::InvalidInput)
+}
+
+/// Read and parse the edge list at `path`, describing it in a [`GraphDataset`]
+/// whose id is the file stem and whose checksum covers the file's bytes.
+pub fn load_edge_list(
+    path: impl AsRef<Path>,
+    options: &EdgeListOptions,
+) -> Result<(GraphInput, GraphDataset), HitLeidenError> {
+    let path = path.as_ref();
+    let invalid = |e: String| HitLeidenError::InvalidInput(format!("{}: {e}", path.display()));
+
+    let bytes = std::fs::read(path).map_err(|e| invalid(e.to_string()))?;
+    let text = std::str::from_utf8(&bytes).map_err(|e| invalid(e.to_string()))?;
+    let dataset_id = path.file_stem().map_or_else(
+        || path.display().to_string(),
+        |s| s.to_string_lossy().into_owned(),
+    );
+    let graph = parse(text, &dataset_id, options).map_err(invalid)?;
+
+    let dataset = GraphDataset {
+        dataset_id,
+        source_uri: path.display().to_string(),
+        is_weighted: graph.edges.iter().any(|(_, _, w)| w.is_some()),
+        node_count: graph.node_count,
+        edge_count: graph.edges.len(),
+        checksum: format!("fnv1a64:{:016x}", fnv1a64(&bytes)),
+        format: GraphFormat::EdgeList,
+        mmap_compatible: false,
+        mmap_path: None,
+        source_type: GraphSourceType::File,
+        source_snapshot_id: None,
+    };
+    Ok((graph, dataset))
+}
+
+/// Write `graph` as an edge list laid out as `options` describe, with a
+/// weight column on the edges that have one. `Auto` separates by spaces and
+/// `header` writes a header line; comments and the node count are not written.
+pub fn write_edge_list(
+    graph: &GraphInput,
+    options: &EdgeListOptions,
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    let separator = match options.separator {
+        Separator::Auto | Separator::Whitespace => " ",
+        Separator::Comma => ",",
+        Separator::Tab => "\t",
+    };
+    let base = match options.id_base {
+        IdBase::Zero => 0,
+        IdBase::One => 1,
+    };
+    if options.header {
+        writeln!(out, "src{separator}dst{separator}weight")?;
+    }
+    for &(u, v, w) in &graph.edges {
+        let (u, v) = (u + base, v + base);
+        match w {
+            Some(w) => writeln!(out, "{u}{separator}{v}{separator}{w}")?,
+            None => writeln!(out, "{u}{separator}{v}")?,
+        }
+    }
+    out.flush()
+}
+
+fn parse(text: &str, dataset_id: &str, options: &EdgeListOptions) -> Result<GraphInput, String> {
+    let mut edges = Vec::new();
+    let mut inferred_count = 0;
+    let mut header = options.header;
+    for (index, line) in text.lines().enumerate() {
+        let at_line = |e: String| format!("line {}: {e}", index + 1);
+        let line = match line.find(options.comment_chars.as_slice()) {
+            Some(start) => &line[..start],
+            None => line,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if header {
+            header = false;
+            continue;
+        }
+
+        let fields = split(line, options.separator);
+        if !(2..=3).contains(&fields.len()) {
+            return Err(at_line(format!(
+                "expected `src dst [weight]`, found {} fields",
+                fields.len()
+            )));
+        }
+        let src = parse_node_id(fields[0], options.id_base).map_err(at_line)?;
+        let dst = parse_node_id(fields[1], options.id_base).map_err(at_line)?;
+        let weight = fields
+            .get(2)
+            .map(|&w| parse_weight(w))
+            .transpose()
+            .map_err(at_line)?;
+        let largest = src.max(dst);
+        if let Some(node_count) = options.node_count {
+            if largest >= node_count {
+                return Err(at_line(format!(
+                    "node {largest} is out of range for {node_count} nodes"
+                )));
+            }
+        }
+        // Without a declared count, the graph ends at the largest id
+        let needed = largest
+            .checked_add(1)
+            .ok_or_else(|| at_line(format!("node {largest} is too large to count")))?;
+        inferred_count = inferred_count.max(needed);
+        edges.push((src, dst, weight));
+    }
+
+    Ok(GraphInput {
+        dataset_id: dataset_id.to_string(),
+        node_count: options.node_count.unwrap_or(inferred_count),
+        edges,
+    })
+}
+
+fn split(line: &str, separator: Separator) -> Vec<&str> {
+    match separator {
+        Separator::Auto if line.contains(',') => line.split(',').map(str::trim).collect(),
+        Separator::Auto | Separator::Whitespace => line.split_whitespace().collect(),
+        Separator::Comma => line.split(',').map(str::trim).collect(),
+        Separator::Tab => line.trim().split('\t').map(str::trim).collect(),
+    }
+}
+
+pub(crate) fn parse_node_id(field: &str, base: IdBase) -> Result<usize, String> {
+    let id: usize = field
+        .parse()
+        .map_err(|_| format!("`{field}` is not a node id"))?;
+    match base {
+        IdBase::Zero => Ok(id),
+        IdBase::One => id
+            .checked_sub(1)
+            .ok_or_else(|| "node id 0 in a 1-based edge list".to_string()),
+    }
+}
+
+fn parse_weight(field: &str) -> Result<f64, String> {
+    match field.parse::<f64>() {
+        Ok(w) if w.is_finite() && w > 0.0 => Ok(w),
+        Ok(_) => Err(format!("weight `{field}` must be finite and > 0")),
+        Err(_) => Err(format!("`{field}` is not a weight")),
+    }
+}
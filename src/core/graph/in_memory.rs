@@ -1,5 +1,24 @@
+use crate::core::binary::{Decoder, Encoder};
 use crate::core::types::GraphInput;
 
+/// Entries whose weight falls to or below this are treated as deleted.
+const WEIGHT_EPSILON: f64 = 1e-9;
+
+/// Adjacency in CSR layout that can also be edited in place.
+///
+/// Node `i` owns the slot `offsets[i]..offsets[i] + capacities[i]` of
+/// `neighbors`/`weights`, of which the first `degrees[i]` entries are live.
+/// Slots are contiguous and ordered after a fresh build, but a node whose slot
+/// overflows is moved to the end of the arrays with room to grow, so slots are
+/// not ordered in general and `offsets[node_count]` is only the end of storage.
+///
+/// An undirected graph lists every edge from both endpoints. A directed graph
+/// lists each arc `u → v` under `u` in these arrays, its out-adjacency, and
+/// under `v` in a second adjacency of the same layout, its in-adjacency.
+///
+/// An undirected self-loop of weight `w` is listed twice under its vertex, so
+/// it adds `2w` to the vertex's strength but `w` to the total weight, which is
+/// the `A_ii = 2w` convention of modularity.
 #[derive(Clone, Debug, PartialEq)]
 pub struct InMemoryGraph {
     pub node_count: usize,
@@ -7,79 +26,451 @@ pub struct InMemoryGraph {
     pub degrees: Vec<usize>, // Precomputed for O(1) lookup
     pub neighbors: Vec<usize>,
     pub weights: Vec<f64>,
+    capacities: Vec<usize>,
+    /// Storage entries no longer owned by any slot, reclaimed by `compact`.
+    dead_entries: usize,
     cached_total_weight: f64,
+    /// In-adjacency of a directed graph; `None` for an undirected one.
+    incoming: Option<Box<InMemoryGraph>>,
 }
 
 impl From<&GraphInput> for InMemoryGraph {
     fn from(value: &GraphInput) -> Self {
-        let mut degrees = vec![0; value.node_count];
-        for &(u, v, _) in &value.edges {
-            degrees[u] += 1;
-            degrees[v] += 1;
+        let entries = value.edges.iter().flat_map(|&(u, v, w)| {
+            let weight = w.unwrap_or(1.0);
+            [(u, v, weight), (v, u, weight)]
+        });
+        let mut graph = Self::from_entries(value.node_count, entries);
+        graph.cached_total_weight = graph.weights.iter().sum::<f64>() / 2.0;
+        graph
+    }
+}
+
+impl InMemoryGraph {
+    /// Build a directed graph with an arc `u → v` for every `(u, v, w)` of
+    /// `value`. A loop `u → u` of weight `w` adds `w` to both the out- and
+    /// in-strength of `u`.
+    pub fn directed(value: &GraphInput) -> Self {
+        let arcs = || {
+            value
+                .edges
+                .iter()
+                .map(|&(u, v, w)| (u, v, w.unwrap_or(1.0)))
+        };
+        let mut graph = Self::from_entries(value.node_count, arcs());
+        let incoming = Self::from_entries(value.node_count, arcs().map(|(u, v, w)| (v, u, w)));
+        graph.cached_total_weight = graph.weights.iter().sum::<f64>();
+        graph.incoming = Some(Box::new(incoming));
+        graph
+    }
+
+    /// Adjacency listing `b` with weight `w` under `a` for every `(a, b, w)`
+    /// of `entries`, in order.
+    fn from_entries(
+        node_count: usize,
+        entries: impl Iterator<Item = (usize, usize, f64)> + Clone,
+    ) -> Self {
+        let mut degrees = vec![0; node_count];
+        for (a, _, _) in entries.clone() {
+            degrees[a] += 1;
         }
 
-        let mut offsets = vec![0; value.node_count + 1];
-        for i in 0..value.node_count {
+        let mut offsets = vec![0; node_count + 1];
+        for i in 0..node_count {
             offsets[i + 1] = offsets[i] + degrees[i];
         }
 
-        let total_entries = offsets[value.node_count];
+        let total_entries = offsets[node_count];
         let mut neighbors = vec![0; total_entries];
         let mut weights = vec![0.0; total_entries];
         let mut current_offsets = offsets.clone();
-
-        for &(u, v, w) in &value.edges {
-            let weight = w.unwrap_or(1.0);
-
-            let u_offset = current_offsets[u];
-            neighbors[u_offset] = v;
-            weights[u_offset] = weight;
-            current_offsets[u] += 1;
-
-            let v_offset = current_offsets[v];
-            neighbors[v_offset] = u;
-            weights[v_offset] = weight;
-            current_offsets[v] += 1;
+        for (a, b, weight) in entries {
+            let offset = current_offsets[a];
+            neighbors[offset] = b;
+            weights[offset] = weight;
+            current_offsets[a] += 1;
         }
 
-        let cached_total_weight = weights.iter().sum::<f64>() / 2.0;
-
-        // Precompute degrees for O(1) neighbor iteration
-        let degrees: Vec<usize> = (0..value.node_count)
-            .map(|i| offsets[i + 1] - offsets[i])
-            .collect();
-
         Self {
-            node_count: value.node_count,
+            node_count,
             offsets,
+            capacities: degrees.clone(),
             degrees,
             neighbors,
             weights,
-            cached_total_weight,
+            dead_entries: 0,
+            cached_total_weight: 0.0,
+            incoming: None,
         }
     }
-}
 
-impl InMemoryGraph {
-    /// Iterate over (neighbor, weight) pairs for a node.
+    pub fn is_directed(&self) -> bool {
+        self.incoming.is_some()
+    }
+
+    /// Iterate over (neighbor, weight) pairs for a node. On a directed graph
+    /// these are its out-arcs followed by its in-arcs, so a neighbour joined
+    /// both ways is listed twice.
     /// Uses precomputed degree for single-load bound calculation.
     #[inline]
     pub fn neighbors(&self, node: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
-        let start = self.offsets[node];
-        let count = self.degrees[node]; // Single load instead of offsets[node+1]
-        self.neighbors[start..start + count]
+        let (neighbors, weights) = self.slot(node);
+        let (in_neighbors, in_weights) = match &self.incoming {
+            Some(incoming) => incoming.slot(node),
+            None => (&[][..], &[][..]),
+        };
+        neighbors
             .iter()
+            .chain(in_neighbors)
             .copied()
-            .zip(self.weights[start..start + count].iter().copied())
+            .zip(weights.iter().chain(in_weights).copied())
+    }
+
+    /// Targets of the arcs leaving `node`; all neighbours when undirected.
+    pub fn out_neighbors(&self, node: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let (neighbors, weights) = self.slot(node);
+        neighbors.iter().copied().zip(weights.iter().copied())
     }
 
-    /// Get node degree in O(1) time.
+    /// Sources of the arcs entering `node`; all neighbours when undirected.
+    pub fn in_neighbors(&self, node: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        self.incoming.as_deref().unwrap_or(self).out_neighbors(node)
+    }
+
+    /// Weight leaving and entering `node`. Each undirected edge counts half
+    /// in either direction, so the two always add up to the node's strength.
+    pub fn arc_strengths(&self, node: usize) -> (f64, f64) {
+        let out: f64 = self.out_neighbors(node).map(|(_, w)| w).sum();
+        match &self.incoming {
+            Some(incoming) => (out, incoming.out_neighbors(node).map(|(_, w)| w).sum()),
+            None => (out / 2.0, out / 2.0),
+        }
+    }
+
+    /// Live entries of the slot of `node`.
+    #[inline]
+    fn slot(&self, node: usize) -> (&[usize], &[f64]) {
+        let start = self.offsets[node];
+        let count = self.degrees[node]; // Single load instead of offsets[node+1]
+        (
+            &self.neighbors[start..start + count],
+            &self.weights[start..start + count],
+        )
+    }
+
+    /// Graph over compact CSR arrays laid out as a fresh build lays them out,
+    /// the entries of node `i` being `offsets[i]..offsets[i + 1]`. `incoming`,
+    /// the in-adjacency arrays in the same layout, makes the graph directed.
+    /// The caller checks that the arrays are consistent.
+    pub(crate) fn from_csr(
+        offsets: Vec<usize>,
+        neighbors: Vec<usize>,
+        weights: Vec<f64>,
+        incoming: Option<(Vec<usize>, Vec<usize>, Vec<f64>)>,
+    ) -> Self {
+        let mut graph = Self::from_arrays(offsets, neighbors, weights);
+        let total_weight: f64 = graph.weights.iter().sum();
+        graph.cached_total_weight = match incoming {
+            Some((offsets, neighbors, weights)) => {
+                graph.incoming = Some(Box::new(Self::from_arrays(offsets, neighbors, weights)));
+                total_weight
+            }
+            None => total_weight / 2.0,
+        };
+        graph
+    }
+
+    /// Adjacency over compact arrays, with no slack.
+    fn from_arrays(offsets: Vec<usize>, neighbors: Vec<usize>, weights: Vec<f64>) -> Self {
+        let degrees: Vec<usize> = offsets.windows(2).map(|w| w[1] - w[0]).collect();
+        Self {
+            node_count: degrees.len(),
+            offsets,
+            capacities: degrees.clone(),
+            degrees,
+            neighbors,
+            weights,
+            dead_entries: 0,
+            cached_total_weight: 0.0,
+            incoming: None,
+        }
+    }
+
+    /// Get node degree in O(1) time; in- plus out-degree when directed.
     #[inline]
     pub fn degree(&self, node: usize) -> usize {
-        self.degrees[node] // Direct lookup, no subtraction
+        self.degrees[node] + self.incoming.as_ref().map_or(0, |g| g.degrees[node])
     }
 
     pub fn total_weight(&self) -> f64 {
         self.cached_total_weight
     }
+
+    /// Export the live edges as an edge list, each edge once with `u <= v`, or
+    /// each arc once as `(source, target)` when directed.
+    pub fn to_graph_input(&self, dataset_id: &str) -> GraphInput {
+        let mut edges = Vec::new();
+        for u in 0..self.node_count {
+            if self.is_directed() {
+                edges.extend(self.out_neighbors(u).map(|(v, w)| (u, v, Some(w))));
+                continue;
+            }
+            // Self-loops are stored as two entries; emit every other one
+            let mut self_loop_seen = false;
+            for (v, w) in self.neighbors(u) {
+                if v == u {
+                    if self_loop_seen {
+                        edges.push((u, v, Some(w)));
+                    }
+                    self_loop_seen = !self_loop_seen;
+                } else if u < v {
+                    edges.push((u, v, Some(w)));
+                }
+            }
+        }
+        GraphInput {
+            dataset_id: dataset_id.to_string(),
+            node_count: self.node_count,
+            edges,
+        }
+    }
+
+    /// Merge an edge delta into the graph in place (G ⊕ ΔG).
+    ///
+    /// Positive weights insert or strengthen an edge, negative weights weaken or
+    /// delete it; an edge whose weight drops to zero is removed. The graph grows
+    /// to `delta.node_count` nodes if that is larger.
+    pub fn apply_delta(&mut self, delta: &GraphInput) {
+        if delta.node_count > self.node_count {
+            self.add_nodes(delta.node_count - self.node_count);
+        }
+        for &(u, v, w) in &delta.edges {
+            self.adjust_edge(u, v, w.unwrap_or(1.0));
+        }
+    }
+
+    /// Add `alpha` to the weight of edge (u, v), inserting it if absent and
+    /// deleting it if the weight reaches zero. Returns the resulting weight.
+    /// On a directed graph every edge method addresses the arc `u → v`.
+    pub fn adjust_edge(&mut self, u: usize, v: usize, alpha: f64) -> f64 {
+        let old = self.edge_weight(u, v).unwrap_or(0.0);
+        let new = old + alpha;
+        if new > WEIGHT_EPSILON {
+            self.set_edge(u, v, new);
+            new
+        } else {
+            self.remove_edge(u, v);
+            0.0
+        }
+    }
+
+    /// Weight of edge (u, v), if present.
+    pub fn edge_weight(&self, u: usize, v: usize) -> Option<f64> {
+        self.find_entry(u, v).map(|i| self.weights[i])
+    }
+
+    /// Insert edge (u, v) with weight `w`, or overwrite its weight if present.
+    pub fn set_edge(&mut self, u: usize, v: usize, w: f64) {
+        let old = self.edge_weight(u, v);
+        if let Some(incoming) = self.incoming.as_deref_mut() {
+            incoming.set_entry(v, u, w);
+            self.set_entry(u, v, w);
+        } else if u == v {
+            // A self-loop is stored as two entries in its node's adjacency
+            match old {
+                Some(_) => {
+                    for nth in 0..2 {
+                        if let Some(i) = self.find_entry_nth(u, u, nth) {
+                            self.weights[i] = w;
+                        }
+                    }
+                }
+                None => {
+                    self.push_entry(u, u, w);
+                    self.push_entry(u, u, w);
+                }
+            }
+        } else {
+            self.set_entry(u, v, w);
+            self.set_entry(v, u, w);
+        }
+        self.cached_total_weight += w - old.unwrap_or(0.0);
+    }
+
+    /// Delete edge (u, v). Returns its weight, if it was present.
+    pub fn remove_edge(&mut self, u: usize, v: usize) -> Option<f64> {
+        let old = self.edge_weight(u, v)?;
+        if let Some(incoming) = self.incoming.as_deref_mut() {
+            incoming.remove_entry(v, u);
+            self.remove_entry(u, v);
+        } else {
+            self.remove_entry(u, v);
+            self.remove_entry(v, u);
+        }
+        self.cached_total_weight -= old;
+        Some(old)
+    }
+
+    /// Append `count` isolated nodes.
+    pub fn add_nodes(&mut self, count: usize) {
+        let end = self.neighbors.len();
+        self.offsets.truncate(self.node_count);
+        self.offsets.extend(std::iter::repeat(end).take(count + 1));
+        self.degrees.extend(std::iter::repeat(0).take(count));
+        self.capacities.extend(std::iter::repeat(0).take(count));
+        self.node_count += count;
+        if let Some(incoming) = self.incoming.as_deref_mut() {
+            incoming.add_nodes(count);
+        }
+    }
+
+    /// Rewrite storage so that slots are contiguous, ordered and hole-free.
+    pub fn compact(&mut self) {
+        let live: usize = self.degrees.iter().sum();
+        let mut neighbors = Vec::with_capacity(live);
+        let mut weights = Vec::with_capacity(live);
+        for node in 0..self.node_count {
+            let start = self.offsets[node];
+            let end = start + self.degrees[node];
+            self.offsets[node] = neighbors.len();
+            self.capacities[node] = self.degrees[node];
+            neighbors.extend_from_slice(&self.neighbors[start..end]);
+            weights.extend_from_slice(&self.weights[start..end]);
+        }
+        self.offsets[self.node_count] = neighbors.len();
+        self.neighbors = neighbors;
+        self.weights = weights;
+        self.dead_entries = 0;
+        if let Some(incoming) = self.incoming.as_deref_mut() {
+            incoming.compact();
+        }
+    }
+
+    fn find_entry(&self, u: usize, v: usize) -> Option<usize> {
+        self.find_entry_nth(u, v, 0)
+    }
+
+    fn find_entry_nth(&self, u: usize, v: usize, nth: usize) -> Option<usize> {
+        let start = self.offsets[u];
+        (start..start + self.degrees[u])
+            .filter(|&i| self.neighbors[i] == v)
+            .nth(nth)
+    }
+
+    /// Overwrite the weight of the (u, v) entry, adding it if absent.
+    fn set_entry(&mut self, u: usize, v: usize, w: f64) {
+        match self.find_entry(u, v) {
+            Some(i) => self.weights[i] = w,
+            None => self.push_entry(u, v, w),
+        }
+    }
+
+    fn push_entry(&mut self, u: usize, v: usize, w: f64) {
+        if self.degrees[u] == self.capacities[u] {
+            self.relocate(u);
+        }
+        let i = self.offsets[u] + self.degrees[u];
+        self.neighbors[i] = v;
+        self.weights[i] = w;
+        self.degrees[u] += 1;
+    }
+
+    /// Remove one (u, v) entry, keeping the order of the remaining ones.
+    fn remove_entry(&mut self, u: usize, v: usize) {
+        if let Some(i) = self.find_entry(u, v) {
+            let end = self.offsets[u] + self.degrees[u];
+            self.neighbors.copy_within(i + 1..end, i);
+            self.weights.copy_within(i + 1..end, i);
+            self.degrees[u] -= 1;
+        }
+    }
+
+    /// Move the slot of `node` to the end of storage with twice its capacity.
+    fn relocate(&mut self, node: usize) {
+        if self.dead_entries > self.neighbors.len() / 2 {
+            self.compact();
+        }
+        let start = self.offsets[node];
+        let count = self.degrees[node];
+        let capacity = (2 * count).max(4);
+        let new_start = self.neighbors.len();
+        self.neighbors.extend_from_within(start..start + count);
+        self.weights.extend_from_within(start..start + count);
+        self.neighbors.resize(new_start + capacity, 0);
+        self.weights.resize(new_start + capacity, 0.0);
+        self.dead_entries += self.capacities[node];
+        self.offsets[node] = new_start;
+        self.capacities[node] = capacity;
+        self.offsets[self.node_count] = self.neighbors.len();
+    }
+
+    /// Append the exact storage layout, slack included, so a decoded graph
+    /// iterates neighbours in the same order. A directed graph's in-adjacency
+    /// is written separately by [`InMemoryGraph::encode_incoming`].
+    pub(crate) fn encode(&self, out: &mut Encoder) {
+        out.usize(self.node_count);
+        out.usizes(&self.offsets);
+        out.usizes(&self.degrees);
+        out.usizes(&self.capacities);
+        out.usizes(&self.neighbors);
+        out.f64s(&self.weights);
+        out.usize(self.dead_entries);
+        out.f64(self.cached_total_weight);
+    }
+
+    /// Read a graph written by [`InMemoryGraph::encode`], checking that every
+    /// slot lies within storage and every live entry names a valid node.
+    pub(crate) fn decode(input: &mut Decoder) -> Result<Self, String> {
+        let graph = Self {
+            node_count: input.usize()?,
+            offsets: input.usizes()?,
+            degrees: input.usizes()?,
+            capacities: input.usizes()?,
+            neighbors: input.usizes()?,
+            weights: input.f64s()?,
+            dead_entries: input.usize()?,
+            cached_total_weight: input.f64()?,
+            incoming: None,
+        };
+        let n = graph.node_count;
+        if graph.offsets.len() != n + 1
+            || graph.degrees.len() != n
+            || graph.capacities.len() != n
+            || graph.weights.len() != graph.neighbors.len()
+        {
+            return Err(format!("graph arrays do not match its {n} nodes"));
+        }
+        for node in 0..n {
+            let end = graph.offsets[node].checked_add(graph.capacities[node]);
+            if graph.degrees[node] > graph.capacities[node]
+                || end.map_or(true, |end| end > graph.neighbors.len())
+            {
+                return Err(format!("slot of node {node} lies outside storage"));
+            }
+            if let Some((neighbor, _)) = graph.neighbors(node).find(|&(v, _)| v >= n) {
+                return Err(format!("node {node} has out-of-range neighbour {neighbor}"));
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Append the in-adjacency of a directed graph in the layout of
+    /// [`InMemoryGraph::encode`].
+    pub(crate) fn encode_incoming(&self, out: &mut Encoder) {
+        if let Some(incoming) = &self.incoming {
+            incoming.encode(out);
+        }
+    }
+
+    /// Make the graph directed with the in-adjacency read back from
+    /// [`InMemoryGraph::encode_incoming`], checking it mirrors the out-adjacency.
+    pub(crate) fn decode_incoming(&mut self, input: &mut Decoder) -> Result<(), String> {
+        let incoming = Self::decode(input)?;
+        let live = |graph: &Self| graph.degrees.iter().sum::<usize>();
+        if incoming.node_count != self.node_count || live(&incoming) != live(self) {
+            return Err("in-adjacency does not mirror the out-adjacency".to_string());
+        }
+        self.incoming = Some(Box::new(incoming));
+        Ok(())
+    }
 }
@@ -1,4 +1,8 @@
 pub mod backend;
+pub mod csr_binary;
+pub mod delta;
+pub mod delta_log;
+pub mod edge_list;
 pub mod in_memory;
 pub mod mmap;
 pub mod mmap_probe;
@@ -1,10 +1,13 @@
 pub mod algorithm;
 pub mod backend;
+pub mod binary;
 pub mod config;
+pub mod durable;
 pub mod error;
 pub mod graph;
 pub mod partition;
 pub mod report;
 pub mod runtime;
+pub mod session;
 pub mod types;
 pub mod validation;
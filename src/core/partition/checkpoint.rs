@@ -0,0 +1,337 @@
+use crate::core::binary::{fnv1a64, replace_file, Decoder, Encoder};
+use crate::core::config::RunConfig;
+use crate::core::error::HitLeidenError;
+use crate::core::graph::in_memory::InMemoryGraph;
+use crate::core::partition::lineage::CommunityLineage;
+use crate::core::partition::stable_ids::StableCommunityIds;
+use crate::core::partition::state::PartitionState;
+use crate::core::session::IncrementalSession;
+use bitvec::prelude::*;
+use std::fs;
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"HLCKPT\0\0";
+const SESSION_MAGIC: &[u8; 8] = b"HLSESS\0\0";
+
+/// Format version written by this build. Version 2 appended the in-adjacency
+/// of directed supergraphs. Version 3 drops the level-0 community and node
+/// weight arrays, both derived from the level-0 node weights stored with the
+/// other levels, so only version-3 readers can read it. This build reads
+/// versions 1 to 3.
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Format version of session checkpoints written by this build.
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+
+/// Header size: magic, format version, minimum reader version, payload length.
+const HEADER_LEN: usize = 8 + 4 + 4 + 8;
+
+/// Checkpoints of a [`PartitionState`].
+///
+/// A checkpoint is a header — magic bytes, the format version it was written
+/// with, the oldest format version able to read it, and the payload length —
+/// followed by the little-endian payload and its FNV-1a 64 checksum. Readers
+/// reject checkpoints whose minimum reader version is newer than
+/// [`FORMAT_VERSION`]; a newer but compatible writer may append fields to the
+/// payload, which older readers skip.
+impl PartitionState {
+    /// Serialise the whole state: supergraphs, per-level mappings and weights.
+    pub fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut payload = Encoder::default();
+        payload.usizes(&self.node_to_comm);
+        let removed: Vec<usize> = self.removed_nodes.iter_ones().collect();
+        payload.usizes(&removed);
+        payload.usize(self.levels);
+        payload.usize(self.supergraphs.len());
+        for graph in &self.supergraphs {
+            graph.encode(&mut payload);
+        }
+        for mappings in [
+            &self.community_mapping_per_level,
+            &self.refined_community_mapping_per_level,
+            &self.previous_subcommunity_mapping_per_level,
+            &self.current_subcommunity_mapping_per_level,
+        ] {
+            payload.usize(mappings.len());
+            for mapping in mappings {
+                payload.usizes(mapping);
+            }
+        }
+        payload.usize(self.node_weights_per_level.len());
+        for weights in &self.node_weights_per_level {
+            payload.f64s(weights);
+        }
+        let directed: Vec<_> = self
+            .supergraphs
+            .iter()
+            .filter(|g| g.is_directed())
+            .collect();
+        payload.usize(directed.len());
+        for graph in &directed {
+            graph.encode_incoming(&mut payload);
+        }
+        // Readers of versions 1 and 2 would look for the level-0 weight arrays
+        seal(MAGIC, FORMAT_VERSION, FORMAT_VERSION, payload.into_bytes())
+    }
+
+    /// Read a state written by [`PartitionState::to_checkpoint_bytes`].
+    pub fn from_checkpoint_bytes(bytes: &[u8]) -> Result<Self, HitLeidenError> {
+        let corrupt = |reason: String| HitLeidenError::Checkpoint(reason);
+        let (version, payload) = unseal(bytes, MAGIC, FORMAT_VERSION).map_err(corrupt)?;
+        let mut payload = Decoder::new(payload);
+        let state = decode_state(&mut payload, version).map_err(|reason| {
+            corrupt(format!("payload at byte {}: {reason}", payload.position()))
+        })?;
+        if version == FORMAT_VERSION && !payload.is_at_end() {
+            return Err(corrupt("unexpected data after the state".to_string()));
+        }
+        check_consistency(&state).map_err(corrupt)?;
+        Ok(state)
+    }
+
+    /// Write a checkpoint to `path`, replacing it atomically where the
+    /// platform's rename does.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), HitLeidenError> {
+        write_atomically(path.as_ref(), &self.to_checkpoint_bytes())
+    }
+
+    /// Read a checkpoint written by [`PartitionState::save`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, HitLeidenError> {
+        Self::from_checkpoint_bytes(&read(path.as_ref())?)
+    }
+}
+
+/// Checkpoints of an [`IncrementalSession`]: its [`PartitionState`] checkpoint
+/// followed by the session version, the [`StableCommunityIds`] and the
+/// [`CommunityLineage`], sealed like a state checkpoint under their own magic
+/// bytes and [`SESSION_FORMAT_VERSION`].
+impl IncrementalSession {
+    /// Serialise the state and the history of its communities.
+    pub fn to_checkpoint_bytes(&self) -> Vec<u8> {
+        let mut payload = Encoder::default();
+        let state = self.state().to_checkpoint_bytes();
+        payload.usize(state.len());
+        payload.bytes(&state);
+        payload.u64(self.version());
+        self.community_ids().encode(&mut payload);
+        self.lineage().encode(&mut payload);
+        seal(
+            SESSION_MAGIC,
+            SESSION_FORMAT_VERSION,
+            SESSION_FORMAT_VERSION,
+            payload.into_bytes(),
+        )
+    }
+
+    /// Resume a session written by [`IncrementalSession::to_checkpoint_bytes`]
+    /// with `config`. Unlike [`IncrementalSession::restore`], community
+    /// identifiers, the lineage and the version carry on where they were.
+    pub fn from_checkpoint_bytes(bytes: &[u8], config: RunConfig) -> Result<Self, HitLeidenError> {
+        let corrupt = |reason: String| HitLeidenError::Checkpoint(reason);
+        let (version, payload) =
+            unseal(bytes, SESSION_MAGIC, SESSION_FORMAT_VERSION).map_err(corrupt)?;
+        let mut payload = Decoder::new(payload);
+        let at = |payload: &Decoder, reason: String| {
+            corrupt(format!("payload at byte {}: {reason}", payload.position()))
+        };
+        let state_len = payload.count(1).map_err(|e| at(&payload, e))?;
+        let state_bytes = payload.bytes(state_len).map_err(|e| at(&payload, e))?;
+        let state = PartitionState::from_checkpoint_bytes(state_bytes)?;
+        let session_version = payload.u64().map_err(|e| at(&payload, e))?;
+        let ids = StableCommunityIds::decode(&mut payload).map_err(|e| at(&payload, e))?;
+        let lineage = CommunityLineage::decode(&mut payload).map_err(|e| at(&payload, e))?;
+        if version == SESSION_FORMAT_VERSION && !payload.is_at_end() {
+            return Err(corrupt("unexpected data after the session".to_string()));
+        }
+        IncrementalSession::resume(state, config, session_version, ids, lineage)
+    }
+
+    /// Write a session checkpoint to `path`, replacing it atomically where
+    /// the platform's rename does.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), HitLeidenError> {
+        write_atomically(path.as_ref(), &self.to_checkpoint_bytes())
+    }
+
+    /// Resume the session checkpointed to `path` by [`IncrementalSession::save`].
+    pub fn load(path: impl AsRef<Path>, config: RunConfig) -> Result<Self, HitLeidenError> {
+        Self::from_checkpoint_bytes(&read(path.as_ref())?, config)
+    }
+}
+
+/// Header, `payload` and its checksum.
+fn seal(magic: &[u8; 8], version: u32, min_reader_version: u32, payload: Vec<u8>) -> Vec<u8> {
+    let mut out = Encoder::default();
+    out.bytes(magic);
+    out.u32(version);
+    out.u32(min_reader_version);
+    out.usize(payload.len());
+    out.bytes(&payload);
+    out.u64(fnv1a64(&payload));
+    out.into_bytes()
+}
+
+/// Format version and verified payload of bytes written by [`seal`], if a
+/// reader of `reader_version` can read them.
+fn unseal<'a>(
+    bytes: &'a [u8],
+    magic: &[u8; 8],
+    reader_version: u32,
+) -> Result<(u32, &'a [u8]), String> {
+    if bytes.len() < HEADER_LEN || &bytes[..8] != magic {
+        return Err("not a checkpoint".to_string());
+    }
+    let mut input = Decoder::new(&bytes[8..]);
+    let version = input.u32()?;
+    let min_reader_version = input.u32()?;
+    if min_reader_version > reader_version || version < min_reader_version {
+        return Err(format!(
+            "format version {version} needs a reader of version {min_reader_version}; \
+             this build reads version {reader_version}"
+        ));
+    }
+    let payload_len = input.usize()?;
+    let payload = input
+        .bytes(payload_len)
+        .map_err(|_| "payload is truncated".to_string())?;
+    let stored = input
+        .u64()
+        .map_err(|_| "checksum is truncated".to_string())?;
+    if !input.is_at_end() {
+        return Err("trailing bytes after the checksum".to_string());
+    }
+    let computed = fnv1a64(payload);
+    if stored != computed {
+        return Err(format!(
+            "checksum mismatch: stored {stored:016x}, computed {computed:016x}"
+        ));
+    }
+    Ok((version, payload))
+}
+
+fn write_atomically(path: &Path, bytes: &[u8]) -> Result<(), HitLeidenError> {
+    replace_file(path, bytes)
+        .map_err(|e| HitLeidenError::Checkpoint(format!("{}: {e}", path.display())))
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, HitLeidenError> {
+    fs::read(path).map_err(|e| HitLeidenError::Checkpoint(format!("{}: {e}", path.display())))
+}
+
+fn decode_state(input: &mut Decoder, version: u32) -> Result<PartitionState, String> {
+    let node_to_comm = input.usizes()?;
+    if version < 3 {
+        // Level-0 weights, read again with the other levels below
+        input.f64s()?;
+        input.f64s()?;
+    }
+    let mut removed_nodes = bitvec![0; node_to_comm.len()];
+    for node in input.usizes()? {
+        if node >= node_to_comm.len() {
+            return Err(format!("removed node {node} is out of range"));
+        }
+        removed_nodes.set(node, true);
+    }
+    let levels = input.usize()?;
+    let graph_count = input.count(1)?;
+    let mut supergraphs = (0..graph_count)
+        .map(|_| InMemoryGraph::decode(input))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut mappings = Vec::with_capacity(4);
+    for _ in 0..4 {
+        let count = input.count(8)?;
+        let per_level = (0..count)
+            .map(|_| input.usizes())
+            .collect::<Result<Vec<_>, _>>()?;
+        mappings.push(per_level);
+    }
+    let count = input.count(8)?;
+    let node_weights_per_level = (0..count)
+        .map(|_| input.f64s())
+        .collect::<Result<Vec<_>, _>>()?;
+    if version >= 2 {
+        // Every supergraph of a directed state is directed
+        let directed = input.count(8)?;
+        if directed != 0 && directed != supergraphs.len() {
+            return Err(format!(
+                "{directed} of {} supergraphs are directed",
+                supergraphs.len()
+            ));
+        }
+        for graph in supergraphs.iter_mut().take(directed) {
+            graph.decode_incoming(input)?;
+        }
+    }
+
+    let mut mappings = mappings.into_iter();
+    Ok(PartitionState {
+        node_to_comm,
+        removed_nodes,
+        levels,
+        supergraphs,
+        community_mapping_per_level: mappings.next().unwrap(),
+        refined_community_mapping_per_level: mappings.next().unwrap(),
+        previous_subcommunity_mapping_per_level: mappings.next().unwrap(),
+        current_subcommunity_mapping_per_level: mappings.next().unwrap(),
+        node_weights_per_level,
+    })
+}
+
+/// Check that the per-level arrays agree with each other and with the
+/// supergraphs, so a loaded state cannot index out of bounds later.
+fn check_consistency(state: &PartitionState) -> Result<(), String> {
+    let n = state.node_to_comm.len();
+    if state.levels == 0 {
+        return Err("state has no levels".to_string());
+    }
+    if !state.supergraphs.is_empty() && state.supergraphs.len() != state.levels {
+        return Err(format!(
+            "{} supergraphs for {} levels",
+            state.supergraphs.len(),
+            state.levels
+        ));
+    }
+    let per_level = [
+        &state.community_mapping_per_level,
+        &state.refined_community_mapping_per_level,
+        &state.previous_subcommunity_mapping_per_level,
+        &state.current_subcommunity_mapping_per_level,
+    ];
+    if per_level.iter().any(|m| m.len() != state.levels)
+        || state.node_weights_per_level.len() != state.levels
+    {
+        return Err(format!(
+            "per-level arrays do not cover {} levels",
+            state.levels
+        ));
+    }
+    let level_size = |p: usize| state.supergraphs.get(p).map_or(n, |g| g.node_count);
+    if level_size(0) != n {
+        return Err(format!("level-0 graph does not have {n} nodes"));
+    }
+    for p in 0..state.levels {
+        let size = level_size(p);
+        if per_level.iter().any(|m| m[p].len() != size)
+            || state.node_weights_per_level[p].len() != size
+        {
+            return Err(format!("level {p} arrays do not match its {size} vertices"));
+        }
+        // Community labels are level-0 node ids at every level
+        if state.community_mapping_per_level[p].iter().any(|&c| c >= n) {
+            return Err(format!("level {p} community label out of range"));
+        }
+        if p + 1 < state.levels {
+            let next = level_size(p + 1);
+            let mapped_out =
+                |mapping: &Vec<usize>| mapping.iter().any(|&s| s != usize::MAX && s >= next);
+            if mapped_out(&state.current_subcommunity_mapping_per_level[p])
+                || mapped_out(&state.previous_subcommunity_mapping_per_level[p])
+            {
+                return Err(format!("level {p} maps vertices past level {}", p + 1));
+            }
+        }
+    }
+    if state.node_to_comm.iter().any(|&c| c >= n) {
+        return Err("community label out of range".to_string());
+    }
+    Ok(())
+}
@@ -0,0 +1,191 @@
+use crate::core::partition::stable_ids::{CommunityId, LevelChanges, StableCommunityIds};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc::Sender;
+
+/// One change to the communities at a level of the hierarchy, in terms of
+/// [`StableCommunityIds`]. Node sets are sorted level-0 node ids.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommunityEvent {
+    /// A community with a fresh identifier appeared.
+    Created {
+        level: usize,
+        id: CommunityId,
+        members: Vec<usize>,
+    },
+    /// A community disappeared; `members` are the nodes it had.
+    Dissolved {
+        level: usize,
+        id: CommunityId,
+        members: Vec<usize>,
+    },
+    /// Nodes joined a surviving community, including newly added nodes.
+    MembersAdded {
+        level: usize,
+        id: CommunityId,
+        nodes: Vec<usize>,
+    },
+    /// Nodes left a surviving community, including removed nodes.
+    MembersRemoved {
+        level: usize,
+        id: CommunityId,
+        nodes: Vec<usize>,
+    },
+    /// Community `id` was split: part of it went into communities created by
+    /// this update. `into` lists those and `id` itself if it survived.
+    Split {
+        level: usize,
+        id: CommunityId,
+        into: Vec<CommunityId>,
+    },
+    /// Community `id` absorbed communities dissolved by this update. `from`
+    /// lists those and `id` itself if it existed before.
+    Merged {
+        level: usize,
+        id: CommunityId,
+        from: Vec<CommunityId>,
+    },
+}
+
+impl CommunityEvent {
+    /// Hierarchy level the event happened at, the community partition being the last.
+    pub fn level(&self) -> usize {
+        match *self {
+            Self::Created { level, .. }
+            | Self::Dissolved { level, .. }
+            | Self::MembersAdded { level, .. }
+            | Self::MembersRemoved { level, .. }
+            | Self::Split { level, .. }
+            | Self::Merged { level, .. } => level,
+        }
+    }
+}
+
+/// Everything that changed in one update of a session.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommunityChanges {
+    /// Session version the update produced.
+    pub version: u64,
+    /// Ordered by level, then by variant in declaration order, then by id.
+    pub events: Vec<CommunityEvent>,
+}
+
+/// Receiver of the [`CommunityChanges`] of every session update.
+///
+/// Implemented for closures and for channel senders; a sender whose receiver
+/// was dropped silently discards further changes.
+pub trait EventSink: Send {
+    fn publish(&mut self, changes: &CommunityChanges);
+}
+
+impl<F: FnMut(&CommunityChanges) + Send> EventSink for F {
+    fn publish(&mut self, changes: &CommunityChanges) {
+        self(changes)
+    }
+}
+
+impl EventSink for Sender<CommunityChanges> {
+    fn publish(&mut self, changes: &CommunityChanges) {
+        let _ = self.send(changes.clone());
+    }
+}
+
+/// Events turning the communities of `previous` into those of `current`.
+///
+/// Levels are paired the way [`StableCommunityIds::update`] pairs them. A
+/// level of `previous` that no level of `current` descends from reports all
+/// of its communities as dissolved, and a level of `current` without a
+/// predecessor reports all of its communities as created.
+pub fn diff(previous: &StableCommunityIds, current: &StableCommunityIds) -> Vec<CommunityEvent> {
+    from_changes(&current.changes_since(previous))
+}
+
+/// Events describing `changes`, as returned by [`StableCommunityIds::update`].
+/// Only the nodes whose identifier changed are visited.
+pub fn from_changes(changes: &[LevelChanges]) -> Vec<CommunityEvent> {
+    let mut events = Vec::new();
+    for level in changes {
+        level_events(level, &mut events);
+    }
+    events.sort_by_key(|event| event.level());
+    events
+}
+
+fn level_events(changes: &LevelChanges, events: &mut Vec<CommunityEvent>) {
+    let level = changes.level;
+    let (retired, minted) = (&changes.retired, &changes.minted);
+
+    // Nodes each identifier lost and gained, and where they went or came from
+    let mut left: BTreeMap<CommunityId, Vec<usize>> = BTreeMap::new();
+    let mut joined: BTreeMap<CommunityId, Vec<usize>> = BTreeMap::new();
+    let mut targets: BTreeMap<CommunityId, BTreeSet<CommunityId>> = BTreeMap::new();
+    let mut sources: BTreeMap<CommunityId, BTreeSet<CommunityId>> = BTreeMap::new();
+    for &(node, from, to) in &changes.moved {
+        if let Some(from) = from {
+            left.entry(from).or_default().push(node);
+        }
+        if let Some(to) = to {
+            joined.entry(to).or_default().push(node);
+        }
+        if let (Some(from), Some(to)) = (from, to) {
+            targets.entry(from).or_default().insert(to);
+            sources.entry(to).or_default().insert(from);
+        }
+    }
+
+    // A dissolved community lost every member and a created one gained all of them
+    for &id in retired {
+        events.push(CommunityEvent::Dissolved {
+            level,
+            id,
+            members: left.get(&id).cloned().unwrap_or_default(),
+        });
+    }
+    for &id in minted {
+        events.push(CommunityEvent::Created {
+            level,
+            id,
+            members: joined.get(&id).cloned().unwrap_or_default(),
+        });
+    }
+    for (&id, nodes) in joined.iter().filter(|(id, _)| !minted.contains(id)) {
+        events.push(CommunityEvent::MembersAdded {
+            level,
+            id,
+            nodes: nodes.clone(),
+        });
+    }
+    for (&id, nodes) in left.iter().filter(|(id, _)| !retired.contains(id)) {
+        events.push(CommunityEvent::MembersRemoved {
+            level,
+            id,
+            nodes: nodes.clone(),
+        });
+    }
+    // A community that survives kept some of its nodes
+    for (&id, to) in &targets {
+        let mut into: BTreeSet<CommunityId> = to.intersection(minted).copied().collect();
+        if !into.is_empty() && !retired.contains(&id) {
+            into.insert(id);
+        }
+        if into.len() > 1 {
+            events.push(CommunityEvent::Split {
+                level,
+                id,
+                into: into.into_iter().collect(),
+            });
+        }
+    }
+    for (&id, from) in &sources {
+        let mut from: BTreeSet<CommunityId> = from.intersection(retired).copied().collect();
+        if !from.is_empty() && !minted.contains(&id) {
+            from.insert(id);
+        }
+        if from.len() > 1 {
+            events.push(CommunityEvent::Merged {
+                level,
+                id,
+                from: from.into_iter().collect(),
+            });
+        }
+    }
+}
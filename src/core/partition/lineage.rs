@@ -0,0 +1,389 @@
+use crate::core::binary::{Decoder, Encoder};
+use crate::core::error::HitLeidenError;
+use crate::core::partition::stable_ids::{CommunityId, LevelChanges};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
+
+const HEADER: &str = "hit_leiden-lineage v1";
+
+/// How a community descends from another one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Relation {
+    /// The parent dissolved and all of its surviving nodes formed the child
+    /// on their own.
+    Continued,
+    /// The child was carved out of the parent, alone or with siblings.
+    Split,
+    /// The parent dissolved into the child, alone or with other parents.
+    Merged,
+}
+
+impl Relation {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Continued => "continued",
+            Self::Split => "split",
+            Self::Merged => "merged",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "continued" => Some(Self::Continued),
+            "split" => Some(Self::Split),
+            "merged" => Some(Self::Merged),
+            _ => None,
+        }
+    }
+}
+
+/// One ancestry relation recorded by the update that produced `version`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineageLink {
+    pub version: u64,
+    pub parent: CommunityId,
+    pub child: CommunityId,
+    pub relation: Relation,
+    /// Level-0 nodes that moved from the parent to the child.
+    pub shared_nodes: usize,
+}
+
+/// When a community identifier was alive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lifespan {
+    /// Version that created the community.
+    pub born: u64,
+    /// Version that dissolved it, if any.
+    pub ended: Option<u64>,
+}
+
+impl Lifespan {
+    pub fn is_alive_at(&self, version: u64) -> bool {
+        self.born <= version && self.ended.map_or(true, |ended| version < ended)
+    }
+}
+
+/// Ancestry of the final communities of a session across versions.
+///
+/// A community keeps its [`CommunityId`] while it survives, so continuation
+/// under the same identifier is implied by its [`Lifespan`]. Links are only
+/// recorded where nodes pass between two different identifiers and one of
+/// them was created or dissolved by the update; nodes migrating between two
+/// communities that both survive are membership changes, not ancestry.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommunityLineage {
+    spans: BTreeMap<CommunityId, Lifespan>,
+    /// Ordered by version, then parent, then child.
+    links: Vec<LineageLink>,
+}
+
+impl CommunityLineage {
+    /// Start a lineage whose communities at `version` are those in `communities`,
+    /// the identifier of each level-0 node's final community.
+    pub fn new(version: u64, communities: &[Option<CommunityId>]) -> Self {
+        let spans = communities
+            .iter()
+            .flatten()
+            .map(|&id| {
+                let span = Lifespan {
+                    born: version,
+                    ended: None,
+                };
+                (id, span)
+            })
+            .collect();
+        Self {
+            spans,
+            links: Vec::new(),
+        }
+    }
+
+    /// Record the update that turned `before` into `after` and produced `version`.
+    pub fn record(
+        &mut self,
+        version: u64,
+        before: &[Option<CommunityId>],
+        after: &[Option<CommunityId>],
+    ) {
+        self.record_changes(version, &LevelChanges::between(0, before, after));
+    }
+
+    /// Record the update that produced `version`, given how it changed the
+    /// identifiers of the final communities. Only the nodes in `changes` are
+    /// visited.
+    pub fn record_changes(&mut self, version: u64, changes: &LevelChanges) {
+        let mut flows: BTreeMap<(CommunityId, CommunityId), usize> = BTreeMap::new();
+        for &(_, from, to) in &changes.moved {
+            if let (Some(from), Some(to)) = (from, to) {
+                *flows.entry((from, to)).or_insert(0) += 1;
+            }
+        }
+        let mut targets: BTreeMap<CommunityId, usize> = BTreeMap::new();
+        let mut sources: BTreeMap<CommunityId, usize> = BTreeMap::new();
+        for &(from, to) in flows.keys() {
+            *targets.entry(from).or_insert(0) += 1;
+            *sources.entry(to).or_insert(0) += 1;
+        }
+
+        for (&(parent, child), &shared_nodes) in &flows {
+            let survived = !changes.retired.contains(&parent);
+            let existed = !changes.minted.contains(&child);
+            let relation = match (survived, existed) {
+                (true, true) => continue,
+                (true, false) => Relation::Split,
+                (false, true) => Relation::Merged,
+                (false, false) if targets[&parent] > 1 => Relation::Split,
+                (false, false) if sources[&child] > 1 => Relation::Merged,
+                (false, false) => Relation::Continued,
+            };
+            self.links.push(LineageLink {
+                version,
+                parent,
+                child,
+                relation,
+                shared_nodes,
+            });
+        }
+        for id in &changes.retired {
+            if let Some(span) = self.spans.get_mut(id) {
+                span.ended = Some(version);
+            }
+        }
+        for &id in &changes.minted {
+            self.spans.insert(
+                id,
+                Lifespan {
+                    born: version,
+                    ended: None,
+                },
+            );
+        }
+    }
+
+    /// Lifespan of community `id`, if it ever existed.
+    pub fn lifespan(&self, id: CommunityId) -> Option<Lifespan> {
+        self.spans.get(&id).copied()
+    }
+
+    /// Identifiers of the communities alive at `version`, ascending.
+    pub fn alive_at(&self, version: u64) -> Vec<CommunityId> {
+        self.spans
+            .iter()
+            .filter(|(_, span)| span.is_alive_at(version))
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    /// Every recorded link, ordered by version.
+    pub fn links(&self) -> &[LineageLink] {
+        &self.links
+    }
+
+    /// Links recorded by the update that produced `version`.
+    pub fn links_at(&self, version: u64) -> &[LineageLink] {
+        let start = self.links.partition_point(|link| link.version < version);
+        let end = self.links.partition_point(|link| link.version <= version);
+        &self.links[start..end]
+    }
+
+    /// Links into community `id`.
+    pub fn parents(&self, id: CommunityId) -> Vec<LineageLink> {
+        self.links
+            .iter()
+            .filter(|link| link.child == id)
+            .copied()
+            .collect()
+    }
+
+    /// Links out of community `id`.
+    pub fn children(&self, id: CommunityId) -> Vec<LineageLink> {
+        self.links
+            .iter()
+            .filter(|link| link.parent == id)
+            .copied()
+            .collect()
+    }
+
+    /// Every community `id` descends from, transitively.
+    pub fn ancestors(&self, id: CommunityId) -> BTreeSet<CommunityId> {
+        self.reachable(id, |link| (link.child, link.parent))
+    }
+
+    /// Every community descending from `id`, transitively.
+    pub fn descendants(&self, id: CommunityId) -> BTreeSet<CommunityId> {
+        self.reachable(id, |link| (link.parent, link.child))
+    }
+
+    fn reachable(
+        &self,
+        id: CommunityId,
+        step: impl Fn(&LineageLink) -> (CommunityId, CommunityId),
+    ) -> BTreeSet<CommunityId> {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            for link in &self.links {
+                let (from, to) = step(link);
+                if from == current && to != id && seen.insert(to) {
+                    stack.push(to);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Append the spans and links, read back by [`CommunityLineage::decode`].
+    pub(crate) fn encode(&self, out: &mut Encoder) {
+        out.usize(self.spans.len());
+        for (&id, span) in &self.spans {
+            out.u64(id);
+            out.u64(span.born);
+            // No update produces version u64::MAX
+            out.u64(span.ended.unwrap_or(u64::MAX));
+        }
+        out.usize(self.links.len());
+        for link in &self.links {
+            out.u64(link.version);
+            out.u64(link.parent);
+            out.u64(link.child);
+            out.u32(match link.relation {
+                Relation::Continued => 0,
+                Relation::Split => 1,
+                Relation::Merged => 2,
+            });
+            out.usize(link.shared_nodes);
+        }
+    }
+
+    /// Read a lineage written by [`CommunityLineage::encode`].
+    pub(crate) fn decode(input: &mut Decoder) -> Result<Self, String> {
+        let mut lineage = Self::default();
+        let count = input.count(24)?;
+        for _ in 0..count {
+            let id = input.u64()?;
+            let span = Lifespan {
+                born: input.u64()?,
+                ended: Some(input.u64()?).filter(|&ended| ended != u64::MAX),
+            };
+            if lineage.spans.insert(id, span).is_some() {
+                return Err(format!("duplicate span for {id}"));
+            }
+        }
+        let count = input.count(36)?;
+        for _ in 0..count {
+            let (version, parent, child) = (input.u64()?, input.u64()?, input.u64()?);
+            let relation = match input.u32()? {
+                0 => Relation::Continued,
+                1 => Relation::Split,
+                2 => Relation::Merged,
+                tag => return Err(format!("unknown relation {tag}")),
+            };
+            let link = LineageLink {
+                version,
+                parent,
+                child,
+                relation,
+                shared_nodes: input.usize()?,
+            };
+            if lineage
+                .links
+                .last()
+                .is_some_and(|last| last.version > link.version)
+            {
+                return Err("links are not ordered by version".to_string());
+            }
+            lineage.links.push(link);
+        }
+        Ok(lineage)
+    }
+
+    /// Write the lineage in a line-oriented text format read by
+    /// [`CommunityLineage::read_from`].
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writeln!(writer, "{HEADER}")?;
+        for (id, span) in &self.spans {
+            match span.ended {
+                Some(ended) => writeln!(writer, "span {id} {} {ended}", span.born)?,
+                None => writeln!(writer, "span {id} {} -", span.born)?,
+            }
+        }
+        for link in &self.links {
+            writeln!(
+                writer,
+                "link {} {} {} {} {}",
+                link.version,
+                link.parent,
+                link.child,
+                link.relation.name(),
+                link.shared_nodes
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Read a lineage written by [`CommunityLineage::write_to`].
+    pub fn read_from(reader: impl BufRead) -> Result<Self, HitLeidenError> {
+        let mut lineage = Self::default();
+        let mut lines = reader.lines().enumerate();
+        let invalid = |line: usize, reason: &str| {
+            HitLeidenError::InvalidInput(format!("lineage line {}: {reason}", line + 1))
+        };
+        let read = |line: usize, result: io::Result<String>| {
+            result.map_err(|e| invalid(line, &e.to_string()))
+        };
+
+        let header = match lines.next() {
+            Some((index, line)) => read(index, line)?,
+            None => String::new(),
+        };
+        if header != HEADER {
+            return Err(invalid(0, &format!("expected header {HEADER:?}")));
+        }
+        for (index, line) in lines {
+            let line = read(index, line)?;
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let number = |field: &str| {
+                field
+                    .parse::<u64>()
+                    .map_err(|_| invalid(index, &format!("{field:?} is not a number")))
+            };
+            match fields.as_slice() {
+                ["span", id, born, ended] => {
+                    let ended = match *ended {
+                        "-" => None,
+                        ended => Some(number(ended)?),
+                    };
+                    let span = Lifespan {
+                        born: number(born)?,
+                        ended,
+                    };
+                    if lineage.spans.insert(number(id)?, span).is_some() {
+                        return Err(invalid(index, &format!("duplicate span for {id}")));
+                    }
+                }
+                ["link", version, parent, child, relation, shared] => {
+                    let link = LineageLink {
+                        version: number(version)?,
+                        parent: number(parent)?,
+                        child: number(child)?,
+                        relation: Relation::parse(relation).ok_or_else(|| {
+                            invalid(index, &format!("unknown relation {relation:?}"))
+                        })?,
+                        shared_nodes: number(shared)? as usize,
+                    };
+                    if lineage
+                        .links
+                        .last()
+                        .is_some_and(|last| last.version > link.version)
+                    {
+                        return Err(invalid(index, "links are not ordered by version"));
+                    }
+                    lineage.links.push(link);
+                }
+                [] => {}
+                _ => return Err(invalid(index, &format!("unrecognised record {line:?}"))),
+            }
+        }
+        Ok(lineage)
+    }
+}
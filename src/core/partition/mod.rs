@@ -1 +1,6 @@
+pub mod checkpoint;
+pub mod events;
+pub mod lineage;
+pub mod stable_ids;
 pub mod state;
+pub mod tree;
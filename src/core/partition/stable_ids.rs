@@ -0,0 +1,411 @@
+use crate::core::binary::{Decoder, Encoder};
+use crate::core::partition::state::PartitionState;
+use bitvec::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Label of a removed node in [`PartitionState::hierarchy_labels`].
+const REMOVED: usize = usize::MAX;
+
+/// Level of the previous hierarchy (of `previous_levels` levels) whose
+/// communities carry over to `level` of a hierarchy of `levels` levels. The
+/// community partition is always matched with the previous one; the levels
+/// below it with the same level, if it was below the previous top.
+pub(crate) fn predecessor(level: usize, levels: usize, previous_levels: usize) -> Option<usize> {
+    if level + 1 == levels {
+        previous_levels.checked_sub(1)
+    } else {
+        Some(level).filter(|&p| p + 1 < previous_levels)
+    }
+}
+
+/// Identifier of a community that survives incremental updates.
+pub type CommunityId = u64;
+
+/// How the identifiers at one level changed in an update.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LevelChanges {
+    pub level: usize,
+    /// Level-0 nodes whose identifier changed, ascending, with the identifier
+    /// before and after; `None` where the node had none.
+    pub moved: Vec<(usize, Option<CommunityId>, Option<CommunityId>)>,
+    /// Identifiers that had members before the update and have none after.
+    pub retired: BTreeSet<CommunityId>,
+    /// Identifiers that have members after the update and had none before.
+    pub minted: BTreeSet<CommunityId>,
+}
+
+impl LevelChanges {
+    /// Changes at `level` turning `before` into `after`, the identifier of
+    /// every level-0 node.
+    pub fn between(
+        level: usize,
+        before: &[Option<CommunityId>],
+        after: &[Option<CommunityId>],
+    ) -> Self {
+        let old: BTreeSet<CommunityId> = before.iter().flatten().copied().collect();
+        let new: BTreeSet<CommunityId> = after.iter().flatten().copied().collect();
+        let moved = (0..before.len().max(after.len()))
+            .filter_map(|node| {
+                let from = before.get(node).copied().flatten();
+                let to = after.get(node).copied().flatten();
+                (from != to).then_some((node, from, to))
+            })
+            .collect();
+        Self {
+            level,
+            moved,
+            retired: old.difference(&new).copied().collect(),
+            minted: new.difference(&old).copied().collect(),
+        }
+    }
+}
+
+/// Stable identifiers for the communities at every level of a partition.
+///
+/// Internal labels (level-0 node ids for communities, supervertex ids below
+/// them) can change when a delta is applied even for communities it did not
+/// touch. After each update every new community inherits the identifier of
+/// the previous community it shares the most live level-0 nodes with; pairs
+/// are matched greedily from the largest overlap down, ties going to the
+/// older identifier and then the smaller label. Communities left unmatched
+/// get a fresh identifier, and identifiers are never reused.
+///
+/// Levels follow [`PartitionState::hierarchy_labels`]: finest first, with the
+/// community partition last.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StableCommunityIds {
+    next_id: CommunityId,
+    /// Per level, internal label -> identifier.
+    ids: Vec<BTreeMap<usize, CommunityId>>,
+    /// Per level, the label of every level-0 node at the last update.
+    labels: Vec<Vec<usize>>,
+    /// Per level, internal label -> the live level-0 nodes carrying it.
+    members: Vec<BTreeMap<usize, BTreeSet<usize>>>,
+}
+
+impl StableCommunityIds {
+    /// Mint identifiers for the communities of `state`, in level then label order.
+    pub fn new(state: &PartitionState) -> Self {
+        let mut ids = Self::default();
+        ids.rebuild(state);
+        ids
+    }
+
+    /// Carry identifiers over to the communities of `state` and report what
+    /// changed at each level.
+    ///
+    /// `touched` must hold every level-0 node whose label changed at some
+    /// level; nodes appended since the last update are always included. Only
+    /// the communities those nodes left or joined are matched again, so an
+    /// update costs O(t·L·log n) for t touched nodes and L levels, plus the
+    /// size of every community whose identifier changed. When the number of
+    /// levels changed, every node is relabelled in O(n·L·log n).
+    pub fn update(&mut self, state: &PartitionState, touched: &BitVec) -> Vec<LevelChanges> {
+        if state.levels != self.labels.len() {
+            return self.rebuild(state);
+        }
+        let n = state.node_to_comm.len();
+        let previous_n = self.labels[0].len();
+        let nodes: BTreeSet<usize> = touched.iter_ones().chain(previous_n..n).collect();
+        for labels in &mut self.labels {
+            labels.resize(n, REMOVED);
+        }
+        let current: Vec<(usize, Vec<usize>)> = nodes
+            .into_iter()
+            .map(|node| (node, state.node_hierarchy_labels(node)))
+            .collect();
+        (0..state.levels)
+            .map(|p| {
+                let relabelled: Vec<(usize, usize, usize)> = current
+                    .iter()
+                    .map(|(node, labels)| (*node, self.labels[p][*node], labels[p]))
+                    .filter(|&(_, old, new)| old != new)
+                    .collect();
+                self.update_level(p, &relabelled)
+            })
+            .collect()
+    }
+
+    /// Apply the `(node, old label, new label)` moves at level `p` and match
+    /// the communities they left or joined.
+    fn update_level(&mut self, p: usize, relabelled: &[(usize, usize, usize)]) -> LevelChanges {
+        let affected: BTreeSet<usize> = relabelled
+            .iter()
+            .flat_map(|&(_, old, new)| [old, new])
+            .filter(|&label| label != REMOVED)
+            .collect();
+        let mut leaving: BTreeMap<usize, usize> = BTreeMap::new();
+        for &(_, old, _) in relabelled.iter().filter(|&&(_, old, _)| old != REMOVED) {
+            *leaving.entry(old).or_insert(0) += 1;
+        }
+        let before: BTreeMap<usize, CommunityId> = affected
+            .iter()
+            .filter_map(|label| self.ids[p].get(label).map(|&id| (*label, id)))
+            .collect();
+
+        // Nodes that kept their label overlap their old community in full
+        let mut overlap: BTreeMap<(CommunityId, usize), usize> = BTreeMap::new();
+        for (&label, &id) in &before {
+            let stayed = self.members[p][&label].len() - leaving.get(&label).unwrap_or(&0);
+            if stayed > 0 {
+                overlap.insert((id, label), stayed);
+            }
+        }
+        for &(_, old, new) in relabelled {
+            if old != REMOVED && new != REMOVED {
+                *overlap.entry((before[&old], new)).or_insert(0) += 1;
+            }
+        }
+        let matched = Self::match_overlaps(overlap);
+
+        for &(node, old, new) in relabelled {
+            if old != REMOVED {
+                let members = self.members[p].get_mut(&old).expect("member of its label");
+                members.remove(&node);
+                if members.is_empty() {
+                    self.members[p].remove(&old);
+                }
+            }
+            if new != REMOVED {
+                self.members[p].entry(new).or_default().insert(node);
+            }
+            self.labels[p][node] = new;
+        }
+
+        let mut minted = BTreeSet::new();
+        for &label in &affected {
+            self.ids[p].remove(&label);
+            if !self.members[p].contains_key(&label) {
+                continue;
+            }
+            let id = matched.get(&label).copied().unwrap_or_else(|| {
+                minted.insert(self.next_id);
+                self.next_id += 1;
+                self.next_id - 1
+            });
+            self.ids[p].insert(label, id);
+        }
+        let kept: BTreeSet<CommunityId> = matched.values().copied().collect();
+        let retired = before
+            .values()
+            .copied()
+            .filter(|id| !kept.contains(id))
+            .collect();
+
+        let mut moved: BTreeMap<usize, (Option<CommunityId>, Option<CommunityId>)> =
+            BTreeMap::new();
+        for &(node, old, new) in relabelled {
+            let from = before.get(&old).copied();
+            let to = self.ids[p].get(&new).copied();
+            if from != to {
+                moved.insert(node, (from, to));
+            }
+        }
+        // Nodes that stayed in a community whose identifier changed
+        let relabelled: BTreeSet<usize> = relabelled.iter().map(|&(node, _, _)| node).collect();
+        for (&label, &id) in &before {
+            let to = self.ids[p].get(&label).copied();
+            if to != Some(id) {
+                for &node in self.members[p].get(&label).into_iter().flatten() {
+                    if !relabelled.contains(&node) {
+                        moved.insert(node, (Some(id), to));
+                    }
+                }
+            }
+        }
+        LevelChanges {
+            level: p,
+            moved: moved
+                .into_iter()
+                .map(|(node, (from, to))| (node, from, to))
+                .collect(),
+            retired,
+            minted,
+        }
+    }
+
+    /// Relabel every node of every level from scratch.
+    fn rebuild(&mut self, state: &PartitionState) -> Vec<LevelChanges> {
+        let previous = self.clone();
+        let labels = state.hierarchy_labels();
+        let levels = labels.len();
+        let mut ids = Vec::with_capacity(levels);
+        for (p, current) in labels.iter().enumerate() {
+            let matched = match predecessor(p, levels, previous.labels.len()) {
+                Some(q) => Self::match_level(&previous.labels[q], &previous.ids[q], current),
+                None => BTreeMap::new(),
+            };
+            ids.push(self.mint_unmatched(matched, current));
+        }
+        self.ids = ids;
+        self.members = labels.iter().map(|current| members_of(current)).collect();
+        self.labels = labels;
+        self.changes_since(&previous)
+    }
+
+    /// Changes at every level turning `previous` into `self`. Levels are
+    /// paired as [`Self::update`] pairs them; a previous level with no
+    /// successor is reported after the others, with all of its identifiers
+    /// retired.
+    pub(crate) fn changes_since(&self, previous: &StableCommunityIds) -> Vec<LevelChanges> {
+        let levels = self.level_count();
+        let mut paired = BTreeSet::new();
+        let mut changes = Vec::new();
+        for level in 0..levels {
+            let before = match predecessor(level, levels, previous.level_count()) {
+                Some(q) => {
+                    paired.insert(q);
+                    previous.node_ids(q)
+                }
+                None => Vec::new(),
+            };
+            changes.push(LevelChanges::between(level, &before, &self.node_ids(level)));
+        }
+        for q in (0..previous.level_count()).filter(|q| !paired.contains(q)) {
+            changes.push(LevelChanges::between(q, &previous.node_ids(q), &[]));
+        }
+        changes
+    }
+
+    /// Match the communities of `current` to those of `previous` by overlap.
+    fn match_level(
+        previous: &[usize],
+        previous_ids: &BTreeMap<usize, CommunityId>,
+        current: &[usize],
+    ) -> BTreeMap<usize, CommunityId> {
+        let mut overlap: BTreeMap<(CommunityId, usize), usize> = BTreeMap::new();
+        for (&old, &new) in previous.iter().zip(current) {
+            if old != REMOVED && new != REMOVED {
+                *overlap.entry((previous_ids[&old], new)).or_insert(0) += 1;
+            }
+        }
+        Self::match_overlaps(overlap)
+    }
+
+    /// Pair identifiers with labels greedily from the largest overlap down.
+    fn match_overlaps(
+        overlap: BTreeMap<(CommunityId, usize), usize>,
+    ) -> BTreeMap<usize, CommunityId> {
+        let mut pairs: Vec<((CommunityId, usize), usize)> = overlap.into_iter().collect();
+        // Stable sort keeps (id, label) order among equal overlaps
+        pairs.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+        let mut matched = BTreeMap::new();
+        let mut taken = BTreeSet::new();
+        for ((id, label), _) in pairs {
+            if !matched.contains_key(&label) && taken.insert(id) {
+                matched.insert(label, id);
+            }
+        }
+        matched
+    }
+
+    fn mint_unmatched(
+        &mut self,
+        mut matched: BTreeMap<usize, CommunityId>,
+        current: &[usize],
+    ) -> BTreeMap<usize, CommunityId> {
+        let mut fresh: Vec<usize> = current
+            .iter()
+            .copied()
+            .filter(|&label| label != REMOVED && !matched.contains_key(&label))
+            .collect();
+        fresh.sort_unstable();
+        fresh.dedup();
+        for label in fresh {
+            matched.insert(label, self.next_id);
+            self.next_id += 1;
+        }
+        matched
+    }
+
+    /// Whether the identifiers were last updated to the communities of `state`.
+    pub(crate) fn describes(&self, state: &PartitionState) -> bool {
+        self.labels == state.hierarchy_labels()
+    }
+
+    /// Append the labels and identifiers of every level, read back by
+    /// [`StableCommunityIds::decode`].
+    pub(crate) fn encode(&self, out: &mut Encoder) {
+        out.u64(self.next_id);
+        out.usize(self.labels.len());
+        for (labels, ids) in self.labels.iter().zip(&self.ids) {
+            out.usizes(labels);
+            out.usize(ids.len());
+            for (&label, &id) in ids {
+                out.usize(label);
+                out.u64(id);
+            }
+        }
+    }
+
+    /// Read identifiers written by [`StableCommunityIds::encode`], checking
+    /// that every community has one and none is left to be minted.
+    pub(crate) fn decode(input: &mut Decoder) -> Result<Self, String> {
+        let mut decoded = Self {
+            next_id: input.u64()?,
+            ..Self::default()
+        };
+        let levels = input.count(16)?;
+        for p in 0..levels {
+            let labels = input.usizes()?;
+            let count = input.count(16)?;
+            let ids = (0..count)
+                .map(|_| Ok((input.usize()?, input.u64()?)))
+                .collect::<Result<BTreeMap<_, _>, String>>()?;
+            let members = members_of(&labels);
+            if !members.keys().eq(ids.keys()) {
+                return Err(format!(
+                    "level {p} identifiers do not match its communities"
+                ));
+            }
+            if ids.values().any(|&id| id >= decoded.next_id) {
+                return Err(format!("level {p} holds an identifier not minted yet"));
+            }
+            decoded.labels.push(labels);
+            decoded.ids.push(ids);
+            decoded.members.push(members);
+        }
+        Ok(decoded)
+    }
+
+    /// Number of levels, the community partition being the last.
+    pub fn level_count(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Internal label -> identifier for every community at `level`.
+    pub fn level(&self, level: usize) -> &BTreeMap<usize, CommunityId> {
+        &self.ids[level]
+    }
+
+    /// Identifier of the community at `level` containing level-0 `node`, or
+    /// `None` if the node was removed.
+    pub fn node_id(&self, level: usize, node: usize) -> Option<CommunityId> {
+        let label = self.labels[level][node];
+        (label != REMOVED).then(|| self.ids[level][&label])
+    }
+
+    /// Identifier of the community every level-0 node is in at `level`, in O(n·log n).
+    pub fn node_ids(&self, level: usize) -> Vec<Option<CommunityId>> {
+        (0..self.labels[level].len())
+            .map(|node| self.node_id(level, node))
+            .collect()
+    }
+
+    /// Identifiers of the final communities, per level-0 node.
+    pub fn communities(&self) -> Vec<Option<CommunityId>> {
+        self.node_ids(self.level_count() - 1)
+    }
+}
+
+/// Live level-0 nodes carrying each label.
+fn members_of(labels: &[usize]) -> BTreeMap<usize, BTreeSet<usize>> {
+    let mut members: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    for (node, &label) in labels.iter().enumerate() {
+        if label != REMOVED {
+            members.entry(label).or_default().insert(node);
+        }
+    }
+    members
+}
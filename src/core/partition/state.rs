@@ -1,10 +1,12 @@
 use crate::core::graph::in_memory::InMemoryGraph;
+use bitvec::prelude::*;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct PartitionState {
     pub node_to_comm: Vec<usize>,
-    pub comm_weights: Vec<f64>,
-    pub node_weights: Vec<f64>,
+    /// Level-0 nodes that have been removed. Their ids stay allocated and they
+    /// have no edges, but they are not reused.
+    pub removed_nodes: BitVec,
 
     // Hierarchical state for HIT-Leiden
     pub levels: usize,
@@ -13,37 +15,103 @@ pub struct PartitionState {
     pub refined_community_mapping_per_level: Vec<Vec<usize>>,
     pub previous_subcommunity_mapping_per_level: Vec<Vec<usize>>,
     pub current_subcommunity_mapping_per_level: Vec<Vec<usize>>,
+    /// Weight of every vertex at every level; a supervertex weighs as much as
+    /// the level-0 nodes it contains. Used as the vertex mass by CPM.
+    pub node_weights_per_level: Vec<Vec<f64>>,
 }
 
 impl PartitionState {
+    /// Singleton partition in which every node weighs 1.
     pub fn identity(node_count: usize) -> Self {
+        Self::with_weights(node_count, vec![1.0; node_count])
+    }
+
+    /// Singleton partition in which node `i` weighs `node_weights[i]`. The
+    /// weights are the vertex mass under CPM and are summed into supervertices.
+    pub fn with_weights(node_count: usize, node_weights: Vec<f64>) -> Self {
         // Share one identity allocation, clone for those that will be mutated independently
         let identity: Vec<usize> = (0..node_count).collect();
         Self {
             node_to_comm: identity.clone(),
-            comm_weights: vec![0.0; node_count],
-            node_weights: vec![0.0; node_count],
+            removed_nodes: bitvec![0; node_count],
             levels: 1,
             supergraphs: Vec::new(),
             community_mapping_per_level: vec![identity.clone()],
             refined_community_mapping_per_level: vec![identity.clone()],
             previous_subcommunity_mapping_per_level: vec![identity.clone()],
             current_subcommunity_mapping_per_level: vec![identity],
+            node_weights_per_level: vec![node_weights],
         }
     }
 
-    pub fn with_weights(node_count: usize, node_weights: Vec<f64>) -> Self {
-        let identity: Vec<usize> = (0..node_count).collect();
-        Self {
-            node_to_comm: identity.clone(),
-            comm_weights: node_weights.clone(),
-            node_weights,
-            levels: 1,
-            supergraphs: Vec::new(),
-            community_mapping_per_level: vec![identity.clone()],
-            refined_community_mapping_per_level: vec![identity.clone()],
-            previous_subcommunity_mapping_per_level: vec![identity.clone()],
-            current_subcommunity_mapping_per_level: vec![identity],
+    /// Extend the level-0 node arrays with `count` nodes, each in its own
+    /// community labelled by its id. The per-level hierarchy is grown by the
+    /// incremental update that places them.
+    pub fn grow(&mut self, count: usize) {
+        let n = self.node_to_comm.len();
+        self.node_to_comm.extend(n..n + count);
+        self.removed_nodes.resize(n + count, false);
+    }
+
+    /// Set the weight of level-0 `node` and of every supervertex containing it.
+    pub fn set_node_weight(&mut self, node: usize, weight: f64) {
+        let delta = weight - self.node_weights_per_level[0][node];
+        let mut vertex = node;
+        for p in 0..self.levels {
+            self.node_weights_per_level[p][vertex] += delta;
+            if p + 1 < self.levels {
+                vertex = self.current_subcommunity_mapping_per_level[p][vertex];
+            }
+        }
+        self.node_weights_per_level[0][node] = weight;
+    }
+
+    /// Weight of every level-0 node.
+    pub fn node_weights(&self) -> &[f64] {
+        &self.node_weights_per_level[0]
+    }
+
+    pub fn is_removed(&self, node: usize) -> bool {
+        self.removed_nodes[node]
+    }
+
+    /// Labels of level-0 `node` at every level, as in [`Self::hierarchy_labels`].
+    pub fn node_hierarchy_labels(&self, node: usize) -> Vec<usize> {
+        if self.removed_nodes[node] {
+            return vec![usize::MAX; self.levels];
+        }
+        let mut vertex = node;
+        let mut labels: Vec<usize> = self.current_subcommunity_mapping_per_level[..self.levels - 1]
+            .iter()
+            .map(|mapping| {
+                vertex = mapping[vertex];
+                vertex
+            })
+            .collect();
+        labels.push(self.node_to_comm[node]);
+        labels
+    }
+
+    /// Label of every level-0 node at every level of the hierarchy, finest
+    /// first. Entry `p < levels - 1` groups nodes by the level-(p+1)
+    /// supervertex containing them; the last entry is the community
+    /// partition. Removed nodes are labelled `usize::MAX`.
+    pub fn hierarchy_labels(&self) -> Vec<Vec<usize>> {
+        let n = self.node_to_comm.len();
+        let mut labels = vec![vec![usize::MAX; n]; self.levels];
+        for v in (0..n).filter(|&v| !self.removed_nodes[v]) {
+            let mut vertex = v;
+            for (mapping, level) in self
+                .current_subcommunity_mapping_per_level
+                .iter()
+                .zip(labels.iter_mut())
+                .take(self.levels - 1)
+            {
+                vertex = mapping[vertex];
+                level[v] = vertex;
+            }
+            labels[self.levels - 1][v] = self.node_to_comm[v];
         }
+        labels
     }
 }
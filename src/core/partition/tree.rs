@@ -0,0 +1,209 @@
+use crate::core::partition::stable_ids::{CommunityId, StableCommunityIds};
+use crate::core::partition::state::PartitionState;
+use std::collections::BTreeMap;
+
+/// Label of a removed node in [`PartitionState::hierarchy_labels`].
+const REMOVED: usize = usize::MAX;
+
+/// One community of a [`CommunityTree`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeCommunity {
+    /// Hierarchy level, leaves at 0 and roots at the top.
+    pub level: usize,
+    /// Internal label of the community at its level.
+    pub label: usize,
+    /// Stable identifier, when the tree was built by a session.
+    pub id: Option<CommunityId>,
+    /// Index of the enclosing community, `None` for roots.
+    pub parent: Option<usize>,
+    /// Indices of the communities one level down, ascending.
+    pub children: Vec<usize>,
+    /// Live level-0 nodes in the community.
+    pub size: usize,
+    /// Total weight of those nodes.
+    pub weight: f64,
+}
+
+/// The community hierarchy of a partition as a forest.
+///
+/// Each level of [`PartitionState::hierarchy_labels`] becomes one level of
+/// the tree: leaves are the finest communities and roots the final ones.
+/// Communities are indexed by level, then label, so a parent always has a
+/// larger index than its children.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommunityTree {
+    communities: Vec<TreeCommunity>,
+    /// Index of the first community of every level, plus the total count.
+    level_starts: Vec<usize>,
+    /// Leaf containing every level-0 node, `None` for removed nodes.
+    leaves: Vec<Option<usize>>,
+    /// Level-0 nodes of every leaf, ascending.
+    members: BTreeMap<usize, Vec<usize>>,
+}
+
+impl CommunityTree {
+    /// Tree of the communities of `state`, without stable identifiers.
+    pub fn from_state(state: &PartitionState) -> Self {
+        Self::build(state, None)
+    }
+
+    /// Tree of the communities of `state`, labelled with the identifiers in `ids`.
+    pub fn with_ids(state: &PartitionState, ids: &StableCommunityIds) -> Self {
+        Self::build(state, Some(ids))
+    }
+
+    fn build(state: &PartitionState, ids: Option<&StableCommunityIds>) -> Self {
+        let labels = state.hierarchy_labels();
+        let weights = &state.node_weights_per_level[0];
+        let node_count = state.node_to_comm.len();
+
+        let mut communities = Vec::new();
+        let mut level_starts = Vec::with_capacity(labels.len() + 1);
+        let mut index_per_level: Vec<BTreeMap<usize, usize>> = Vec::with_capacity(labels.len());
+        for (level, level_labels) in labels.iter().enumerate() {
+            level_starts.push(communities.len());
+            let mut totals: BTreeMap<usize, (usize, f64)> = BTreeMap::new();
+            for (node, &label) in level_labels.iter().enumerate() {
+                if label != REMOVED {
+                    let total = totals.entry(label).or_insert((0, 0.0));
+                    total.0 += 1;
+                    total.1 += weights[node];
+                }
+            }
+            let mut index = BTreeMap::new();
+            for (label, (size, weight)) in totals {
+                index.insert(label, communities.len());
+                communities.push(TreeCommunity {
+                    level,
+                    label,
+                    id: ids.map(|ids| ids.level(level)[&label]),
+                    parent: None,
+                    children: Vec::new(),
+                    size,
+                    weight,
+                });
+            }
+            index_per_level.push(index);
+        }
+        level_starts.push(communities.len());
+
+        // Link every community to the one containing its nodes; the levels
+        // of the hierarchy nest, so all of them agree on it
+        for level in 1..labels.len() {
+            for node in 0..node_count {
+                let child_label = labels[level - 1][node];
+                if child_label == REMOVED {
+                    continue;
+                }
+                let child = index_per_level[level - 1][&child_label];
+                let parent = index_per_level[level][&labels[level][node]];
+                match communities[child].parent {
+                    None => {
+                        communities[child].parent = Some(parent);
+                        communities[parent].children.push(child);
+                    }
+                    Some(linked) => debug_assert_eq!(
+                        linked,
+                        parent,
+                        "level {} community {} spans several communities above it",
+                        level - 1,
+                        child_label
+                    ),
+                }
+            }
+        }
+        for community in &mut communities {
+            community.children.sort_unstable();
+        }
+
+        let mut leaves = vec![None; node_count];
+        let mut members: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        if let Some(leaf_labels) = labels.first() {
+            for (node, &label) in leaf_labels.iter().enumerate() {
+                if label != REMOVED {
+                    let leaf = index_per_level[0][&label];
+                    leaves[node] = Some(leaf);
+                    members.entry(leaf).or_default().push(node);
+                }
+            }
+        }
+
+        Self {
+            communities,
+            level_starts,
+            leaves,
+            members,
+        }
+    }
+
+    /// Number of levels, leaves being level 0.
+    pub fn level_count(&self) -> usize {
+        self.level_starts.len() - 1
+    }
+
+    /// Total number of communities over all levels.
+    pub fn len(&self) -> usize {
+        self.communities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.communities.is_empty()
+    }
+
+    pub fn community(&self, index: usize) -> &TreeCommunity {
+        &self.communities[index]
+    }
+
+    /// Indices of the communities at `level`, ascending by label.
+    pub fn level(&self, level: usize) -> std::ops::Range<usize> {
+        self.level_starts[level]..self.level_starts[level + 1]
+    }
+
+    /// Indices of the top-level communities.
+    pub fn roots(&self) -> std::ops::Range<usize> {
+        self.level(self.level_count() - 1)
+    }
+
+    /// Leaf community containing level-0 `node`, `None` if it was removed.
+    pub fn leaf_of(&self, node: usize) -> Option<usize> {
+        self.leaves[node]
+    }
+
+    /// Communities containing level-0 `node`, from its leaf up to its root.
+    pub fn path(&self, node: usize) -> Vec<usize> {
+        let mut path = Vec::with_capacity(self.level_count());
+        let mut current = self.leaf_of(node);
+        while let Some(index) = current {
+            path.push(index);
+            current = self.communities[index].parent;
+        }
+        path
+    }
+
+    /// Level-0 nodes of community `index`, ascending.
+    pub fn members(&self, index: usize) -> Vec<usize> {
+        let mut stack = vec![index];
+        let mut nodes = Vec::with_capacity(self.communities[index].size);
+        while let Some(current) = stack.pop() {
+            match self.members.get(&current) {
+                Some(leaf_nodes) => nodes.extend_from_slice(leaf_nodes),
+                None => stack.extend(&self.communities[current].children),
+            }
+        }
+        nodes.sort_unstable();
+        nodes
+    }
+
+    /// Every community after all of its children: leaves first, roots last.
+    pub fn bottom_up(&self) -> impl DoubleEndedIterator<Item = (usize, &TreeCommunity)> + '_ {
+        self.communities.iter().enumerate()
+    }
+
+    /// Every community before all of its children: roots first, leaves last.
+    pub fn top_down(&self) -> impl Iterator<Item = (usize, &TreeCommunity)> + '_ {
+        (0..self.level_count())
+            .rev()
+            .flat_map(move |level| self.level(level))
+            .map(move |index| (index, &self.communities[index]))
+    }
+}
@@ -0,0 +1,330 @@
+use crate::core::algorithm::hit_leiden::{
+    self, check_endpoints, check_node_weights, optimize, partition_result, restart_from,
+    PassSummary,
+};
+use crate::core::algorithm::quality::CommunityStats;
+use crate::core::algorithm::randomized::RandomMerge;
+use crate::core::config::RunConfig;
+use crate::core::error::HitLeidenError;
+use crate::core::graph::delta::DeltaBatch;
+use crate::core::partition::events::{self, CommunityChanges, EventSink};
+use crate::core::partition::lineage::CommunityLineage;
+use crate::core::partition::stable_ids::StableCommunityIds;
+use crate::core::partition::state::PartitionState;
+use crate::core::partition::tree::CommunityTree;
+use crate::core::types::{GraphInput, PartitionResult, StopReason};
+use bitvec::prelude::*;
+
+/// Long-lived HIT-Leiden session.
+///
+/// Owns the hierarchical `PartitionState` (supergraphs and per-level mappings)
+/// so that each call to [`IncrementalSession::apply_delta`] only re-optimises
+/// the region of the graph affected by that delta instead of re-clustering the
+/// whole graph.
+#[derive(Clone, Debug)]
+pub struct IncrementalSession {
+    config: RunConfig,
+    state: PartitionState,
+    stats: CommunityStats,
+    version: u64,
+    /// How the most recent build or update went.
+    last_summary: PassSummary,
+    ids: StableCommunityIds,
+    lineage: CommunityLineage,
+    sinks: Sinks,
+}
+
+/// Subscribers of a session. Not carried over to clones, which would
+/// otherwise publish a diverging history to the same receivers.
+#[derive(Default)]
+struct Sinks(Vec<Box<dyn EventSink>>);
+
+impl Clone for Sinks {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl std::fmt::Debug for Sinks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} sink(s)", self.0.len())
+    }
+}
+
+impl IncrementalSession {
+    /// Cluster `initial` from scratch and keep the resulting state for later deltas.
+    pub fn new(initial: &GraphInput, config: RunConfig) -> Result<Self, HitLeidenError> {
+        Self::with_node_weights(initial, &vec![1.0; initial.node_count], config)
+    }
+
+    /// Like [`IncrementalSession::new`], with `node_weights[i]` as the weight
+    /// of node `i`. Later batches set weights with [`DeltaBatch::set_node_weight`].
+    pub fn with_node_weights(
+        initial: &GraphInput,
+        node_weights: &[f64],
+        config: RunConfig,
+    ) -> Result<Self, HitLeidenError> {
+        config.validate().map_err(HitLeidenError::InvalidInput)?;
+        check_endpoints(initial, initial.node_count)?;
+        check_node_weights(node_weights, initial.node_count)?;
+
+        let merged = initial.merge_parallel_edges(config.parallel_edges, config.directed);
+        let (state, stats, last_summary) = optimize(
+            &merged,
+            &config,
+            PartitionState::with_weights(initial.node_count, node_weights.to_vec()),
+        );
+
+        let ids = StableCommunityIds::new(&state);
+        Ok(Self {
+            config,
+            lineage: CommunityLineage::new(0, &ids.communities()),
+            ids,
+            state,
+            stats,
+            version: 0,
+            last_summary,
+            sinks: Sinks::default(),
+        })
+    }
+
+    /// Resume from `state`, typically one loaded with [`PartitionState::load`]
+    /// after a restart, without re-clustering. Community identifiers and the
+    /// lineage start afresh, and the version restarts at zero; a session
+    /// saved with [`IncrementalSession::save`] and resumed with
+    /// [`IncrementalSession::load`] keeps all three.
+    pub fn restore(state: PartitionState, config: RunConfig) -> Result<Self, HitLeidenError> {
+        let ids = StableCommunityIds::new(&state);
+        let lineage = CommunityLineage::new(0, &ids.communities());
+        Self::resume(state, config, 0, ids, lineage)
+    }
+
+    /// Like [`IncrementalSession::restore`], carrying on with the version,
+    /// identifiers and lineage of the session `state` was taken from.
+    pub(crate) fn resume(
+        state: PartitionState,
+        config: RunConfig,
+        version: u64,
+        ids: StableCommunityIds,
+        lineage: CommunityLineage,
+    ) -> Result<Self, HitLeidenError> {
+        check_restorable(&state, &config)?;
+        if !ids.describes(&state) {
+            return Err(HitLeidenError::Checkpoint(
+                "community identifiers do not match the state".to_string(),
+            ));
+        }
+        let stats = CommunityStats::from_partition(
+            &state.supergraphs[0],
+            &state.node_to_comm,
+            &state.node_weights_per_level[0],
+            &state.removed_nodes,
+        );
+        Ok(Self {
+            config,
+            lineage,
+            ids,
+            state,
+            stats,
+            version,
+            last_summary: PassSummary {
+                movement_rounds: 0,
+                passes: 0,
+                stop_reason: StopReason::Incremental,
+            },
+            sinks: Sinks::default(),
+        })
+    }
+
+    /// Apply one batch of graph changes and return the updated partition.
+    ///
+    /// The whole batch is validated before anything is applied, so a rejected
+    /// batch leaves the session unchanged. Added nodes get the next free ids;
+    /// removed nodes keep their id and community label but lose all edges.
+    pub fn apply_delta(&mut self, batch: &DeltaBatch) -> Result<&[usize], HitLeidenError> {
+        let delta = batch.resolve(
+            &self.state.supergraphs[0],
+            &self.state.removed_nodes,
+            &format!("session:v{}", self.version + 1),
+        )?;
+
+        let grows = delta.node_count > self.node_count();
+        // Removed nodes leave with their weight, so movement already sees
+        // their communities without them
+        let mut reweights = batch.node_weights.clone();
+        for &node in &batch.removed_nodes {
+            self.state.removed_nodes.set(node, true);
+            reweights.push((node, 0.0));
+        }
+        if !delta.edges.is_empty() || grows || !reweights.is_empty() {
+            let (movement_rounds, mut touched) = hit_leiden::hit_leiden(
+                &mut self.state,
+                &delta,
+                &reweights,
+                &self.config,
+                RandomMerge::from_config(&self.config).map(|r| r.for_stream(self.version + 1)),
+            );
+            self.last_summary = PassSummary {
+                movement_rounds,
+                passes: 1,
+                stop_reason: StopReason::Incremental,
+            };
+            for &node in &batch.removed_nodes {
+                touched.set(node, true);
+            }
+            self.stats.update(
+                &self.state.supergraphs[0],
+                &self.state.node_to_comm,
+                &self.state.node_weights_per_level[0],
+                &self.state.removed_nodes,
+                &touched,
+            );
+            self.advance(&touched);
+        }
+
+        Ok(&self.state.node_to_comm)
+    }
+
+    /// Reject `batch` exactly as [`IncrementalSession::apply_delta`] would,
+    /// without applying it.
+    pub(crate) fn check_delta(&self, batch: &DeltaBatch) -> Result<(), HitLeidenError> {
+        batch
+            .resolve(&self.state.supergraphs[0], &self.state.removed_nodes, "")
+            .map(|_| ())
+    }
+
+    /// Current level-0 community assignment.
+    pub fn partition(&self) -> &[usize] {
+        &self.state.node_to_comm
+    }
+
+    /// Summary of the current partition in the same shape `run` reports. The
+    /// iteration and pass counts cover the most recent build or update only.
+    pub fn partition_result(&self) -> PartitionResult {
+        partition_result(
+            format!("session:v{}", self.version),
+            &self.state,
+            &self.stats,
+            &self.last_summary,
+            &self.config,
+        )
+    }
+
+    /// Switch the session to resolution `resolution` and re-optimise.
+    ///
+    /// The hierarchy built so far was optimised for the old resolution, so it
+    /// is discarded: the current graph is clustered again with the usual
+    /// outer loop, starting from the current level-0 partition. Later deltas
+    /// then use the new resolution. Setting the current resolution again is a
+    /// no-op; an invalid one leaves the session unchanged.
+    pub fn set_resolution(&mut self, resolution: f64) -> Result<&[usize], HitLeidenError> {
+        if resolution == self.config.resolution {
+            return Ok(&self.state.node_to_comm);
+        }
+        let config = RunConfig {
+            resolution,
+            ..self.config.clone()
+        };
+        config.validate().map_err(HitLeidenError::InvalidInput)?;
+
+        // The level-0 graph already has its parallel edges merged
+        let graph =
+            self.state.supergraphs[0].to_graph_input(&format!("session:v{}", self.version + 1));
+        let (state, stats, summary) = optimize(&graph, &config, restart_from(&self.state));
+        self.config = config;
+        self.state = state;
+        self.stats = stats;
+        self.last_summary = summary;
+        self.advance(&bitvec![1; self.node_count()]);
+
+        Ok(&self.state.node_to_comm)
+    }
+
+    /// Carry community identifiers over to the new state, bump the version,
+    /// extend the lineage and tell subscribers what changed. `touched` holds
+    /// the level-0 nodes whose labels may have changed.
+    fn advance(&mut self, touched: &BitVec) {
+        self.version += 1;
+        let changes = self.ids.update(&self.state, touched);
+        if let Some(top) = self.ids.level_count().checked_sub(1) {
+            self.lineage.record_changes(self.version, &changes[top]);
+        }
+
+        if !self.sinks.0.is_empty() {
+            let changes = CommunityChanges {
+                version: self.version,
+                events: events::from_changes(&changes),
+            };
+            for sink in &mut self.sinks.0 {
+                sink.publish(&changes);
+            }
+        }
+    }
+
+    /// Deliver the [`CommunityChanges`] of every later update to `sink`,
+    /// including updates that leave all communities as they were. Clones of
+    /// the session do not inherit its subscribers.
+    pub fn subscribe(&mut self, sink: impl EventSink + 'static) {
+        self.sinks.0.push(Box::new(sink));
+    }
+
+    /// Identifiers of the communities at every level, kept stable across
+    /// updates for communities that survive them.
+    pub fn community_ids(&self) -> &StableCommunityIds {
+        &self.ids
+    }
+
+    /// The current community hierarchy, labelled with stable identifiers.
+    pub fn community_tree(&self) -> CommunityTree {
+        CommunityTree::with_ids(&self.state, &self.ids)
+    }
+
+    /// How the final communities descend from each other across versions.
+    pub fn lineage(&self) -> &CommunityLineage {
+        &self.lineage
+    }
+
+    pub fn state(&self) -> &PartitionState {
+        &self.state
+    }
+
+    pub fn config(&self) -> &RunConfig {
+        &self.config
+    }
+
+    /// Number of non-empty deltas applied since the session was created.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Number of allocated node ids, including removed ones.
+    pub fn node_count(&self) -> usize {
+        self.state.node_to_comm.len()
+    }
+
+    pub fn is_removed(&self, node: usize) -> bool {
+        self.state.is_removed(node)
+    }
+}
+
+/// Check that a session with `config` can continue from `state`.
+fn check_restorable(state: &PartitionState, config: &RunConfig) -> Result<(), HitLeidenError> {
+    config.validate().map_err(HitLeidenError::InvalidInput)?;
+    if state.supergraphs.is_empty() {
+        return Err(HitLeidenError::InvalidInput(
+            "cannot restore a session from a state that was never clustered".to_string(),
+        ));
+    }
+    if state.supergraphs[0].is_directed() != config.directed {
+        return Err(HitLeidenError::InvalidInput(format!(
+            "config.directed is {} but the state's graph is {}",
+            config.directed,
+            if config.directed {
+                "undirected"
+            } else {
+                "directed"
+            }
+        )));
+    }
+    Ok(())
+}
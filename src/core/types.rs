@@ -1,3 +1,7 @@
+use crate::core::config::ParallelEdges;
+use std::borrow::Cow;
+use std::collections::hash_map::{Entry, HashMap};
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum GraphFormat {
     EdgeList,
@@ -95,13 +99,28 @@ pub struct RunExecution {
     pub fallback_reason: Option<String>,
 }
 
+/// Why optimisation stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The last pass improved quality by less than `quality_tolerance`.
+    Converged,
+    /// `max_iterations` passes were run.
+    MaxIterations,
+    /// A single incremental pass over the region affected by a delta.
+    Incremental,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct PartitionResult {
     pub run_id: String,
     pub node_to_community: Vec<usize>,
     pub community_count: usize,
     pub quality_score: f64,
+    /// Movement rounds performed, summed over all passes and levels.
     pub iteration_count: usize,
+    /// Full move/refine/aggregate passes performed.
+    pub pass_count: usize,
+    pub stop_reason: StopReason,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -136,6 +155,51 @@ impl GraphInput {
             edges: Vec::new(),
         }
     }
+
+    /// This graph with every set of parallel edges merged into one by `merge`,
+    /// at the position of the first. `(u, v)` and `(v, u)` are parallel unless
+    /// `directed`; repeated self-loops are parallel too. Borrows `self` when
+    /// nothing changes.
+    pub fn merge_parallel_edges(&self, merge: ParallelEdges, directed: bool) -> Cow<'_, Self> {
+        let key = |u: usize, v: usize| if directed || u <= v { (u, v) } else { (v, u) };
+        let weight = |w: Option<f64>| match merge {
+            ParallelEdges::Count => 1.0,
+            ParallelEdges::Sum | ParallelEdges::Max => w.unwrap_or(1.0),
+        };
+
+        let mut positions: HashMap<(usize, usize), usize> =
+            HashMap::with_capacity(self.edges.len());
+        let mut edges: Vec<(usize, usize, f64)> = Vec::with_capacity(self.edges.len());
+        for &(u, v, w) in &self.edges {
+            match positions.entry(key(u, v)) {
+                Entry::Vacant(entry) => {
+                    entry.insert(edges.len());
+                    edges.push((u, v, weight(w)));
+                }
+                Entry::Occupied(entry) => {
+                    let merged = &mut edges[*entry.get()].2;
+                    *merged = match merge {
+                        ParallelEdges::Sum | ParallelEdges::Count => *merged + weight(w),
+                        ParallelEdges::Max => merged.max(weight(w)),
+                    };
+                }
+            }
+        }
+
+        let reweighted = merge == ParallelEdges::Count
+            && self
+                .edges
+                .iter()
+                .any(|&(_, _, w)| w.is_some_and(|w| w != 1.0));
+        if edges.len() == self.edges.len() && !reweighted {
+            return Cow::Borrowed(self);
+        }
+        Cow::Owned(Self {
+            dataset_id: self.dataset_id.clone(),
+            node_count: self.node_count,
+            edges: edges.into_iter().map(|(u, v, w)| (u, v, Some(w))).collect(),
+        })
+    }
 }
 
 /// Results from a single batch update
@@ -18,7 +18,7 @@ pub fn validate(
             quality_delta_vs_reference: Some(quality_delta),
             equivalence_passed: same_partition,
         },
-        RunMode::Throughput => ValidationOutcome {
+        RunMode::Throughput | RunMode::DeterministicParallel => ValidationOutcome {
             hard_invariants_passed: true,
             deterministic_identity_passed: None,
             quality_delta_vs_reference: Some(quality_delta),
@@ -3,15 +3,26 @@ pub mod cli;
 pub mod core;
 
 pub use core::backend::{AccelerationTarget, GraphBackend, GraphSource};
-pub use core::config::{RunConfig, RunMode};
+pub use core::config::{ParallelEdges, RunConfig, RunMode};
+pub use core::durable::DurableSession;
 pub use core::error::HitLeidenError;
+pub use core::graph::delta::{DeltaBatch, EdgeDelta};
 pub use core::report::{BenchmarkOutcome, ValidationOutcome};
-pub use core::types::{GraphInput, RunOutcome};
+pub use core::session::IncrementalSession;
+pub use core::types::{GraphInput, RunOutcome, StopReason};
 
 pub fn run(graph: &GraphInput, config: &RunConfig) -> Result<RunOutcome, HitLeidenError> {
     core::algorithm::hit_leiden::run(graph, config)
 }
 
+pub fn run_with_node_weights(
+    graph: &GraphInput,
+    node_weights: &[f64],
+    config: &RunConfig,
+) -> Result<RunOutcome, HitLeidenError> {
+    core::algorithm::hit_leiden::run_with_node_weights(graph, node_weights, config)
+}
+
 pub fn project_from_neo4j(
     source_config: &core::graph::neo4j_snapshot::Neo4jSourceConfig,
     projection_config: &core::graph::neo4j_mapping::ProjectionConfig,
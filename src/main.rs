@@ -0,0 +1,15 @@
+use clap::Parser;
+use hit_leiden::cli::commands::execute;
+use hit_leiden::cli::options::Cli;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match execute(&cli.command) {
+        Ok(status) => ExitCode::from(status.code()),
+        Err(error) => {
+            eprintln!("hit_leiden: {error}");
+            ExitCode::from(error.exit_status().code())
+        }
+    }
+}
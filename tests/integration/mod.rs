@@ -1 +1,22 @@
-// Integration test module marker.
+//! Fixtures shared by the integration tests.
+
+use hit_leiden::GraphInput;
+
+/// `cliques` cliques of `size` nodes, joined in a ring by single edges.
+pub fn ring_of_cliques(cliques: usize, size: usize) -> GraphInput {
+    let mut edges = Vec::new();
+    for c in 0..cliques {
+        let base = c * size;
+        for i in 0..size {
+            for j in (i + 1)..size {
+                edges.push((base + i, base + j, None));
+            }
+        }
+        edges.push((base, ((c + 1) % cliques) * size + 1, None));
+    }
+    GraphInput {
+        dataset_id: "ring-of-cliques".to_string(),
+        node_count: cliques * size,
+        edges,
+    }
+}
@@ -0,0 +1,149 @@
+use crate::integration::ring_of_cliques;
+use hit_leiden::core::binary::fnv1a64;
+use hit_leiden::core::partition::checkpoint::FORMAT_VERSION;
+use hit_leiden::core::partition::state::PartitionState;
+use hit_leiden::{DeltaBatch, HitLeidenError, IncrementalSession, RunConfig};
+
+fn trained_session() -> IncrementalSession {
+    let graph = ring_of_cliques(12, 5);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    session
+        .apply_delta(
+            &DeltaBatch::new()
+                .add_nodes(3)
+                .insert(60, 61, 1.0)
+                .insert(61, 62, 1.0)
+                .insert(0, 60, 1.0)
+                .remove_node(7)
+                .set_node_weight(12, 2.0),
+        )
+        .expect("delta");
+    session
+}
+
+fn checkpoint_error(bytes: &[u8]) -> String {
+    match PartitionState::from_checkpoint_bytes(bytes) {
+        Err(HitLeidenError::Checkpoint(message)) => message,
+        other => panic!("expected a checkpoint error, got {other:?}"),
+    }
+}
+
+/// Rewrite the header versions and re-seal a checkpoint after editing its payload.
+fn reseal(bytes: &[u8], version: u32, min_reader: u32, extra: &[u8]) -> Vec<u8> {
+    let payload_len = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+    let mut payload = bytes[24..24 + payload_len].to_vec();
+    payload.extend_from_slice(extra);
+    let mut out = bytes[..8].to_vec();
+    out.extend_from_slice(&version.to_le_bytes());
+    out.extend_from_slice(&min_reader.to_le_bytes());
+    out.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    out.extend_from_slice(&payload);
+    out.extend_from_slice(&fnv1a64(&payload).to_le_bytes());
+    out
+}
+
+#[test]
+fn restored_session_continues_like_the_original() {
+    let mut original = trained_session();
+    let path = std::env::temp_dir().join(format!("hit_leiden_{}.ckpt", std::process::id()));
+    original.state().save(&path).expect("save");
+    let loaded = PartitionState::load(&path).expect("load");
+    std::fs::remove_file(&path).expect("cleanup");
+    assert_eq!(&loaded, original.state());
+
+    let mut restored = IncrementalSession::restore(loaded, RunConfig::default()).expect("restore");
+    assert_eq!(restored.partition(), original.partition());
+    assert_eq!(
+        restored.partition_result().quality_score,
+        original.partition_result().quality_score
+    );
+
+    let batch = DeltaBatch::new()
+        .insert(3, 33, 2.0)
+        .insert(4, 34, 2.0)
+        .delete(10, 11);
+    original.apply_delta(&batch).expect("delta");
+    restored.apply_delta(&batch).expect("delta");
+    assert_eq!(restored.state(), original.state());
+}
+
+#[test]
+fn corrupt_checkpoints_are_rejected() {
+    let bytes = trained_session().state().to_checkpoint_bytes();
+
+    let mut flipped = bytes.clone();
+    let middle = flipped.len() / 2;
+    flipped[middle] ^= 0x40;
+    assert!(checkpoint_error(&flipped).contains("checksum mismatch"));
+
+    assert!(checkpoint_error(&bytes[..bytes.len() - 3]).contains("truncated"));
+    assert!(checkpoint_error(b"not a checkpoint at all").contains("not a checkpoint"));
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(checkpoint_error(&trailing).contains("trailing"));
+
+    // A well-sealed payload that is not a consistent state
+    let mut empty = bytes[..16].to_vec();
+    empty.extend_from_slice(&0u64.to_le_bytes());
+    let garbage = reseal(&empty, FORMAT_VERSION, FORMAT_VERSION, &[1, 2, 3]);
+    assert!(checkpoint_error(&garbage).contains("payload"));
+
+    // A tampered state, sealed with a valid checksum, whose upper level
+    // labels a community past every node
+    let mut tampered = trained_session().state().clone();
+    let top = tampered.levels - 1;
+    assert!(top > 0);
+    tampered.community_mapping_per_level[top][0] = usize::MAX - 1;
+    assert_eq!(
+        checkpoint_error(&tampered.to_checkpoint_bytes()),
+        format!("level {top} community label out of range")
+    );
+}
+
+#[test]
+fn format_versions_gate_compatibility() {
+    let session = trained_session();
+    let bytes = session.state().to_checkpoint_bytes();
+
+    // A newer writer whose checkpoints this build cannot read
+    let incompatible = reseal(&bytes, FORMAT_VERSION + 1, FORMAT_VERSION + 1, &[]);
+    assert!(checkpoint_error(&incompatible).contains("needs a reader"));
+
+    // A newer writer that appended fields but stays readable
+    let compatible = reseal(&bytes, FORMAT_VERSION + 1, FORMAT_VERSION, &[0xAB; 16]);
+    let loaded = PartitionState::from_checkpoint_bytes(&compatible).expect("compatible");
+    assert_eq!(&loaded, session.state());
+
+    // The current version must not carry unknown fields
+    let padded = reseal(&bytes, FORMAT_VERSION, FORMAT_VERSION, &[0xAB; 16]);
+    assert!(checkpoint_error(&padded).contains("unexpected data"));
+}
+
+#[test]
+fn version_two_checkpoints_still_load() {
+    let session = trained_session();
+    let state = session.state();
+    let bytes = state.to_checkpoint_bytes();
+
+    // Version 2 stored two level-0 weight arrays after the community labels
+    let n = state.node_to_comm.len();
+    let payload_len = u64::from_le_bytes(bytes[16..24].try_into().unwrap()) as usize;
+    let payload = &bytes[24..24 + payload_len];
+    let (labels, rest) = payload.split_at(8 * (n + 1));
+    let mut weights = (n as u64).to_le_bytes().to_vec();
+    for &weight in state.node_weights() {
+        weights.extend_from_slice(&weight.to_le_bytes());
+    }
+    let mut old = labels.to_vec();
+    old.extend_from_slice(&weights);
+    old.extend_from_slice(&weights);
+    old.extend_from_slice(rest);
+
+    let mut header = bytes[..16].to_vec();
+    header.extend_from_slice(&0u64.to_le_bytes());
+    let version_two = reseal(&header, 2, 1, &old);
+    let loaded = PartitionState::from_checkpoint_bytes(&version_two).expect("version 2");
+    assert_eq!(&loaded, state);
+    assert_eq!(loaded.node_weights()[12], 2.0);
+}
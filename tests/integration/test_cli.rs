@@ -0,0 +1,380 @@
+use clap::Parser;
+use hit_leiden::cli::commands::execute;
+use hit_leiden::cli::error::ExitStatus;
+use hit_leiden::cli::options::Cli;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hit_leiden_cli_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Two triangles joined by a bridge that is listed twice.
+fn write_graph(dir: &Path) -> PathBuf {
+    let path = dir.join("graph.txt");
+    fs::write(
+        &path,
+        "# two triangles\n0 1\n1 2\n2 0\n3 4\n4 5\n5 3\n2 3 0.5\n2 3\n",
+    )
+    .unwrap();
+    path
+}
+
+fn hit_leiden(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hit_leiden"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn exec(args: &[&str]) -> ExitStatus {
+    let cli =
+        Cli::try_parse_from(std::iter::once("hit_leiden").chain(args.iter().copied())).unwrap();
+    execute(&cli.command).unwrap()
+}
+
+fn partition(path: &Path) -> Vec<(usize, usize)> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| {
+            let (node, community) = line.split_once(' ').unwrap();
+            (node.parse().unwrap(), community.parse().unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn run_writes_the_partition_and_report() {
+    let dir = scratch_dir("run");
+    let graph = write_graph(&dir);
+    let output = hit_leiden(&["run", "--path", graph.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "# node community\n0 0\n1 0\n2 0\n3 1\n4 1\n5 1\n");
+    let report = String::from_utf8(output.stderr).unwrap();
+    assert!(report.contains("Nodes: 6\n"));
+    assert!(report.contains("Edges: 8\n"));
+    assert!(report.contains("Communities: 2\n"));
+    assert!(report.contains("Checksum: fnv1a64:"));
+
+    // Every RunConfig knob is reachable from the command line
+    let out = dir.join("cpm.part");
+    let report = dir.join("cpm.txt");
+    let status = exec(&[
+        "run",
+        "--path",
+        graph.to_str().unwrap(),
+        "--quality",
+        "cpm",
+        "--resolution",
+        "0.4",
+        "--mode",
+        "deterministic-parallel",
+        "--seed",
+        "7",
+        "--randomness",
+        "0.05",
+        "--max-iterations",
+        "3",
+        "--quality-tolerance",
+        "0",
+        "--max-community-nodes",
+        "3",
+        "--parallel-edges",
+        "max",
+        "--directed",
+        "--backend",
+        "mmap",
+        "--output",
+        out.to_str().unwrap(),
+        "--report",
+        report.to_str().unwrap(),
+    ]);
+    assert_eq!(status, ExitStatus::Success);
+    assert_eq!(partition(&out).len(), 6);
+    let report = fs::read_to_string(&report).unwrap();
+    assert!(report.contains("Mode: DeterministicParallel\n"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn update_applies_a_delta_script() {
+    let dir = scratch_dir("update");
+    let graph = write_graph(&dir);
+    let script = dir.join("deltas.txt");
+    fs::write(
+        &script,
+        "insert 5 6\nadd-nodes 2 # nodes 6 and 7\ninsert 6 7 2\ncommit\n\
+         delete 2 3\nremove-node 0\nnode-weight 1 2\n",
+    )
+    .unwrap();
+    let out = dir.join("updated.part");
+    let report = dir.join("report.txt");
+    let status = exec(&[
+        "update",
+        "--path",
+        graph.to_str().unwrap(),
+        "--deltas",
+        script.to_str().unwrap(),
+        "--output",
+        out.to_str().unwrap(),
+        "--report",
+        report.to_str().unwrap(),
+    ]);
+    assert_eq!(status, ExitStatus::Success);
+
+    let assignment = partition(&out);
+    // The removed node is left out
+    assert_eq!(
+        assignment.iter().map(|&(node, _)| node).collect::<Vec<_>>(),
+        vec![1, 2, 3, 4, 5, 6, 7]
+    );
+    assert_eq!(assignment[5].1, assignment[6].1);
+    // Communities are numbered from 0 even after nodes and communities come and go
+    let mut ids: Vec<usize> = assignment.iter().map(|&(_, community)| community).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids, (0..ids.len()).collect::<Vec<_>>());
+    let report = fs::read_to_string(&report).unwrap();
+    assert!(report.contains("Version 0: "));
+    assert!(report.contains("Version 2: "));
+
+    fs::write(&script, "insert 0 1\ncommit\nexplode 1 2\n").unwrap();
+    let output = hit_leiden(&[
+        "update",
+        "--path",
+        graph.to_str().unwrap(),
+        "--deltas",
+        script.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("line 3: unknown change `explode`"),
+        "{stderr}"
+    );
+
+    // Without updates the partition is written exactly as `run` writes it
+    fs::write(&script, "# nothing to apply\n").unwrap();
+    let unchanged = dir.join("unchanged.part");
+    let status = exec(&[
+        "update",
+        "--path",
+        graph.to_str().unwrap(),
+        "--deltas",
+        script.to_str().unwrap(),
+        "--output",
+        unchanged.to_str().unwrap(),
+    ]);
+    assert_eq!(status, ExitStatus::Success);
+    let output = hit_leiden(&["run", "--path", graph.to_str().unwrap()]);
+    assert_eq!(
+        fs::read_to_string(&unchanged).unwrap(),
+        String::from_utf8(output.stdout).unwrap()
+    );
+
+    // Rejected batches name the batch
+    fs::write(&script, "insert 0 5\ncommit\ndelete 0 4\n").unwrap();
+    let output = hit_leiden(&[
+        "update",
+        "--path",
+        graph.to_str().unwrap(),
+        "--deltas",
+        script.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("batch 2"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn validate_and_compare_report_through_the_exit_code() {
+    let dir = scratch_dir("compare");
+    let graph = write_graph(&dir);
+    let output = hit_leiden(&[
+        "validate",
+        "--path",
+        graph.to_str().unwrap(),
+        "--mode",
+        "throughput",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(report.contains("Equivalence passed: true\n"));
+
+    let left = dir.join("left.part");
+    let relabelled = dir.join("relabelled.part");
+    let split = dir.join("split.part");
+    fs::write(&left, "0 0\n1 0\n2 0\n3 1\n4 1\n5 1\n").unwrap();
+    fs::write(
+        &relabelled,
+        "# node community\n5 9\n4 9\n3 9\n2 4\n1 4\n0 4\n",
+    )
+    .unwrap();
+    fs::write(&split, "0 0\n1 0\n2 2\n3 1\n4 1\n5 1\n").unwrap();
+    let compare =
+        |right: &Path| hit_leiden(&["compare", left.to_str().unwrap(), right.to_str().unwrap()]);
+
+    let same = compare(&relabelled);
+    assert_eq!(same.status.code(), Some(0));
+    let report = String::from_utf8(same.stdout).unwrap();
+    assert!(report.contains("NMI: 1\n"));
+    assert!(report.contains("Identical: true\n"));
+
+    let different = compare(&split);
+    assert_eq!(different.status.code(), Some(1));
+    let report = String::from_utf8(different.stdout).unwrap();
+    assert!(report.contains("Identical: false\n"));
+    assert!(report.contains("Right communities: 3\n"));
+
+    fs::write(&split, "0 0\n1 0\n").unwrap();
+    assert_eq!(compare(&split).status.code(), Some(3));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn stats_and_convert_describe_and_rewrite_graphs() {
+    let dir = scratch_dir("convert");
+    let graph = write_graph(&dir);
+    let output = hit_leiden(&[
+        "stats",
+        "--path",
+        graph.to_str().unwrap(),
+        "--node-count",
+        "7",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    let stats = String::from_utf8(output.stdout).unwrap();
+    for expected in [
+        "Nodes: 7\n",
+        "Edges: 8\n",
+        "Weighted: true\n",
+        "Parallel edges: 1\n",
+        "Isolated nodes: 1\n",
+        "Total weight: 7.5\n",
+    ] {
+        assert!(stats.contains(expected), "{expected:?} in {stats}");
+    }
+
+    let csv = dir.join("graph.csv");
+    let status = exec(&[
+        "convert",
+        "--path",
+        graph.to_str().unwrap(),
+        "--merge-parallel-edges",
+        "sum",
+        "--to-separator",
+        "comma",
+        "--to-one-based",
+        "--output",
+        csv.to_str().unwrap(),
+    ]);
+    assert_eq!(status, ExitStatus::Success);
+    assert_eq!(
+        fs::read_to_string(&csv).unwrap(),
+        "1,2,1\n2,3,1\n3,1,1\n4,5,1\n5,6,1\n6,4,1\n3,4,1.5\n"
+    );
+
+    // The converted file clusters like the original
+    let original = dir.join("original.part");
+    let converted = dir.join("converted.part");
+    exec(&[
+        "run",
+        "--path",
+        graph.to_str().unwrap(),
+        "--output",
+        original.to_str().unwrap(),
+        "--report",
+        dir.join("r1").to_str().unwrap(),
+    ]);
+    exec(&[
+        "run",
+        "--path",
+        csv.to_str().unwrap(),
+        "--one-based",
+        "--output",
+        converted.to_str().unwrap(),
+        "--report",
+        dir.join("r2").to_str().unwrap(),
+    ]);
+    let shifted: Vec<_> = partition(&original)
+        .into_iter()
+        .map(|(node, community)| (node + 1, community))
+        .collect();
+    assert_eq!(partition(&converted), shifted);
+
+    // CSR files are recognised by their header and cluster the same way
+    let csr = dir.join("graph.csr");
+    let binary = dir.join("binary.part");
+    let status = exec(&[
+        "convert",
+        "--path",
+        graph.to_str().unwrap(),
+        "--to",
+        "csr-binary",
+        "--output",
+        csr.to_str().unwrap(),
+    ]);
+    assert_eq!(status, ExitStatus::Success);
+    exec(&[
+        "run",
+        "--path",
+        csr.to_str().unwrap(),
+        "--output",
+        binary.to_str().unwrap(),
+        "--report",
+        dir.join("r3").to_str().unwrap(),
+    ]);
+    assert_eq!(partition(&binary), partition(&original));
+    let output = hit_leiden(&["run", "--path", csr.to_str().unwrap(), "--directed"]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("holds an undirected graph"));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn failures_map_to_exit_codes() {
+    let dir = scratch_dir("exit_codes");
+    let broken = dir.join("broken.txt");
+    fs::write(&broken, "0 1\n1 x\n").unwrap();
+    let output = hit_leiden(&["run", "--path", broken.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("broken.txt: line 2: `x` is not a node id"));
+
+    let graph = write_graph(&dir);
+    let invalid_setting = hit_leiden(&[
+        "run",
+        "--path",
+        graph.to_str().unwrap(),
+        "--resolution",
+        "0",
+    ]);
+    assert_eq!(invalid_setting.status.code(), Some(3));
+
+    let unwritable = dir.join("missing").join("out.part");
+    let output = hit_leiden(&[
+        "run",
+        "--path",
+        graph.to_str().unwrap(),
+        "--output",
+        unwritable.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(4));
+
+    assert_eq!(hit_leiden(&["run"]).status.code(), Some(2));
+    assert_eq!(hit_leiden(&["cluster"]).status.code(), Some(2));
+    assert_eq!(hit_leiden(&["--help"]).status.code(), Some(0));
+    fs::remove_dir_all(&dir).unwrap();
+}
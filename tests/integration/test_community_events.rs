@@ -0,0 +1,197 @@
+use crate::integration::ring_of_cliques;
+use hit_leiden::core::partition::events::{CommunityChanges, CommunityEvent};
+use hit_leiden::{DeltaBatch, IncrementalSession, RunConfig};
+use std::collections::BTreeMap;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+fn top_level(session: &IncrementalSession, changes: &CommunityChanges) -> Vec<CommunityEvent> {
+    let top = session.community_ids().level_count() - 1;
+    changes
+        .events
+        .iter()
+        .filter(|event| event.level() == top)
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn new_clique_is_reported_as_created_through_a_channel() {
+    let graph = ring_of_cliques(6, 4);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let (sender, receiver) = mpsc::channel();
+    session.subscribe(sender);
+
+    let mut batch = DeltaBatch::new().add_nodes(4);
+    for i in 24..28 {
+        for j in (i + 1)..28 {
+            batch = batch.insert(i, j, 1.0);
+        }
+    }
+    session.apply_delta(&batch).expect("delta");
+
+    let changes = receiver.try_recv().expect("changes published");
+    assert_eq!(changes.version, 1);
+    let id = session.community_ids().communities()[24].unwrap();
+    assert_eq!(
+        top_level(&session, &changes),
+        vec![CommunityEvent::Created {
+            level: session.community_ids().level_count() - 1,
+            id,
+            members: vec![24, 25, 26, 27],
+        }]
+    );
+    assert!(receiver.try_recv().is_err());
+}
+
+#[test]
+fn fused_cliques_are_reported_as_merged() {
+    let graph = ring_of_cliques(8, 4);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let before = session.community_ids().communities();
+    let published = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&published);
+    session.subscribe(move |changes: &CommunityChanges| sink.lock().unwrap().push(changes.clone()));
+
+    let mut batch = DeltaBatch::new();
+    for i in 0..4 {
+        for j in 4..8 {
+            batch = if (i, j) == (0, 5) {
+                batch.adjust_weight(i, j, 1.0)
+            } else {
+                batch.insert(i, j, 1.0)
+            };
+        }
+    }
+    session.apply_delta(&batch).expect("delta");
+
+    let published = published.lock().unwrap();
+    assert_eq!(published.len(), 1);
+    let events = top_level(&session, &published[0]);
+    let level = session.community_ids().level_count() - 1;
+    let merged = session.community_ids().communities()[0].unwrap();
+    let (kept, lost) = if merged == before[0].unwrap() {
+        (0, 4)
+    } else {
+        (4, 0)
+    };
+    assert_eq!(merged, before[kept].unwrap());
+    let lost_id = before[lost].unwrap();
+    assert_eq!(
+        events,
+        vec![
+            CommunityEvent::Dissolved {
+                level,
+                id: lost_id,
+                members: (lost..lost + 4).collect(),
+            },
+            CommunityEvent::MembersAdded {
+                level,
+                id: merged,
+                nodes: (lost..lost + 4).collect(),
+            },
+            CommunityEvent::Merged {
+                level,
+                id: merged,
+                from: {
+                    let mut from = vec![merged, lost_id];
+                    from.sort_unstable();
+                    from
+                },
+            },
+        ]
+    );
+}
+
+#[test]
+fn removed_nodes_leave_their_community() {
+    let graph = ring_of_cliques(6, 5);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let id = session.community_ids().communities()[7].unwrap();
+    let (sender, receiver) = mpsc::channel();
+    session.subscribe(sender);
+
+    session
+        .apply_delta(&DeltaBatch::new().remove_node(7))
+        .expect("delta");
+    let changes = receiver.try_recv().expect("changes published");
+    assert!(
+        top_level(&session, &changes).contains(&CommunityEvent::MembersRemoved {
+            level: session.community_ids().level_count() - 1,
+            id,
+            nodes: vec![7],
+        })
+    );
+}
+
+#[test]
+fn events_replay_the_membership_changes() {
+    let graph = ring_of_cliques(10, 4);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let (sender, receiver) = mpsc::channel();
+    session.subscribe(sender);
+
+    let batches = vec![
+        DeltaBatch::new().adjust_weight(0, 1, 1.0),
+        DeltaBatch::new()
+            .insert(0, 6, 3.0)
+            .insert(1, 6, 3.0)
+            .insert(2, 6, 3.0),
+        DeltaBatch::new().remove_node(13).insert(12, 20, 1.0),
+        DeltaBatch::new().add_nodes(2).insert(40, 41, 5.0),
+        DeltaBatch::new().delete(0, 6).delete(1, 6).delete(2, 6),
+    ];
+    for batch in &batches {
+        let levels_before = session.community_ids().level_count();
+        let mut members: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+        for (node, id) in session
+            .community_ids()
+            .communities()
+            .into_iter()
+            .enumerate()
+        {
+            if let Some(id) = id {
+                members.entry(id).or_default().push(node);
+            }
+        }
+        session.apply_delta(batch).expect("delta");
+        let changes = receiver.try_recv().expect("changes published");
+        assert_eq!(changes.version, session.version());
+        if session.community_ids().level_count() != levels_before {
+            continue;
+        }
+
+        for event in top_level(&session, &changes) {
+            match event {
+                CommunityEvent::Created { id, members: m, .. } => {
+                    assert!(members.insert(id, m).is_none());
+                }
+                CommunityEvent::Dissolved { id, members: m, .. } => {
+                    assert_eq!(members.remove(&id), Some(m));
+                }
+                CommunityEvent::MembersAdded { id, nodes, .. } => {
+                    members.get_mut(&id).unwrap().extend(nodes);
+                }
+                CommunityEvent::MembersRemoved { id, nodes, .. } => {
+                    members.get_mut(&id).unwrap().retain(|v| !nodes.contains(v));
+                }
+                CommunityEvent::Split { .. } | CommunityEvent::Merged { .. } => {}
+            }
+        }
+        let mut expected: BTreeMap<u64, Vec<usize>> = BTreeMap::new();
+        for (node, id) in session
+            .community_ids()
+            .communities()
+            .into_iter()
+            .enumerate()
+        {
+            if let Some(id) = id {
+                expected.entry(id).or_default().push(node);
+            }
+        }
+        for nodes in members.values_mut() {
+            nodes.sort_unstable();
+        }
+        assert_eq!(members, expected);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::integration::ring_of_cliques;
+use hit_leiden::core::partition::lineage::{CommunityLineage, Lifespan, LineageLink, Relation};
+use hit_leiden::{DeltaBatch, HitLeidenError, IncrementalSession, RunConfig};
+use std::collections::BTreeSet;
+
+fn link(version: u64, parent: u64, child: u64, relation: Relation, shared: usize) -> LineageLink {
+    LineageLink {
+        version,
+        parent,
+        child,
+        relation,
+        shared_nodes: shared,
+    }
+}
+
+#[test]
+fn session_records_merges_and_new_communities() {
+    let graph = ring_of_cliques(8, 4);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let before = session.community_ids().communities();
+
+    let mut fuse = DeltaBatch::new();
+    for i in 0..4 {
+        for j in 4..8 {
+            fuse = if (i, j) == (0, 5) {
+                fuse.adjust_weight(i, j, 1.0)
+            } else {
+                fuse.insert(i, j, 1.0)
+            };
+        }
+    }
+    session.apply_delta(&fuse).expect("delta");
+    session
+        .apply_delta(
+            &DeltaBatch::new()
+                .add_nodes(3)
+                .insert(32, 33, 1.0)
+                .insert(33, 34, 1.0),
+        )
+        .expect("delta");
+
+    let lineage = session.lineage();
+    let merged = session.community_ids().communities()[0].unwrap();
+    let lost = if merged == before[0].unwrap() {
+        before[4].unwrap()
+    } else {
+        before[0].unwrap()
+    };
+    assert_eq!(
+        lineage.links_at(1),
+        &[link(1, lost, merged, Relation::Merged, 4)]
+    );
+    assert_eq!(lineage.ancestors(merged), BTreeSet::from([lost]));
+    assert_eq!(lineage.descendants(lost), BTreeSet::from([merged]));
+    assert_eq!(
+        lineage.lifespan(lost),
+        Some(Lifespan {
+            born: 0,
+            ended: Some(1)
+        })
+    );
+    assert!(lineage.alive_at(0).contains(&lost));
+    assert!(!lineage.alive_at(1).contains(&lost));
+
+    // An unrelated new community has a lifespan but no ancestry
+    let fresh = session.community_ids().communities()[32].unwrap();
+    assert!(lineage.links_at(2).is_empty());
+    assert_eq!(lineage.lifespan(fresh).map(|span| span.born), Some(2));
+    assert!(lineage.ancestors(fresh).is_empty());
+    assert_eq!(lineage.alive_at(2).len(), 8);
+}
+
+#[test]
+fn relations_follow_how_nodes_moved() {
+    let mut lineage = CommunityLineage::new(0, &[Some(0), Some(0), Some(0), Some(1), Some(1)]);
+    // Community 0 sheds two nodes into the new community 2
+    lineage.record(
+        1,
+        &[Some(0), Some(0), Some(0), Some(1), Some(1)],
+        &[Some(0), Some(2), Some(2), Some(1), Some(1)],
+    );
+    // Community 1 is renamed and community 2 dissolves into 0 and 3
+    lineage.record(
+        2,
+        &[Some(0), Some(2), Some(2), Some(1), Some(1)],
+        &[Some(0), Some(0), Some(3), Some(4), Some(4)],
+    );
+
+    assert_eq!(lineage.links_at(1), &[link(1, 0, 2, Relation::Split, 2)]);
+    assert_eq!(
+        lineage.links_at(2),
+        &[
+            link(2, 1, 4, Relation::Continued, 2),
+            link(2, 2, 0, Relation::Merged, 1),
+            link(2, 2, 3, Relation::Split, 1),
+        ]
+    );
+    assert_eq!(lineage.ancestors(3), BTreeSet::from([0, 2]));
+    assert_eq!(lineage.descendants(0), BTreeSet::from([2, 3]));
+    assert_eq!(lineage.alive_at(1), vec![0, 1, 2]);
+    assert_eq!(lineage.alive_at(2), vec![0, 3, 4]);
+}
+
+#[test]
+fn lineage_round_trips_through_its_text_format() {
+    let graph = ring_of_cliques(8, 4);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    session
+        .apply_delta(
+            &DeltaBatch::new()
+                .insert(3, 5, 2.0)
+                .insert(1, 6, 2.0)
+                .insert(2, 7, 2.0),
+        )
+        .expect("delta");
+    session
+        .apply_delta(&DeltaBatch::new().remove_node(12).remove_node(13))
+        .expect("delta");
+
+    let mut bytes = Vec::new();
+    session.lineage().write_to(&mut bytes).expect("write");
+    let restored = CommunityLineage::read_from(bytes.as_slice()).expect("read");
+    assert!(!session.lineage().links().is_empty());
+    assert_eq!(&restored, session.lineage());
+}
+
+#[test]
+fn malformed_lineage_is_rejected_with_its_line() {
+    let text = "hit_leiden-lineage v1\nspan 0 0 -\nlink 1 0 2 adopted 3\n";
+    match CommunityLineage::read_from(text.as_bytes()) {
+        Err(HitLeidenError::InvalidInput(message)) => {
+            assert!(message.contains("line 3"), "{message}");
+            assert!(message.contains("adopted"), "{message}");
+        }
+        other => panic!("expected InvalidInput, got {other:?}"),
+    }
+    assert!(CommunityLineage::read_from("span 0 0 -\n".as_bytes()).is_err());
+}
+
+#[test]
+fn saved_sessions_keep_their_identifiers_and_lineage() {
+    let graph = ring_of_cliques(8, 4);
+    let mut original = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let mut fuse = DeltaBatch::new();
+    for i in 0..4 {
+        for j in 4..8 {
+            fuse = if (i, j) == (0, 5) {
+                fuse.adjust_weight(i, j, 1.0)
+            } else {
+                fuse.insert(i, j, 1.0)
+            };
+        }
+    }
+    original.apply_delta(&fuse).expect("delta");
+
+    let path = std::env::temp_dir().join(format!("hit_leiden_session_{}.ckpt", std::process::id()));
+    original.save(&path).expect("save");
+    let mut loaded = IncrementalSession::load(&path, RunConfig::default()).expect("load");
+    std::fs::remove_file(&path).expect("cleanup");
+    assert_eq!(loaded.version(), original.version());
+    assert_eq!(loaded.community_ids(), original.community_ids());
+    assert_eq!(loaded.lineage(), original.lineage());
+
+    // New communities get identifiers never handed out before the save
+    let seen: BTreeSet<u64> = (0..original.community_ids().level_count())
+        .flat_map(|level| original.community_ids().level(level).values().copied())
+        .chain(original.lineage().alive_at(0))
+        .collect();
+    let clique = DeltaBatch::new()
+        .add_nodes(4)
+        .insert(32, 33, 1.0)
+        .insert(33, 34, 1.0)
+        .insert(34, 35, 1.0)
+        .insert(35, 32, 1.0)
+        .insert(32, 34, 1.0);
+    original.apply_delta(&clique).expect("delta");
+    loaded.apply_delta(&clique).expect("delta");
+    assert_eq!(loaded.community_ids(), original.community_ids());
+    assert_eq!(loaded.lineage(), original.lineage());
+    let fresh = loaded.community_ids().communities()[32].unwrap();
+    assert!(!seen.contains(&fresh));
+    assert_eq!(
+        loaded.lineage().lifespan(fresh).map(|span| span.born),
+        Some(2)
+    );
+
+    let state_only = original.state().to_checkpoint_bytes();
+    assert!(matches!(
+        IncrementalSession::from_checkpoint_bytes(&state_only, RunConfig::default()),
+        Err(HitLeidenError::Checkpoint(message)) if message == "not a checkpoint"
+    ));
+}
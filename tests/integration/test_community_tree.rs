@@ -0,0 +1,196 @@
+use hit_leiden::core::partition::tree::CommunityTree;
+use hit_leiden::{DeltaBatch, GraphInput, IncrementalSession, RunConfig};
+use std::collections::BTreeMap;
+
+/// Groups of cliques: cliques in a group share several edges, groups one.
+fn nested_cliques(groups: usize, cliques: usize, size: usize) -> GraphInput {
+    let mut edges = Vec::new();
+    let group_size = cliques * size;
+    for g in 0..groups {
+        for c in 0..cliques {
+            let base = g * group_size + c * size;
+            for i in 0..size {
+                for j in (i + 1)..size {
+                    edges.push((base + i, base + j, None));
+                }
+            }
+            let next = g * group_size + ((c + 1) % cliques) * size;
+            edges.push((base, next + 1, None));
+            edges.push((base + 2, next + 3, None));
+        }
+        edges.push((g * group_size, ((g + 1) % groups) * group_size + 4, None));
+    }
+    GraphInput {
+        dataset_id: "community-tree".to_string(),
+        node_count: groups * group_size,
+        edges,
+    }
+}
+
+#[test]
+fn tree_mirrors_the_hierarchy() {
+    let graph = nested_cliques(4, 4, 5);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    session
+        .apply_delta(&DeltaBatch::new().remove_node(3).set_node_weight(7, 2.5))
+        .expect("delta");
+    let tree = session.community_tree();
+    let state = session.state();
+    let labels = state.hierarchy_labels();
+
+    assert_eq!(tree.level_count(), state.levels);
+    assert!(tree.level_count() > 1);
+    assert_eq!(tree.leaf_of(3), None);
+    assert!(tree.path(3).is_empty());
+    for (level, labels) in labels.iter().enumerate() {
+        for index in tree.level(level) {
+            let community = tree.community(index);
+            assert_eq!(community.level, level);
+            let members = tree.members(index);
+            assert_eq!(community.size, members.len());
+            assert!(members.iter().all(|&v| labels[v] == community.label));
+            let weight: f64 = members
+                .iter()
+                .map(|&v| state.node_weights_per_level[0][v])
+                .sum();
+            assert!((community.weight - weight).abs() < 1e-12);
+            assert_eq!(
+                community.id,
+                Some(session.community_ids().level(level)[&community.label])
+            );
+
+            let child_size: usize = community
+                .children
+                .iter()
+                .map(|&c| tree.community(c).size)
+                .sum();
+            if level > 0 {
+                assert_eq!(child_size, community.size);
+            } else {
+                assert!(community.children.is_empty());
+            }
+            for &child in &community.children {
+                assert_eq!(tree.community(child).parent, Some(index));
+            }
+        }
+    }
+    for index in tree.roots() {
+        assert_eq!(tree.community(index).parent, None);
+    }
+    let root_sizes: usize = tree.roots().map(|i| tree.community(i).size).sum();
+    assert_eq!(root_sizes, graph.node_count - 1);
+
+    // Paths run from the leaf to the root of the node's final community
+    for node in (0..graph.node_count).filter(|&v| v != 3) {
+        let path = tree.path(node);
+        assert_eq!(path.len(), tree.level_count());
+        assert_eq!(Some(path[0]), tree.leaf_of(node));
+        assert_eq!(
+            tree.community(*path.last().unwrap()).label,
+            session.partition()[node]
+        );
+    }
+}
+
+#[test]
+fn traversals_visit_children_and_parents_in_order() {
+    let graph = nested_cliques(3, 3, 4);
+    let session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let tree = CommunityTree::from_state(session.state());
+    assert!(tree
+        .bottom_up()
+        .all(|(_, community)| community.id.is_none()));
+
+    // Bottom-up: every child is folded into its parent before the parent is visited
+    let mut folded: BTreeMap<usize, usize> = BTreeMap::new();
+    for (index, community) in tree.bottom_up() {
+        let size = if community.children.is_empty() {
+            tree.members(index).len()
+        } else {
+            community.children.iter().map(|c| folded[c]).sum()
+        };
+        assert_eq!(size, community.size);
+        folded.insert(index, size);
+    }
+    assert_eq!(folded.len(), tree.len());
+
+    // Top-down: every parent is visited before its children
+    let mut seen = vec![false; tree.len()];
+    for (index, community) in tree.top_down() {
+        if let Some(parent) = community.parent {
+            assert!(seen[parent]);
+        }
+        seen[index] = true;
+    }
+    assert!(seen.into_iter().all(|s| s));
+}
+
+/// Every member of every community of the session's tree sits in the
+/// community's parent.
+fn assert_nested(session: &IncrementalSession) {
+    let tree = session.community_tree();
+    let state = session.state();
+    let labels = state.hierarchy_labels();
+    assert_eq!(tree.level_count(), labels.len());
+    for node in (0..session.node_count()).filter(|&v| !state.removed_nodes[v]) {
+        let path = tree.path(node);
+        assert_eq!(path.len(), labels.len());
+        for (level, &index) in path.iter().enumerate() {
+            assert_eq!(tree.community(index).label, labels[level][node]);
+            if level > 0 {
+                assert_eq!(tree.community(path[level - 1]).parent, Some(index));
+            }
+        }
+    }
+}
+
+#[test]
+fn trees_of_updated_sessions_nest_every_level() {
+    let graph = nested_cliques(4, 4, 5);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let batches = [
+        DeltaBatch::new().insert(0, 45, 1.0).insert(1, 46, 1.0),
+        DeltaBatch::new().delete(0, 1).remove_node(22),
+        DeltaBatch::new()
+            .add_nodes(2)
+            .insert(80, 5, 1.0)
+            .insert(81, 60, 2.0),
+        DeltaBatch::new()
+            .insert(7, 70, 3.0)
+            .set_node_weight(12, 4.0),
+    ];
+    for batch in &batches {
+        session.apply_delta(batch).expect("delta");
+        assert_nested(&session);
+    }
+
+    // Members that change community relabel supervertices two levels up
+    let graph = GraphInput {
+        dataset_id: "community-tree".to_string(),
+        node_count: 12,
+        edges: vec![
+            (8, 10, None),
+            (7, 2, None),
+            (8, 0, None),
+            (6, 1, None),
+            (1, 6, None),
+            (4, 9, None),
+            (4, 1, None),
+            (2, 2, None),
+            (2, 2, None),
+        ],
+    };
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let batches = [
+        DeltaBatch::new()
+            .add_nodes(1)
+            .insert(6, 3, 2.0)
+            .insert(12, 0, 1.0),
+        DeltaBatch::new().add_nodes(1).insert(13, 1, 1.0),
+        DeltaBatch::new().insert(8, 4, 2.0),
+    ];
+    for batch in &batches {
+        session.apply_delta(batch).expect("delta");
+        assert_nested(&session);
+    }
+}
@@ -0,0 +1,130 @@
+use hit_leiden::core::algorithm::quality::QualityKind;
+use hit_leiden::{run, DeltaBatch, GraphInput, IncrementalSession, RunConfig};
+use std::process::Command;
+
+const CHILD_ENV: &str = "HIT_LEIDEN_DETERMINISM_CHILD";
+
+/// A graph full of ties: identical cliques on a ring, plus a sprinkle of
+/// pseudo-random unit chords so that many moves have equal gain.
+fn tied_graph() -> GraphInput {
+    let (cliques, size) = (24, 5);
+    let mut edges = Vec::new();
+    for c in 0..cliques {
+        let base = c * size;
+        for i in 0..size {
+            for j in (i + 1)..size {
+                edges.push((base + i, base + j, None));
+            }
+        }
+        edges.push((base, ((c + 1) % cliques) * size, None));
+    }
+    let n = cliques * size;
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    for _ in 0..40 {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        let u = (state >> 33) as usize % n;
+        let v = (state >> 13) as usize % n;
+        if u != v {
+            edges.push((u, v, None));
+        }
+    }
+    GraphInput {
+        dataset_id: "ties".to_string(),
+        node_count: n,
+        edges,
+    }
+}
+
+/// Everything deterministic mode produces, with floats as raw bits.
+fn fingerprint() -> String {
+    let graph = tied_graph();
+    let mut lines = Vec::new();
+    for quality in [QualityKind::Modularity, QualityKind::Cpm] {
+        let config = RunConfig {
+            quality,
+            resolution: if quality == QualityKind::Cpm {
+                0.5
+            } else {
+                1.0
+            },
+            ..RunConfig::default()
+        };
+        let partition = run(&graph, &config).unwrap().partition.unwrap();
+        lines.push(format!(
+            "{:?} {:x} {:?}",
+            quality,
+            partition.quality_score.to_bits(),
+            partition.node_to_community
+        ));
+
+        let mut session = IncrementalSession::new(&graph, config).unwrap();
+        session
+            .apply_delta(
+                &DeltaBatch::new()
+                    .add_nodes(3)
+                    .insert(120, 7, 1.0)
+                    .insert(121, 120, 1.0)
+                    .insert(122, 64, 1.0)
+                    .adjust_weight(0, 5, 2.0)
+                    .remove_node(33),
+            )
+            .unwrap();
+        let result = session.partition_result();
+        lines.push(format!(
+            "{:?} {:x} {:?}",
+            quality,
+            result.quality_score.to_bits(),
+            result.node_to_community
+        ));
+    }
+
+    let seeded = RunConfig {
+        seed: Some(9),
+        randomness: 0.5,
+        ..RunConfig::default()
+    };
+    let partition = run(&graph, &seeded).unwrap().partition.unwrap();
+    lines.push(format!(
+        "seeded {:x} {:?}",
+        partition.quality_score.to_bits(),
+        partition.node_to_community
+    ));
+    lines.join("\n")
+}
+
+/// Runs only when spawned by `identical_partitions_across_processes`.
+#[test]
+fn emit_fingerprint_for_parent() {
+    if std::env::var_os(CHILD_ENV).is_some() {
+        println!("BEGIN\n{}\nEND", fingerprint());
+    }
+}
+
+#[test]
+fn identical_partitions_across_processes() {
+    let exe = std::env::current_exe().expect("test binary");
+    let runs: Vec<String> = (0..3)
+        .map(|_| {
+            let output = Command::new(&exe)
+                .args([
+                    "--exact",
+                    "test_cross_process_determinism::emit_fingerprint_for_parent",
+                    "--nocapture",
+                    "--test-threads=1",
+                ])
+                .env(CHILD_ENV, "1")
+                .output()
+                .expect("spawn child");
+            assert!(output.status.success());
+            let stdout = String::from_utf8(output.stdout).expect("utf-8");
+            let start = stdout.find("BEGIN\n").expect("fingerprint") + "BEGIN\n".len();
+            let end = stdout.find("\nEND").expect("fingerprint");
+            stdout[start..end].to_string()
+        })
+        .collect();
+
+    assert_eq!(runs[0], fingerprint());
+    assert!(runs.iter().all(|r| *r == runs[0]));
+}
@@ -0,0 +1,253 @@
+use hit_leiden::core::binary::fnv1a64;
+use hit_leiden::core::graph::csr_binary::{
+    convert_edge_list, load_csr, parse_csr, write_csr, FORMAT_VERSION, MAGIC,
+};
+use hit_leiden::core::graph::edge_list::{load_edge_list, EdgeListOptions};
+use hit_leiden::core::graph::in_memory::InMemoryGraph;
+use hit_leiden::core::types::GraphFormat;
+use hit_leiden::{run, GraphInput, HitLeidenError, ParallelEdges, RunConfig};
+use std::fs;
+use std::path::PathBuf;
+
+fn scratch_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("hit_leiden_csr_{}_{name}", std::process::id()))
+}
+
+fn input(weighted: bool) -> GraphInput {
+    let w = |w: f64| weighted.then_some(w);
+    GraphInput {
+        dataset_id: "csr".to_string(),
+        node_count: 7,
+        edges: vec![
+            (0, 1, w(2.0)),
+            (1, 2, None),
+            (2, 0, w(0.5)),
+            (3, 4, None),
+            (4, 5, w(3.0)),
+            (5, 3, None),
+            (2, 3, None),
+            (4, 4, w(1.5)),
+        ],
+    }
+}
+
+fn to_bytes(graph: &InMemoryGraph) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    write_csr(graph, &mut bytes).unwrap();
+    bytes
+}
+
+/// A weighted CSR file holding `adjacencies` as given, directed when there
+/// are two, with a valid header and checksum.
+fn raw_csr(node_count: usize, adjacencies: &[(&[u64], &[u64], &[f64])]) -> Vec<u8> {
+    let directed = adjacencies.len() == 2;
+    let entries = adjacencies[0].1.len() as u64;
+    let mut body = Vec::new();
+    for (offsets, neighbors, weights) in adjacencies {
+        for word in offsets.iter().chain(neighbors.iter()) {
+            body.extend_from_slice(&word.to_le_bytes());
+        }
+        for weight in weights.iter() {
+            body.extend_from_slice(&weight.to_le_bytes());
+        }
+    }
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(if directed { 3u32 } else { 1 }).to_le_bytes());
+    bytes.extend_from_slice(&(node_count as u64).to_le_bytes());
+    let edges = if directed { entries } else { entries / 2 };
+    bytes.extend_from_slice(&edges.to_le_bytes());
+    bytes.extend_from_slice(&entries.to_le_bytes());
+    bytes.extend_from_slice(&fnv1a64(&body).to_le_bytes());
+    bytes.extend_from_slice(&body);
+    bytes
+}
+
+fn parse_error(bytes: &[u8]) -> String {
+    match parse_csr(bytes) {
+        Err(HitLeidenError::InvalidInput(message)) => message,
+        other => panic!("expected an invalid input error, got {other:?}"),
+    }
+}
+
+#[test]
+fn graphs_round_trip_through_the_format() {
+    for weighted in [true, false] {
+        let undirected = InMemoryGraph::from(&input(weighted));
+        let directed = InMemoryGraph::directed(&input(weighted));
+        for graph in [undirected, directed] {
+            let bytes = to_bytes(&graph);
+            assert_eq!(parse_csr(&bytes).unwrap(), graph);
+        }
+    }
+
+    // Unweighted graphs store no weight array
+    let weighted = to_bytes(&InMemoryGraph::from(&input(true)));
+    let unweighted = to_bytes(&InMemoryGraph::from(&input(false)));
+    let entries = 2 * 8;
+    assert_eq!(weighted.len() - unweighted.len(), entries * 8);
+    assert_eq!(unweighted.len(), 48 + 8 * (8 + entries));
+}
+
+#[test]
+fn edited_graphs_are_written_without_slack() {
+    let mut graph = InMemoryGraph::from(&input(true));
+    graph.set_edge(6, 0, 4.0);
+    graph.set_edge(6, 1, 1.0);
+    graph.set_edge(6, 2, 1.0);
+    graph.set_edge(6, 3, 1.0);
+    graph.set_edge(6, 4, 1.0);
+    graph.remove_edge(0, 1);
+    graph.add_nodes(1);
+
+    let loaded = parse_csr(&to_bytes(&graph)).unwrap();
+    assert_eq!(loaded.node_count, 8);
+    assert_eq!(loaded.total_weight(), graph.total_weight());
+    assert_eq!(
+        loaded.to_graph_input("edited"),
+        graph.to_graph_input("edited")
+    );
+    assert_eq!(
+        loaded.offsets.last(),
+        Some(&(0..8).map(|v| graph.degree(v)).sum())
+    );
+}
+
+#[test]
+fn damaged_files_are_rejected() {
+    let bytes = to_bytes(&InMemoryGraph::directed(&input(true)));
+    assert_eq!(parse_error(&bytes[..20]), "not a CSR graph file");
+    assert_eq!(parse_error(b"0 1\n1 2\n"), "not a CSR graph file");
+
+    let mut flipped = bytes.clone();
+    *flipped.last_mut().unwrap() ^= 1;
+    assert!(parse_error(&flipped).starts_with("checksum mismatch"));
+
+    assert!(parse_error(&bytes[..bytes.len() - 8]).starts_with("body holds"));
+    let mut longer = bytes.clone();
+    longer.extend_from_slice(&[0; 8]);
+    assert!(parse_error(&longer).starts_with("body holds"));
+
+    let mut future = bytes.clone();
+    future[8..12].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+    assert!(parse_error(&future).contains("newer than this build reads"));
+
+    let mut flags = bytes.clone();
+    flags[12] |= 4;
+    assert!(parse_error(&flags).starts_with("unknown flags"));
+
+    let mut node_count = bytes;
+    node_count[16..24].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(parse_error(&node_count).starts_with("body holds"));
+}
+
+#[test]
+fn adjacencies_that_disagree_are_rejected() {
+    let edge = GraphInput {
+        dataset_id: "edge".to_string(),
+        node_count: 2,
+        edges: vec![(0, 1, Some(2.0))],
+    };
+    let valid = raw_csr(2, &[(&[0, 1, 2], &[1, 0], &[2.0, 2.0])]);
+    assert_eq!(parse_csr(&valid).unwrap(), InMemoryGraph::from(&edge));
+
+    let cases: [(Vec<u8>, &str); 5] = [
+        (
+            raw_csr(2, &[(&[0, 1, 2], &[1, 0], &[2.0, 3.0])]),
+            "adjacency is not symmetric",
+        ),
+        (
+            raw_csr(3, &[(&[0, 1, 2, 2], &[1, 2], &[1.0, 1.0])]),
+            "adjacency is not symmetric",
+        ),
+        (
+            raw_csr(2, &[(&[0, 2, 4], &[1, 1, 0, 0], &[1.0; 4])]),
+            "node 0 lists neighbour 1 2 times",
+        ),
+        // An undirected self-loop is listed twice under its node
+        (
+            raw_csr(2, &[(&[0, 2, 4], &[0, 1, 0, 1], &[1.0; 4])]),
+            "node 0 lists neighbour 0 1 times",
+        ),
+        (
+            raw_csr(1, &[(&[0, 2], &[0, 0], &[1.0, 2.0])]),
+            "self-loop of node 0 has two weights",
+        ),
+    ];
+    for (bytes, message) in &cases {
+        assert_eq!(parse_error(bytes), *message);
+    }
+
+    let arc = raw_csr(2, &[(&[0, 1, 1], &[1], &[1.0]), (&[0, 0, 1], &[0], &[1.0])]);
+    assert!(parse_csr(&arc).unwrap().is_directed());
+    let unreversed = raw_csr(2, &[(&[0, 1, 1], &[1], &[1.0]), (&[0, 1, 1], &[1], &[1.0])]);
+    assert_eq!(
+        parse_error(&unreversed),
+        "in-adjacency does not list the reversed arcs"
+    );
+}
+
+#[test]
+fn edge_lists_convert_to_csr_files() {
+    let text = scratch_path("graph.txt");
+    let csr = scratch_path("graph.csr");
+    fs::write(
+        &text,
+        "# weighted\n0 1 2\n1 2\n2 0\n3 4\n4 5\n5 3\n2 3\n3 2\n",
+    )
+    .unwrap();
+
+    let options = EdgeListOptions::default();
+    let dataset = convert_edge_list(&text, &options, false, &csr).unwrap();
+    assert_eq!(dataset.format, GraphFormat::CsrBinary);
+    assert!(dataset.is_weighted);
+    assert!(dataset.mmap_compatible);
+    // The repeated bridge is merged into one edge
+    assert_eq!((dataset.node_count, dataset.edge_count), (6, 7));
+
+    let (graph, loaded) = load_csr(&csr).unwrap();
+    assert_eq!(loaded, dataset);
+    let (edges, _) = load_edge_list(&text, &options).unwrap();
+    let merged = edges.merge_parallel_edges(ParallelEdges::Sum, false);
+    assert_eq!(graph, InMemoryGraph::from(&*merged));
+    assert_eq!(graph.edge_weight(2, 3), Some(2.0));
+
+    let from_text = run(&edges, &RunConfig::default())
+        .unwrap()
+        .partition
+        .unwrap();
+    let from_csr = run(&graph.to_graph_input("graph"), &RunConfig::default())
+        .unwrap()
+        .partition
+        .unwrap();
+    assert_eq!(from_csr.node_to_community, from_text.node_to_community);
+    assert_eq!(from_csr.quality_score, from_text.quality_score);
+
+    let directed = convert_edge_list(&text, &options, true, &csr).unwrap();
+    assert_eq!(directed.edge_count, 8);
+    assert!(load_csr(&csr).unwrap().0.is_directed());
+    assert_ne!(directed.checksum, dataset.checksum);
+
+    // The previous file stays in place until the new one is complete
+    let staging = scratch_path("graph.csr.tmp");
+    fs::create_dir(&staging).unwrap();
+    let before = fs::read(&csr).unwrap();
+    assert!(matches!(
+        convert_edge_list(&text, &options, false, &csr),
+        Err(HitLeidenError::Backend(_))
+    ));
+    assert_eq!(fs::read(&csr).unwrap(), before);
+    fs::remove_dir(&staging).unwrap();
+    assert_eq!(
+        convert_edge_list(&text, &options, false, &csr).unwrap(),
+        dataset
+    );
+    assert!(!staging.exists());
+
+    fs::remove_file(&text).unwrap();
+    fs::remove_file(&csr).unwrap();
+    assert!(matches!(
+        load_csr(&csr),
+        Err(HitLeidenError::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,103 @@
+use hit_leiden::{DeltaBatch, GraphInput, HitLeidenError, IncrementalSession, RunConfig};
+
+fn two_triangles() -> IncrementalSession {
+    let graph = GraphInput {
+        dataset_id: "delta-batch".to_string(),
+        node_count: 6,
+        edges: vec![
+            (0, 1, None),
+            (1, 2, None),
+            (2, 0, None),
+            (3, 4, None),
+            (4, 5, None),
+            (5, 3, None),
+        ],
+    };
+    IncrementalSession::new(&graph, RunConfig::default()).expect("session should build")
+}
+
+fn assert_invalid(result: Result<&[usize], HitLeidenError>, needle: &str) {
+    match result {
+        Err(HitLeidenError::InvalidInput(message)) => {
+            assert!(message.contains(needle), "unexpected message: {message}")
+        }
+        other => panic!("expected InvalidInput containing {needle:?}, got {other:?}"),
+    }
+}
+
+#[test]
+fn invalid_operations_are_rejected_without_side_effects() {
+    let mut session = two_triangles();
+    let before = session.state().supergraphs[0].clone();
+
+    // The valid insert is not applied because a later operation fails
+    let batch = DeltaBatch::new().insert(0, 3, 1.0).delete(1, 4);
+    assert_invalid(session.apply_delta(&batch), "delete of a missing edge");
+    assert_invalid(
+        session.apply_delta(&DeltaBatch::new().insert(0, 1, 2.0)),
+        "insert of an existing edge",
+    );
+    assert_invalid(
+        session.apply_delta(&DeltaBatch::new().set_weight(0, 4, 2.0)),
+        "reweight of a missing edge",
+    );
+    assert_invalid(
+        session.apply_delta(&DeltaBatch::new().insert(0, 3, -1.0)),
+        "must be finite and positive",
+    );
+    assert_invalid(
+        session.apply_delta(&DeltaBatch::new().adjust_weight(0, 1, -2.0)),
+        "negative",
+    );
+    assert_invalid(
+        session.apply_delta(&DeltaBatch::new().delete(0, 6)),
+        "out of range",
+    );
+
+    assert_eq!(session.version(), 0);
+    assert_eq!(session.state().supergraphs[0], before);
+}
+
+#[test]
+fn operations_apply_in_order_within_a_batch() {
+    let mut session = two_triangles();
+
+    // Inserting then deleting the same edge nets out to no change
+    let batch = DeltaBatch::new().insert(2, 3, 1.0).delete(3, 2);
+    assert_eq!(batch.len(), 2);
+    session.apply_delta(&batch).expect("batch should apply");
+    assert_eq!(session.version(), 0);
+
+    let batch = DeltaBatch::new()
+        .set_weight(0, 1, 4.0)
+        .adjust_weight(1, 2, 0.5)
+        .adjust_weight(2, 0, -1.0)
+        .insert(2, 3, 1.0)
+        .adjust_weight(2, 3, 1.0);
+    session.apply_delta(&batch).expect("batch should apply");
+    assert_eq!(session.version(), 1);
+
+    let graph = &session.state().supergraphs[0];
+    assert_eq!(graph.edge_weight(1, 0), Some(4.0));
+    assert_eq!(graph.edge_weight(1, 2), Some(1.5));
+    assert_eq!(graph.edge_weight(0, 2), None);
+    assert_eq!(graph.edge_weight(3, 2), Some(2.0));
+    assert!((graph.total_weight() - 10.5).abs() < 1e-9);
+}
+
+#[test]
+fn every_kind_of_change_counts_towards_the_length() {
+    assert!(DeltaBatch::new().is_empty());
+    assert_eq!(DeltaBatch::new().len(), 0);
+
+    let nodes_only = DeltaBatch::new().add_nodes(2).remove_node(0);
+    assert!(!nodes_only.is_empty());
+    assert_eq!(nodes_only.len(), 3);
+
+    let batch = DeltaBatch::new()
+        .add_nodes(1)
+        .insert(0, 6, 1.0)
+        .set_node_weight(6, 2.0)
+        .remove_node(5);
+    assert_eq!(batch.len(), 4);
+}
@@ -0,0 +1,292 @@
+use hit_leiden::core::binary::fnv1a64;
+use hit_leiden::core::graph::delta_log::DeltaLog;
+use hit_leiden::{
+    DeltaBatch, DurableSession, GraphInput, HitLeidenError, IncrementalSession, RunConfig,
+};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+fn two_cliques() -> GraphInput {
+    let mut edges = Vec::new();
+    for base in [0, 6] {
+        for i in 0..6 {
+            for j in (i + 1)..6 {
+                edges.push((base + i, base + j, None));
+            }
+        }
+    }
+    edges.push((0, 6, None));
+    GraphInput {
+        dataset_id: "delta-log".to_string(),
+        node_count: 12,
+        edges,
+    }
+}
+
+fn batches() -> Vec<DeltaBatch> {
+    vec![
+        DeltaBatch::new()
+            .add_nodes(2)
+            .insert(12, 13, 1.0)
+            .insert(12, 1, 1.0),
+        DeltaBatch::new().delete(0, 6).insert(13, 7, 2.0),
+        DeltaBatch::new().remove_node(3).adjust_weight(7, 8, 0.5),
+        DeltaBatch::new().set_node_weight(5, 2.0).insert(2, 9, 1.0),
+    ]
+}
+
+/// A fresh directory per test, so tests can run in parallel.
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "hit_leiden_delta_log_{name}_{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn uninterrupted_session(count: usize) -> IncrementalSession {
+    let mut session = IncrementalSession::new(&two_cliques(), RunConfig::default()).unwrap();
+    for batch in &batches()[..count] {
+        session.apply_delta(batch).unwrap();
+    }
+    session
+}
+
+fn uninterrupted(count: usize) -> Vec<usize> {
+    uninterrupted_session(count).state().node_to_comm.clone()
+}
+
+#[test]
+fn reopening_replays_the_log_after_the_checkpoint() {
+    let dir = scratch_dir("replay");
+    let mut durable = DurableSession::create(&dir, &two_cliques(), RunConfig::default()).unwrap();
+    let batches = batches();
+    durable.apply_delta(&batches[0]).unwrap();
+    durable.checkpoint().unwrap();
+    durable.apply_delta(&batches[1]).unwrap();
+    durable.apply_delta(&batches[2]).unwrap();
+    // Simulate a crash: drop the session without checkpointing
+    drop(durable);
+
+    let mut recovered = DurableSession::open(&dir, RunConfig::default()).unwrap();
+    assert_eq!(recovered.sequence(), 3);
+    assert_eq!(recovered.session().version(), 3);
+    assert_eq!(recovered.session().state().node_to_comm, uninterrupted(3));
+
+    recovered.apply_delta(&batches[3]).unwrap();
+    assert_eq!(recovered.sequence(), 4);
+    let expected = uninterrupted_session(4);
+    assert_eq!(recovered.session().state(), expected.state());
+    // Identifiers and lineage carry on from before the checkpoint
+    assert_eq!(
+        recovered.session().community_ids(),
+        expected.community_ids()
+    );
+    assert_eq!(recovered.session().lineage(), expected.lineage());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn checkpoint_truncates_the_log_and_replaces_older_checkpoints() {
+    let dir = scratch_dir("truncate");
+    let mut durable = DurableSession::create(&dir, &two_cliques(), RunConfig::default()).unwrap();
+    for batch in &batches()[..2] {
+        durable.apply_delta(batch).unwrap();
+    }
+    assert_eq!(durable.log().entries().unwrap().len(), 2);
+
+    durable.checkpoint().unwrap();
+    assert!(durable.log().entries().unwrap().is_empty());
+    let checkpoints: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".ckpt"))
+        .collect();
+    assert_eq!(checkpoints.len(), 1);
+
+    let recovered = DurableSession::open(&dir, RunConfig::default()).unwrap();
+    assert_eq!(recovered.sequence(), 2);
+    assert_eq!(recovered.session().state().node_to_comm, uninterrupted(2));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Name and checksum of every file in `dir`.
+fn files(dir: &Path) -> BTreeMap<String, u64> {
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            (name, fnv1a64(&fs::read(entry.path()).unwrap()))
+        })
+        .collect()
+}
+
+#[test]
+fn checkpoint_drops_nothing_before_the_new_checkpoint_is_in_place() {
+    let dir = scratch_dir("ordering");
+    let mut durable = DurableSession::create(&dir, &two_cliques(), RunConfig::default()).unwrap();
+    let batches = batches();
+    durable.apply_delta(&batches[0]).unwrap();
+    durable.checkpoint().unwrap();
+    durable.apply_delta(&batches[1]).unwrap();
+    durable.apply_delta(&batches[2]).unwrap();
+    let before = files(&dir);
+    let new = format!("checkpoint-{:020}-{:020}.ckpt", 3, 3);
+
+    // A directory in the way of a staging file makes that step fail
+    let blocked = |name: &str| {
+        let path = dir.join(name);
+        fs::create_dir(&path).unwrap();
+        path
+    };
+
+    // Staging the checkpoint fails: the files it would supersede are untouched
+    let staging = blocked(&format!("{new}.tmp"));
+    assert!(durable.checkpoint().is_err());
+    fs::remove_dir(staging).unwrap();
+    assert_eq!(files(&dir), before);
+
+    // Staging the truncated log fails: the new checkpoint has replaced the old one
+    let staging = blocked("deltas.log.tmp");
+    assert!(durable.checkpoint().is_err());
+    fs::remove_dir(staging).unwrap();
+    let after = files(&dir);
+    assert_eq!(
+        after.keys().collect::<Vec<_>>(),
+        [new.as_str(), "config.fingerprint", "deltas.log"]
+    );
+    assert_eq!(after["deltas.log"], before["deltas.log"]);
+    drop(durable);
+
+    let mut recovered = DurableSession::open(&dir, RunConfig::default()).unwrap();
+    assert_eq!(recovered.sequence(), 3);
+    assert_eq!(recovered.session().state().node_to_comm, uninterrupted(3));
+    recovered.checkpoint().unwrap();
+    assert!(recovered.log().entries().unwrap().is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn open_rejects_another_configuration() {
+    let dir = scratch_dir("config");
+    let mut durable = DurableSession::create(&dir, &two_cliques(), RunConfig::default()).unwrap();
+    durable.apply_delta(&batches()[0]).unwrap();
+    drop(durable);
+
+    let other = RunConfig {
+        resolution: 0.5,
+        ..RunConfig::default()
+    };
+    match DurableSession::open(&dir, other) {
+        Err(HitLeidenError::InvalidInput(message)) => {
+            assert!(message.contains("another configuration"))
+        }
+        other => panic!("expected a configuration mismatch, got {other:?}"),
+    }
+
+    // Settings that do not shape the partition may differ
+    let profiled = RunConfig {
+        pinned_profile: Some("profile".to_string()),
+        ..RunConfig::default()
+    };
+    let recovered = DurableSession::open(&dir, profiled).unwrap();
+    assert_eq!(recovered.session().state().node_to_comm, uninterrupted(1));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn rejected_batch_is_not_logged() {
+    let dir = scratch_dir("rejected");
+    let mut durable = DurableSession::create(&dir, &two_cliques(), RunConfig::default()).unwrap();
+    let result = durable.apply_delta(&DeltaBatch::new().insert(0, 99, 1.0));
+    assert!(matches!(result, Err(HitLeidenError::InvalidInput(_))));
+    assert_eq!(durable.sequence(), 0);
+    assert!(durable.log().entries().unwrap().is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn create_refuses_an_existing_session() {
+    let dir = scratch_dir("existing");
+    DurableSession::create(&dir, &two_cliques(), RunConfig::default()).unwrap();
+    let result = DurableSession::create(&dir, &two_cliques(), RunConfig::default());
+    assert!(matches!(result, Err(HitLeidenError::InvalidInput(_))));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn torn_final_record_is_dropped() {
+    let dir = scratch_dir("torn");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("deltas.log");
+    let mut log = DeltaLog::open(&path).unwrap();
+    let batches = batches();
+    log.append(1, &batches[0]).unwrap();
+    log.append(2, &batches[1]).unwrap();
+    drop(log);
+
+    let intact = fs::read(&path).unwrap().len();
+    OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(&[3, 0, 0, 0, 0, 0, 0, 0, 200, 0])
+        .unwrap();
+
+    let mut log = DeltaLog::open(&path).unwrap();
+    assert_eq!(fs::read(&path).unwrap().len(), intact);
+    assert_eq!(log.last_sequence(), Some(2));
+    log.append(3, &batches[2]).unwrap();
+    let sequences: Vec<u64> = log.entries().unwrap().iter().map(|(s, _)| *s).collect();
+    assert_eq!(sequences, vec![1, 2, 3]);
+    assert_eq!(log.entries().unwrap()[1].1, batches[1]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn damaged_record_before_the_tail_is_an_error() {
+    let dir = scratch_dir("corrupt");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("deltas.log");
+    let mut log = DeltaLog::open(&path).unwrap();
+    for (sequence, batch) in (1..).zip(&batches()) {
+        log.append(sequence, batch).unwrap();
+    }
+    drop(log);
+
+    let mut bytes = fs::read(&path).unwrap();
+    // Flip a payload byte of the first record, just past its header
+    bytes[12 + 16 + 4] ^= 0xff;
+    fs::write(&path, &bytes).unwrap();
+    match DeltaLog::open(&path) {
+        Err(HitLeidenError::Checkpoint(message)) => {
+            assert!(message.contains("corrupt delta log"), "{message}")
+        }
+        other => panic!("expected a checkpoint error, got {other:?}"),
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sequence_numbers_must_increase() {
+    let dir = scratch_dir("sequence");
+    fs::create_dir_all(&dir).unwrap();
+    let mut log = DeltaLog::open(dir.join("deltas.log")).unwrap();
+    let batch = DeltaBatch::new().add_nodes(1);
+    log.append(5, &batch).unwrap();
+    assert!(matches!(
+        log.append(5, &batch),
+        Err(HitLeidenError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        log.append(4, &batch),
+        Err(HitLeidenError::InvalidInput(_))
+    ));
+    log.append(9, &batch).unwrap();
+    assert_eq!(log.entries_after(5).unwrap().len(), 1);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,192 @@
+use hit_leiden::core::algorithm::quality::QualityKind;
+use hit_leiden::core::types::PartitionResult;
+use hit_leiden::{run, validate, DeltaBatch, GraphInput, IncrementalSession, RunConfig, RunMode};
+
+/// Several thousand vertices, so that work spans many fixed-size chunks: dense
+/// groups of eight with pseudo-random chords between and inside them.
+fn chunky_graph() -> GraphInput {
+    let n = 2400;
+    let mut edges = Vec::new();
+    for g in 0..n / 8 {
+        let base = g * 8;
+        for i in 0..8 {
+            edges.push((base + i, base + (i + 1) % 8, None));
+            edges.push((base + i, base + (i + 3) % 8, Some(0.5)));
+        }
+    }
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    for _ in 0..1800 {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        let u = (state >> 33) as usize % n;
+        let v = (state >> 11) as usize % n;
+        if u != v {
+            edges.push((u.min(v), u.max(v), None));
+        }
+    }
+    edges.sort_by_key(|&(u, v, _)| (u, v));
+    edges.dedup_by_key(|&mut (u, v, _)| (u, v));
+    GraphInput {
+        dataset_id: "chunky".to_string(),
+        node_count: n,
+        edges,
+    }
+}
+
+fn on_threads<T: Send>(threads: usize, f: impl FnOnce() -> T + Send) -> T {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .expect("pool")
+        .install(f)
+}
+
+fn fingerprint(result: &PartitionResult) -> (Vec<usize>, u64, usize) {
+    (
+        result.node_to_community.clone(),
+        result.quality_score.to_bits(),
+        result.iteration_count,
+    )
+}
+
+fn configs() -> Vec<RunConfig> {
+    let base = RunConfig {
+        mode: RunMode::DeterministicParallel,
+        ..RunConfig::default()
+    };
+    vec![
+        base.clone(),
+        RunConfig {
+            quality: QualityKind::Cpm,
+            resolution: 0.2,
+            ..base.clone()
+        },
+        RunConfig {
+            seed: Some(11),
+            randomness: 0.5,
+            ..base
+        },
+    ]
+}
+
+#[test]
+fn run_is_identical_for_any_thread_count() {
+    let graph = chunky_graph();
+    for config in configs() {
+        let results: Vec<_> = [1, 3, 8]
+            .into_iter()
+            .map(|threads| {
+                on_threads(threads, || {
+                    fingerprint(&run(&graph, &config).unwrap().partition.unwrap())
+                })
+            })
+            .collect();
+        assert!(results.iter().all(|r| *r == results[0]), "{config:?}");
+        assert!(
+            results[0]
+                .0
+                .iter()
+                .collect::<std::collections::HashSet<_>>()
+                .len()
+                > 1
+        );
+    }
+}
+
+#[test]
+fn session_updates_are_identical_for_any_thread_count() {
+    let graph = chunky_graph();
+    let batch = DeltaBatch::new()
+        .add_nodes(2)
+        .insert(2400, 17, 1.0)
+        .insert(2401, 2400, 2.0)
+        .adjust_weight(0, 1, 3.0)
+        .remove_node(1500);
+    for config in configs() {
+        let results: Vec<_> = [1, 4]
+            .into_iter()
+            .map(|threads| {
+                on_threads(threads, || {
+                    let mut session = IncrementalSession::new(&graph, config.clone()).unwrap();
+                    session.apply_delta(&batch).unwrap();
+                    fingerprint(&session.partition_result())
+                })
+            })
+            .collect();
+        assert!(results.iter().all(|r| *r == results[0]), "{config:?}");
+    }
+}
+
+#[test]
+fn quality_matches_sequential_mode_closely() {
+    let graph = chunky_graph();
+    let score = |mode| {
+        run(
+            &graph,
+            &RunConfig {
+                mode,
+                ..RunConfig::default()
+            },
+        )
+        .unwrap()
+        .partition
+        .unwrap()
+        .quality_score
+    };
+    let sequential = score(RunMode::Deterministic);
+    let parallel = score(RunMode::DeterministicParallel);
+    assert!(
+        (sequential - parallel).abs() < 0.05,
+        "{sequential} vs {parallel}"
+    );
+}
+
+#[test]
+fn small_graphs_are_reproducible_but_not_held_to_the_sequential_partition() {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    for g in 0..100 {
+        let mut edges = Vec::new();
+        for _ in 0..60 {
+            state = state
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            edges.push((
+                (state >> 33) as usize % 30,
+                (state >> 11) as usize % 30,
+                None,
+            ));
+        }
+        let graph = GraphInput {
+            dataset_id: format!("small-{g}"),
+            node_count: 30,
+            edges,
+        };
+        let config = RunConfig {
+            mode: RunMode::DeterministicParallel,
+            ..RunConfig::default()
+        };
+        let results: Vec<_> = [1, 4]
+            .into_iter()
+            .map(|threads| on_threads(threads, || run(&graph, &config).unwrap()))
+            .collect();
+        assert_eq!(
+            fingerprint(results[0].partition.as_ref().unwrap()),
+            fingerprint(results[1].partition.as_ref().unwrap()),
+            "graph {g}"
+        );
+
+        // Validation compares quality with the sequential reference, not labels
+        let reference = run(
+            &graph,
+            &RunConfig {
+                mode: RunMode::Deterministic,
+                ..config.clone()
+            },
+        )
+        .unwrap();
+        let outcome = validate(&reference, &results[0], config.mode);
+        assert_eq!(outcome.deterministic_identity_passed, None);
+        assert!(outcome.quality_delta_vs_reference.is_some());
+    }
+}
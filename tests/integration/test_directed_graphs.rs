@@ -0,0 +1,302 @@
+use hit_leiden::core::algorithm::quality::modularity;
+use hit_leiden::core::graph::in_memory::InMemoryGraph;
+use hit_leiden::core::partition::state::PartitionState;
+use hit_leiden::{
+    run, DeltaBatch, GraphInput, HitLeidenError, IncrementalSession, RunConfig, RunMode,
+};
+use std::collections::BTreeMap;
+
+fn directed() -> RunConfig {
+    RunConfig {
+        directed: true,
+        ..RunConfig::default()
+    }
+}
+
+fn graph(node_count: usize, arcs: &[(usize, usize)]) -> GraphInput {
+    GraphInput {
+        dataset_id: "directed".to_string(),
+        node_count,
+        edges: arcs.iter().map(|&(u, v)| (u, v, None)).collect(),
+    }
+}
+
+/// Cliques whose members point at each other in both directions, joined in a
+/// ring by single arcs.
+fn ring_of_bidirected_cliques(cliques: usize, size: usize) -> GraphInput {
+    let mut arcs = Vec::new();
+    for c in 0..cliques {
+        let base = c * size;
+        for i in 0..size {
+            for j in 0..size {
+                if i != j {
+                    arcs.push((base + i, base + j));
+                }
+            }
+        }
+        arcs.push((base, ((c + 1) % cliques) * size + 1));
+    }
+    graph(cliques * size, &arcs)
+}
+
+/// Leicht–Newman modularity straight from its definition,
+/// `Σ_ij [A_ij − k_i^out·k_j^in / m]·δ(c_i, c_j) / m`.
+fn directed_modularity(input: &GraphInput, labels: &[usize]) -> f64 {
+    let n = input.node_count;
+    let mut out = vec![0.0; n];
+    let mut incoming = vec![0.0; n];
+    let mut m = 0.0;
+    let mut internal = 0.0;
+    for &(u, v, w) in &input.edges {
+        let w = w.unwrap_or(1.0);
+        out[u] += w;
+        incoming[v] += w;
+        m += w;
+        if labels[u] == labels[v] {
+            internal += w;
+        }
+    }
+    let mut expected = 0.0;
+    for i in 0..n {
+        for j in 0..n {
+            if labels[i] == labels[j] {
+                expected += out[i] * incoming[j] / m;
+            }
+        }
+    }
+    (internal - expected) / m
+}
+
+#[test]
+fn adjacency_keeps_arc_direction() {
+    let mut graph = InMemoryGraph::directed(&GraphInput {
+        dataset_id: "arcs".to_string(),
+        node_count: 4,
+        edges: vec![(0, 1, Some(2.0)), (1, 0, Some(3.0)), (2, 2, None)],
+    });
+    assert!(graph.is_directed());
+    assert_eq!(graph.total_weight(), 6.0);
+    assert_eq!(graph.edge_weight(0, 1), Some(2.0));
+    assert_eq!(graph.edge_weight(1, 0), Some(3.0));
+    assert_eq!(graph.out_neighbors(0).collect::<Vec<_>>(), vec![(1, 2.0)]);
+    assert_eq!(graph.in_neighbors(0).collect::<Vec<_>>(), vec![(1, 3.0)]);
+    assert_eq!(graph.arc_strengths(2), (1.0, 1.0));
+    assert_eq!(graph.degree(0), 2);
+
+    graph.set_edge(1, 3, 4.0);
+    graph.adjust_edge(0, 1, -2.0);
+    assert_eq!(graph.edge_weight(0, 1), None);
+    assert_eq!(graph.edge_weight(1, 0), Some(3.0));
+    assert_eq!(graph.in_neighbors(3).collect::<Vec<_>>(), vec![(1, 4.0)]);
+    assert_eq!(graph.arc_strengths(1), (7.0, 0.0));
+    assert_eq!(graph.total_weight(), 8.0);
+
+    let exported = graph.to_graph_input("arcs");
+    let mut arcs: Vec<_> = exported.edges.iter().map(|&(u, v, w)| (u, v, w)).collect();
+    arcs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(
+        arcs,
+        vec![(1, 0, Some(3.0)), (1, 3, Some(4.0)), (2, 2, Some(1.0))]
+    );
+    graph.compact();
+    assert_eq!(graph, {
+        let mut rebuilt = InMemoryGraph::directed(&exported);
+        rebuilt.compact();
+        rebuilt
+    });
+}
+
+#[test]
+fn modularity_matches_the_leicht_newman_definition() {
+    let input = graph(
+        6,
+        &[
+            (0, 1),
+            (1, 2),
+            (2, 0),
+            (3, 4),
+            (4, 5),
+            (5, 3),
+            (0, 3),
+            (4, 1),
+            (2, 2),
+        ],
+    );
+    let graph = InMemoryGraph::directed(&input);
+    for labels in [
+        vec![0, 0, 0, 3, 3, 3],
+        vec![0, 1, 2, 3, 4, 5],
+        vec![0, 0, 0, 0, 0, 0],
+        vec![0, 0, 2, 2, 4, 4],
+    ] {
+        let expected = directed_modularity(&input, &labels);
+        assert!((modularity(&graph, &labels, 1.0) - expected).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn arcs_both_ways_cluster_like_undirected_edges() {
+    let mut edges = Vec::new();
+    for c in 0..6 {
+        let base = c * 5;
+        for i in 0..5 {
+            for j in (i + 1)..5 {
+                edges.push((base + i, base + j, None));
+            }
+        }
+        edges.push((base, ((c + 1) % 6) * 5 + 1, Some(2.0)));
+    }
+    let undirected = GraphInput {
+        dataset_id: "undirected".to_string(),
+        node_count: 30,
+        edges,
+    };
+    let both_ways = GraphInput {
+        edges: undirected
+            .edges
+            .iter()
+            .flat_map(|&(u, v, w)| [(u, v, w), (v, u, w)])
+            .collect(),
+        ..undirected.clone()
+    };
+
+    let directed_run = run(&both_ways, &directed()).unwrap().partition.unwrap();
+    let undirected_run = run(&undirected, &RunConfig::default())
+        .unwrap()
+        .partition
+        .unwrap();
+    assert_eq!(directed_run.community_count, 6);
+    assert_eq!(
+        directed_run.node_to_community,
+        undirected_run.node_to_community
+    );
+    assert!((directed_run.quality_score - undirected_run.quality_score).abs() < 1e-12);
+    let expected = directed_modularity(&both_ways, &directed_run.node_to_community);
+    assert!((directed_run.quality_score - expected).abs() < 1e-12);
+}
+
+#[test]
+fn direction_changes_the_partition() {
+    // A sparse graph whose best split depends on which way its arcs point
+    let input = graph(
+        10,
+        &[
+            (7, 3),
+            (7, 6),
+            (7, 2),
+            (7, 5),
+            (6, 2),
+            (4, 3),
+            (4, 7),
+            (6, 3),
+            (3, 8),
+            (0, 9),
+            (9, 6),
+        ],
+    );
+    let directed_run = run(&input, &directed()).unwrap().partition.unwrap();
+    let undirected_run = run(&input, &RunConfig::default())
+        .unwrap()
+        .partition
+        .unwrap();
+    assert_ne!(
+        directed_run.node_to_community,
+        undirected_run.node_to_community
+    );
+    let directed_quality = directed_modularity(&input, &directed_run.node_to_community);
+    assert!((directed_run.quality_score - directed_quality).abs() < 1e-12);
+    assert!(directed_quality > directed_modularity(&input, &undirected_run.node_to_community));
+}
+
+/// Every supergraph must be the aggregate of the level below it.
+fn assert_supergraphs_consistent(state: &PartitionState) {
+    for p in 0..state.levels - 1 {
+        let below = &state.supergraphs[p];
+        let above = &state.supergraphs[p + 1];
+        assert!(above.is_directed());
+        let mapping = &state.current_subcommunity_mapping_per_level[p];
+        let mut expected: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+        for u in 0..below.node_count {
+            for (v, w) in below.out_neighbors(u) {
+                *expected.entry((mapping[u], mapping[v])).or_insert(0.0) += w;
+            }
+        }
+        for (&(a, b), &w) in &expected {
+            let actual = above.edge_weight(a, b).unwrap_or(0.0);
+            assert!((actual - w).abs() < 1e-9, "level {p}: arc ({a}, {b})");
+        }
+        let arcs: usize = (0..above.node_count)
+            .map(|v| above.out_neighbors(v).count())
+            .sum();
+        assert_eq!(arcs, expected.values().filter(|&&w| w > 1e-9).count());
+    }
+}
+
+#[test]
+fn incremental_updates_follow_arc_direction() {
+    for mode in [
+        RunMode::Deterministic,
+        RunMode::Throughput,
+        RunMode::DeterministicParallel,
+    ] {
+        let config = RunConfig { mode, ..directed() };
+        let mut session =
+            IncrementalSession::new(&ring_of_bidirected_cliques(5, 4), config).unwrap();
+        assert!(session.state().levels > 1);
+        assert_supergraphs_consistent(session.state());
+
+        // The reverse of an existing arc is a new arc
+        assert_eq!(session.state().supergraphs[0].edge_weight(5, 0), None);
+        let batches = [
+            DeltaBatch::new().insert(5, 0, 1.0).insert(5, 9, 2.0),
+            DeltaBatch::new()
+                .add_nodes(2)
+                .insert(20, 21, 1.0)
+                .insert(21, 20, 1.0),
+            DeltaBatch::new().insert(20, 2, 1.0).insert(3, 21, 1.0),
+            DeltaBatch::new().delete(4, 9).adjust_weight(5, 0, 0.5),
+            DeltaBatch::new().remove_node(6),
+        ];
+        for batch in &batches {
+            session.apply_delta(batch).unwrap();
+            let graph = session.state().supergraphs[0].to_graph_input("directed");
+            let expected = directed_modularity(&graph, session.partition());
+            let reported = session.partition_result().quality_score;
+            assert!((reported - expected).abs() < 1e-9, "{mode:?}");
+            assert_supergraphs_consistent(session.state());
+        }
+        let graph = &session.state().supergraphs[0];
+        assert_eq!(graph.edge_weight(5, 0), Some(1.5));
+        assert_eq!(graph.edge_weight(4, 9), None);
+        assert_eq!(session.partition()[20], session.partition()[21], "{mode:?}");
+
+        // Deleting a missing arc is rejected even when its reverse exists
+        assert!(matches!(
+            session.apply_delta(&DeltaBatch::new().delete(13, 8)),
+            Err(HitLeidenError::InvalidInput(_))
+        ));
+    }
+}
+
+#[test]
+fn directed_state_survives_a_checkpoint() {
+    let mut session =
+        IncrementalSession::new(&ring_of_bidirected_cliques(4, 4), directed()).unwrap();
+    session
+        .apply_delta(&DeltaBatch::new().insert(5, 0, 1.0))
+        .unwrap();
+    let bytes = session.state().to_checkpoint_bytes();
+    let loaded = PartitionState::from_checkpoint_bytes(&bytes).unwrap();
+    assert_eq!(&loaded, session.state());
+    assert!(loaded.supergraphs.iter().all(InMemoryGraph::is_directed));
+
+    assert!(matches!(
+        IncrementalSession::restore(loaded.clone(), RunConfig::default()),
+        Err(HitLeidenError::InvalidInput(_))
+    ));
+    let mut restored = IncrementalSession::restore(loaded, directed()).unwrap();
+    let batch = DeltaBatch::new().insert(2, 9, 1.0);
+    restored.apply_delta(&batch).unwrap();
+    session.apply_delta(&batch).unwrap();
+    assert_eq!(restored.partition(), session.partition());
+}
@@ -0,0 +1,136 @@
+use hit_leiden::core::algorithm::quality::QualityKind;
+use hit_leiden::{DeltaBatch, GraphInput, HitLeidenError, IncrementalSession, RunConfig};
+
+fn ring_of_triangles(triangles: usize) -> GraphInput {
+    let mut edges = Vec::new();
+    for t in 0..triangles {
+        let base = 3 * t;
+        edges.extend([(base, base + 1, None), (base + 1, base + 2, None)]);
+        edges.push((base + 2, base, None));
+        edges.push((base, (3 * (t + 1)) % (3 * triangles) + 1, None));
+    }
+    GraphInput {
+        dataset_id: "ring-of-triangles".to_string(),
+        node_count: 3 * triangles,
+        edges,
+    }
+}
+
+fn assert_levels_cover_all_nodes(session: &IncrementalSession) {
+    let state = session.state();
+    assert_eq!(state.supergraphs[0].node_count, session.node_count());
+    for p in 0..state.levels - 1 {
+        let f_next = &state.community_mapping_per_level[p + 1];
+        let s_p = &state.current_subcommunity_mapping_per_level[p];
+        assert_eq!(f_next.len(), state.supergraphs[p + 1].node_count);
+        for (v, &community) in state.community_mapping_per_level[p].iter().enumerate() {
+            assert_eq!(community, f_next[s_p[v]], "level {p} vertex {v}");
+        }
+    }
+}
+
+#[test]
+fn added_nodes_grow_every_level() {
+    let mut session = IncrementalSession::new(&ring_of_triangles(8), RunConfig::default())
+        .expect("session should build");
+    let n = session.node_count();
+
+    // A new triangle attached to node 0 plus one isolated node
+    let batch = DeltaBatch::new()
+        .add_nodes(4)
+        .insert(n, n + 1, 1.0)
+        .insert(n + 1, n + 2, 1.0)
+        .insert(n + 2, n, 1.0)
+        .insert(n, 0, 1.0);
+    let partition = session
+        .apply_delta(&batch)
+        .expect("batch should apply")
+        .to_vec();
+
+    assert_eq!(partition.len(), n + 4);
+    assert_eq!(partition[n], partition[n + 1]);
+    assert_eq!(partition[n + 1], partition[n + 2]);
+    assert_eq!(partition[n + 3], n + 3, "isolated node stays a singleton");
+    assert_levels_cover_all_nodes(&session);
+}
+
+#[test]
+fn removed_nodes_are_detached_and_retired() {
+    let mut session = IncrementalSession::new(&ring_of_triangles(6), RunConfig::default())
+        .expect("session should build");
+    let weight_before = session.state().supergraphs[0].total_weight();
+
+    session
+        .apply_delta(&DeltaBatch::new().remove_node(4))
+        .expect("removal should apply");
+
+    assert!(session.is_removed(4));
+    assert_eq!(session.state().supergraphs[0].degree(4), 0);
+    // Node 4 had three triangle/ring edges: (3, 4), (4, 5) and (0, 4)
+    let weight_after = session.state().supergraphs[0].total_weight();
+    assert!((weight_before - weight_after - 3.0).abs() < 1e-9);
+    assert_levels_cover_all_nodes(&session);
+
+    for batch in [
+        DeltaBatch::new().insert(4, 7, 1.0),
+        DeltaBatch::new().remove_node(4),
+    ] {
+        assert!(matches!(
+            session.apply_delta(&batch),
+            Err(HitLeidenError::InvalidInput(_))
+        ));
+    }
+}
+
+#[test]
+fn removed_nodes_leave_their_community_before_movement() {
+    // Under CPM the weight of node 4 would drive 3 and 5 out to the new node
+    let graph = ring_of_triangles(6);
+    let mut weights = vec![1.0; graph.node_count];
+    weights[4] = 2.0;
+    let config = RunConfig {
+        quality: QualityKind::Cpm,
+        resolution: 0.2,
+        ..RunConfig::default()
+    };
+    let session = || {
+        IncrementalSession::with_node_weights(&graph, &weights, config.clone())
+            .expect("session should build")
+    };
+    let n = graph.node_count;
+    let joining = DeltaBatch::new()
+        .add_nodes(1)
+        .insert(n, 3, 1.0)
+        .insert(n, 5, 1.0);
+
+    // Movement already treats the removed node as detached and weightless
+    let mut removed = session();
+    removed
+        .apply_delta(&joining.clone().remove_node(4))
+        .expect("removal should apply");
+    let mut emptied = session();
+    emptied
+        .apply_delta(
+            &joining
+                .set_node_weight(4, 0.0)
+                .delete(3, 4)
+                .delete(4, 5)
+                .delete(0, 4),
+        )
+        .expect("detachment should apply");
+    for v in (0..=n).filter(|&v| v != 4) {
+        assert_eq!(removed.partition()[v], emptied.partition()[v], "node {v}");
+    }
+
+    // No supervertex, and so no community, still weighs the removed node
+    let state = removed.state();
+    assert_eq!(state.node_weights()[4], 0.0);
+    let labels = state.hierarchy_labels();
+    for p in 0..state.levels - 1 {
+        let mut live = vec![0.0; state.supergraphs[p + 1].node_count];
+        for v in (0..=n).filter(|&v| !removed.is_removed(v)) {
+            live[labels[p][v]] += state.node_weights()[v];
+        }
+        assert_eq!(state.node_weights_per_level[p + 1], live, "level {}", p + 1);
+    }
+}
@@ -0,0 +1,172 @@
+use hit_leiden::core::graph::edge_list::{
+    load_edge_list, parse_edge_list, EdgeListOptions, IdBase, Separator,
+};
+use hit_leiden::core::types::{GraphFormat, GraphSourceType};
+use hit_leiden::{run, HitLeidenError, RunConfig};
+use std::fs;
+use std::path::PathBuf;
+
+fn scratch_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "hit_leiden_edge_list_{}_{name}",
+        std::process::id()
+    ));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn parse(
+    text: &str,
+    options: &EdgeListOptions,
+) -> Result<Vec<(usize, usize, Option<f64>)>, String> {
+    parse_edge_list(text, "test", options)
+        .map(|graph| graph.edges)
+        .map_err(|e| match e {
+            HitLeidenError::InvalidInput(message) => message,
+            other => panic!("unexpected error {other:?}"),
+        })
+}
+
+#[test]
+fn separators_comments_and_optional_weights() {
+    let text = "\
+# a SNAP-style header comment
+0 1
+1\t2 0.5
+% Matrix Market comment
+
+2,3, 2.5
+3   0   # trailing comment
+";
+    let graph = parse_edge_list(text, "mixed", &EdgeListOptions::default()).unwrap();
+    assert_eq!(graph.dataset_id, "mixed");
+    assert_eq!(graph.node_count, 4);
+    assert_eq!(
+        graph.edges,
+        vec![
+            (0, 1, None),
+            (1, 2, Some(0.5)),
+            (2, 3, Some(2.5)),
+            (3, 0, None),
+        ]
+    );
+
+    let tsv = EdgeListOptions {
+        separator: Separator::Tab,
+        ..EdgeListOptions::default()
+    };
+    assert_eq!(parse("4\t5\t3\n", &tsv).unwrap(), vec![(4, 5, Some(3.0))]);
+    assert!(parse("4 5\n", &tsv).unwrap_err().starts_with("line 1:"));
+
+    let csv = EdgeListOptions {
+        separator: Separator::Comma,
+        header: true,
+        ..EdgeListOptions::default()
+    };
+    let text = "# exported\nsource,target,weight\n0,1,2\n1,2,1\n";
+    assert_eq!(
+        parse(text, &csv).unwrap(),
+        vec![(0, 1, Some(2.0)), (1, 2, Some(1.0))]
+    );
+}
+
+#[test]
+fn one_based_ids_and_declared_node_count() {
+    let options = EdgeListOptions {
+        id_base: IdBase::One,
+        node_count: Some(5),
+        ..EdgeListOptions::default()
+    };
+    let graph = parse_edge_list("1 2\n2 3\n", "one-based", &options).unwrap();
+    assert_eq!(graph.node_count, 5);
+    assert_eq!(graph.edges, vec![(0, 1, None), (1, 2, None)]);
+
+    assert_eq!(
+        parse("1 2\n0 3\n", &options).unwrap_err(),
+        "line 2: node id 0 in a 1-based edge list"
+    );
+    assert_eq!(
+        parse("1 2\n\n6 1\n", &options).unwrap_err(),
+        "line 3: node 5 is out of range for 5 nodes"
+    );
+    let empty = parse_edge_list("# nothing\n", "empty", &EdgeListOptions::default()).unwrap();
+    assert_eq!(empty.node_count, 0);
+    assert!(empty.edges.is_empty());
+}
+
+#[test]
+fn malformed_lines_are_reported_by_number() {
+    let options = EdgeListOptions::default();
+    for (text, expected) in [
+        (
+            "0 1\n2\n",
+            "line 2: expected `src dst [weight]`, found 1 fields",
+        ),
+        (
+            "0 1 1 7\n",
+            "line 1: expected `src dst [weight]`, found 4 fields",
+        ),
+        ("0 1\n# c\na 1\n", "line 3: `a` is not a node id"),
+        ("0 -1\n", "line 1: `-1` is not a node id"),
+        ("0 1 heavy\n", "line 1: `heavy` is not a weight"),
+        ("0 1 0\n", "line 1: weight `0` must be finite and > 0"),
+        ("0 1 inf\n", "line 1: weight `inf` must be finite and > 0"),
+        ("0 1 -2\n", "line 1: weight `-2` must be finite and > 0"),
+        (
+            &format!("0 1\n1 {}\n", usize::MAX),
+            &format!("line 2: node {} is too large to count", usize::MAX),
+        ),
+    ] {
+        assert_eq!(parse(text, &options).unwrap_err(), expected);
+    }
+}
+
+#[test]
+fn loading_a_file_describes_the_dataset() {
+    let contents = "0 1 2.0\n1 2\n2 0\n";
+    let path = scratch_file("triangle.txt", contents);
+    let (graph, dataset) = load_edge_list(&path, &EdgeListOptions::default()).unwrap();
+    assert_eq!(graph.edges.len(), 3);
+    assert_eq!(dataset.dataset_id, graph.dataset_id);
+    assert!(dataset.dataset_id.ends_with("triangle"));
+    assert_eq!(dataset.source_uri, path.display().to_string());
+    assert!(dataset.is_weighted);
+    assert_eq!((dataset.node_count, dataset.edge_count), (3, 3));
+    assert_eq!(dataset.format, GraphFormat::EdgeList);
+    assert_eq!(dataset.source_type, GraphSourceType::File);
+    assert!(dataset.checksum.starts_with("fnv1a64:"));
+
+    // The checksum follows the bytes, not just the parsed edges
+    let same = scratch_file("same.txt", contents);
+    let commented = scratch_file("commented.txt", &format!("# note\n{contents}"));
+    let unweighted = scratch_file("unweighted.txt", "0 1\n1 2\n2 0\n");
+    let load = |path| load_edge_list(path, &EdgeListOptions::default()).unwrap().1;
+    assert_eq!(load(&same).checksum, dataset.checksum);
+    assert_ne!(load(&commented).checksum, dataset.checksum);
+    assert!(!load(&unweighted).is_weighted);
+
+    assert!(run(&graph, &RunConfig::default()).is_ok());
+    for path in [path, same, commented, unweighted] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn loading_errors_name_the_file() {
+    let path = scratch_file("broken.txt", "0 1\n1 x\n");
+    let Err(HitLeidenError::InvalidInput(message)) =
+        load_edge_list(&path, &EdgeListOptions::default())
+    else {
+        panic!("expected an invalid input error");
+    };
+    assert_eq!(
+        message,
+        format!("{}: line 2: `x` is not a node id", path.display())
+    );
+    fs::remove_file(&path).unwrap();
+
+    assert!(matches!(
+        load_edge_list(&path, &EdgeListOptions::default()),
+        Err(HitLeidenError::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,70 @@
+use crate::integration::ring_of_cliques;
+use hit_leiden::core::partition::state::PartitionState;
+use hit_leiden::{DeltaBatch, IncrementalSession, RunConfig, RunMode};
+
+fn assert_hierarchy_consistent(state: &PartitionState) {
+    assert_eq!(state.supergraphs.len(), state.levels);
+    assert_eq!(state.node_to_comm, state.community_mapping_per_level[0]);
+    for p in 0..state.levels - 1 {
+        let f_p = &state.community_mapping_per_level[p];
+        let f_next = &state.community_mapping_per_level[p + 1];
+        let s_p = &state.current_subcommunity_mapping_per_level[p];
+        assert_eq!(f_p.len(), state.supergraphs[p].node_count);
+        for v in 0..f_p.len() {
+            assert!(
+                s_p[v] < f_next.len(),
+                "level {p} vertex {v} has no supervertex"
+            );
+            assert_eq!(
+                f_p[v], f_next[s_p[v]],
+                "f_p(v) != f_(p+1)(s_p(v)) at level {p}"
+            );
+        }
+        // Aggregation preserves the total edge weight
+        let lower = state.supergraphs[p].total_weight();
+        let upper = state.supergraphs[p + 1].total_weight();
+        assert!(
+            (lower - upper).abs() < 1e-9,
+            "level {p}: {lower} vs {upper}"
+        );
+    }
+}
+
+fn check_mode(mode: RunMode) {
+    let graph = ring_of_cliques(12, 5);
+    let config = RunConfig {
+        mode,
+        ..RunConfig::default()
+    };
+    let mut session = IncrementalSession::new(&graph, config).expect("session should build");
+    assert!(session.state().levels > 1, "expected aggregated levels");
+    assert_hierarchy_consistent(session.state());
+
+    // Tie cliques 0 and 6 together and cut the ring between cliques 3 and 4
+    let mut delta = DeltaBatch::new().delete(15, 21);
+    for i in 0..5 {
+        for j in 30..35 {
+            delta = delta.insert(i, j, 1.0);
+        }
+    }
+    let levels = session.state().levels;
+    let partition = session
+        .apply_delta(&delta)
+        .expect("delta should apply")
+        .to_vec();
+    assert_eq!(session.state().levels, levels);
+    assert_hierarchy_consistent(session.state());
+    assert_eq!(partition[0], partition[30]);
+    // 12 cliques of 10 edges, 12 ring edges, 25 new edges, 1 removed
+    assert!((session.state().supergraphs[0].total_weight() - 156.0).abs() < 1e-9);
+}
+
+#[test]
+fn deterministic_hierarchy_stays_consistent_across_deltas() {
+    check_mode(RunMode::Deterministic);
+}
+
+#[test]
+fn throughput_hierarchy_stays_consistent_across_deltas() {
+    check_mode(RunMode::Throughput);
+}
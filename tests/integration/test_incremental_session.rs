@@ -0,0 +1,31 @@
+use hit_leiden::{DeltaBatch, GraphInput, IncrementalSession, RunConfig};
+
+#[test]
+fn session_keeps_state_across_deltas() {
+    let initial = GraphInput {
+        dataset_id: "session-1".to_string(),
+        node_count: 6,
+        edges: vec![(0, 1, None), (1, 2, None), (2, 0, None)],
+    };
+    let mut session =
+        IncrementalSession::new(&initial, RunConfig::default()).expect("session should build");
+    let before = session.partition().to_vec();
+    assert_eq!(before[0], before[1]);
+    assert_eq!(before[1], before[2]);
+
+    let delta = DeltaBatch::new()
+        .insert(3, 4, 1.0)
+        .insert(4, 5, 1.0)
+        .insert(5, 3, 1.0);
+    let after = session
+        .apply_delta(&delta)
+        .expect("delta should apply")
+        .to_vec();
+
+    // The untouched triangle keeps its labels; the new one forms its own community.
+    assert_eq!(&after[..3], &before[..3]);
+    assert_eq!(after[3], after[4]);
+    assert_eq!(after[4], after[5]);
+    assert_ne!(after[0], after[3]);
+    assert_eq!(session.version(), 1);
+}
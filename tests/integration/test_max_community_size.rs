@@ -0,0 +1,137 @@
+use crate::integration::ring_of_cliques;
+use hit_leiden::core::config::CommunitySizeLimit;
+use hit_leiden::core::partition::tree::CommunityTree;
+use hit_leiden::{run, DeltaBatch, IncrementalSession, RunConfig, RunMode};
+use std::collections::HashMap;
+
+fn largest_leaf(tree: &CommunityTree) -> (usize, f64) {
+    tree.level(0)
+        .map(|i| tree.community(i))
+        .fold((0, 0.0), |(size, weight), c| {
+            (size.max(c.size), weight.max(c.weight))
+        })
+}
+
+/// Largest community of `partition`, by node count and by total weight.
+fn largest_community(partition: &[usize], weights: &[f64]) -> (usize, f64) {
+    let mut totals: HashMap<usize, (usize, f64)> = HashMap::new();
+    for (node, &community) in partition.iter().enumerate() {
+        let total = totals.entry(community).or_default();
+        total.0 += 1;
+        total.1 += weights[node];
+    }
+    totals
+        .into_values()
+        .fold((0, 0.0), |(size, weight), (s, w)| {
+            (size.max(s), weight.max(w))
+        })
+}
+
+fn largest_reported(session: &IncrementalSession) -> (usize, f64) {
+    largest_community(session.partition(), session.state().node_weights())
+}
+
+#[test]
+fn leaves_respect_a_node_budget_in_every_mode() {
+    let graph = ring_of_cliques(10, 8);
+    let unbounded = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    assert_eq!(largest_leaf(&unbounded.community_tree()).0, 8);
+
+    for mode in [
+        RunMode::Deterministic,
+        RunMode::Throughput,
+        RunMode::DeterministicParallel,
+    ] {
+        let config = RunConfig {
+            mode,
+            max_community_size: Some(CommunitySizeLimit::Nodes(3)),
+            ..RunConfig::default()
+        };
+        let mut session = IncrementalSession::new(&graph, config).expect("session");
+        assert!(largest_leaf(&session.community_tree()).0 <= 3, "{mode:?}");
+        assert!(largest_reported(&session).0 <= 3, "{mode:?}");
+
+        // New nodes attached to a clique must not overflow its leaves
+        let mut batch = DeltaBatch::new().add_nodes(4);
+        for v in 80..84 {
+            for u in 0..8 {
+                batch = batch.insert(u, v, 1.0);
+            }
+        }
+        session.apply_delta(&batch).expect("delta");
+        assert!(largest_leaf(&session.community_tree()).0 <= 3, "{mode:?}");
+        assert!(largest_reported(&session).0 <= 3, "{mode:?}");
+    }
+}
+
+#[test]
+fn leaves_respect_a_weight_budget_across_reweights() {
+    let graph = ring_of_cliques(6, 6);
+    let config = RunConfig {
+        max_community_size: Some(CommunitySizeLimit::Weight(4.0)),
+        ..RunConfig::default()
+    };
+    let mut session =
+        IncrementalSession::with_node_weights(&graph, &vec![1.0; graph.node_count], config)
+            .expect("session");
+    let (size, weight) = largest_leaf(&session.community_tree());
+    assert!(weight <= 4.0);
+    assert!(size > 1);
+    assert!(largest_reported(&session).1 <= 4.0);
+
+    // Heavier nodes force leaves that already fit to split
+    let mut batch = DeltaBatch::new();
+    for v in 0..12 {
+        batch = batch.set_node_weight(v, 1.5);
+    }
+    session.apply_delta(&batch).expect("delta");
+    let tree = session.community_tree();
+    assert!(largest_leaf(&tree).1 <= 4.0);
+    assert!(largest_reported(&session).1 <= 4.0);
+    for v in 0..12 {
+        let leaf = tree.community(tree.leaf_of(v).unwrap());
+        assert!(leaf.size <= 2, "leaf of {v} holds {} nodes", leaf.size);
+    }
+}
+
+#[test]
+fn runs_report_communities_within_the_budget() {
+    let graph = ring_of_cliques(10, 8);
+    let weights = vec![1.0; graph.node_count];
+    for mode in [
+        RunMode::Deterministic,
+        RunMode::Throughput,
+        RunMode::DeterministicParallel,
+    ] {
+        let config = RunConfig {
+            mode,
+            max_community_size: Some(CommunitySizeLimit::Nodes(3)),
+            ..RunConfig::default()
+        };
+        let partition = run(&graph, &config).unwrap().partition.unwrap();
+        let (size, _) = largest_community(&partition.node_to_community, &weights);
+        assert!(size <= 3, "{mode:?}: a community holds {size} nodes");
+        assert!(size > 1, "{mode:?}");
+
+        // Every level of the hierarchy is as small as the communities it nests in
+        let tree = IncrementalSession::new(&graph, config)
+            .expect("session")
+            .community_tree();
+        assert!(tree.top_down().all(|(_, c)| c.size <= 3), "{mode:?}");
+    }
+}
+
+#[test]
+fn size_budget_must_be_positive() {
+    for limit in [
+        CommunitySizeLimit::Nodes(0),
+        CommunitySizeLimit::Weight(0.0),
+        CommunitySizeLimit::Weight(f64::NAN),
+    ] {
+        let config = RunConfig {
+            max_community_size: Some(limit),
+            ..RunConfig::default()
+        };
+        assert!(config.validate().is_err(), "{limit:?}");
+    }
+}
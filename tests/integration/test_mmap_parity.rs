@@ -8,8 +8,10 @@ fn mmap_backend_parity() {
         edges: vec![(0, 1, None)],
     };
     let mem = run(&graph, &RunConfig::default()).expect("mem");
-    let mut mmap_cfg = RunConfig::default();
-    mmap_cfg.graph_backend = GraphBackend::Mmap;
+    let mmap_cfg = RunConfig {
+        graph_backend: GraphBackend::Mmap,
+        ..RunConfig::default()
+    };
     let mmap = run(&graph, &mmap_cfg).expect("mmap");
     assert_eq!(mem.partition, mmap.partition);
 }
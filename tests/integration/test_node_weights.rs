@@ -0,0 +1,199 @@
+use hit_leiden::core::algorithm::quality::{CommunityStats, ConstantPotts, QualityKind};
+use hit_leiden::core::graph::in_memory::InMemoryGraph;
+use hit_leiden::core::partition::state::PartitionState;
+use hit_leiden::{
+    run, run_with_node_weights, DeltaBatch, GraphInput, HitLeidenError, IncrementalSession,
+    RunConfig,
+};
+
+/// Cliques of four with internal edges of weight 3 joined in a light ring.
+fn cliques(count: usize) -> GraphInput {
+    let mut edges = Vec::new();
+    for c in 0..count {
+        let base = c * 4;
+        for i in 0..4 {
+            for j in (i + 1)..4 {
+                edges.push((base + i, base + j, Some(3.0)));
+            }
+        }
+        edges.push((base, ((c + 1) % count) * 4 + 1, Some(1.0)));
+    }
+    GraphInput {
+        dataset_id: "node-weights".to_string(),
+        node_count: count * 4,
+        edges,
+    }
+}
+
+fn cpm() -> RunConfig {
+    RunConfig {
+        quality: QualityKind::Cpm,
+        ..RunConfig::default()
+    }
+}
+
+fn cpm_from_scratch(graph: &GraphInput, node_weights: &[f64], node_to_comm: &[usize]) -> f64 {
+    let n = graph.node_count;
+    let graph = InMemoryGraph::from(graph);
+    CommunityStats::from_partition(&graph, node_to_comm, node_weights, &bitvec::bitvec![0; n])
+        .quality(&ConstantPotts, graph.total_weight(), 1.0)
+}
+
+/// Every supervertex weighs as much as the vertices below it.
+fn assert_weights_aggregate(state: &PartitionState) {
+    let total: f64 = state.node_weights_per_level[0].iter().sum();
+    for p in 0..state.levels {
+        let level_total: f64 = state.node_weights_per_level[p].iter().sum();
+        assert!((level_total - total).abs() < 1e-9, "level {p}");
+        if p + 1 < state.levels {
+            let mut sums = vec![0.0; state.node_weights_per_level[p + 1].len()];
+            for (v, &w) in state.node_weights_per_level[p].iter().enumerate() {
+                sums[state.current_subcommunity_mapping_per_level[p][v]] += w;
+            }
+            for (expected, actual) in sums.iter().zip(&state.node_weights_per_level[p + 1]) {
+                assert!((expected - actual).abs() < 1e-9, "level {}", p + 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn heavy_node_is_split_off_under_cpm() {
+    let graph = cliques(4);
+    let mut weights = vec![1.0; graph.node_count];
+    weights[0] = 10.0;
+
+    let partition = run_with_node_weights(&graph, &weights, &cpm())
+        .expect("run")
+        .partition
+        .expect("partition");
+    let labels = &partition.node_to_community;
+    assert_ne!(labels[0], labels[1]);
+    assert_eq!(labels[1], labels[2]);
+    assert_eq!(labels[5], labels[4]);
+    let expected = cpm_from_scratch(&graph, &weights, labels);
+    assert!((partition.quality_score - expected).abs() < 1e-9);
+}
+
+#[test]
+fn modularity_ignores_node_weights() {
+    let graph = cliques(4);
+    let weights: Vec<f64> = (0..graph.node_count).map(|i| i as f64).collect();
+    let weighted = run_with_node_weights(&graph, &weights, &RunConfig::default())
+        .expect("run")
+        .partition
+        .expect("partition");
+    let unweighted = run(&graph, &RunConfig::default())
+        .expect("run")
+        .partition
+        .expect("partition");
+    // Labels may differ, the grouping may not
+    for u in 0..graph.node_count {
+        for v in 0..graph.node_count {
+            assert_eq!(
+                weighted.node_to_community[u] == weighted.node_to_community[v],
+                unweighted.node_to_community[u] == unweighted.node_to_community[v]
+            );
+        }
+    }
+    assert_eq!(weighted.quality_score, unweighted.quality_score);
+}
+
+#[test]
+fn invalid_node_weights_are_rejected() {
+    let graph = cliques(2);
+    let n = graph.node_count;
+    let mut negative = vec![1.0; n];
+    negative[3] = -1.0;
+    let mut nan = vec![1.0; n];
+    nan[0] = f64::NAN;
+    for weights in [vec![1.0; n - 1], negative, nan] {
+        assert!(matches!(
+            run_with_node_weights(&graph, &weights, &cpm()),
+            Err(HitLeidenError::InvalidInput(_))
+        ));
+        assert!(IncrementalSession::with_node_weights(&graph, &weights, cpm()).is_err());
+    }
+
+    let mut session = IncrementalSession::new(&graph, cpm()).expect("session");
+    for batch in [
+        DeltaBatch::new().set_node_weight(n, 1.0),
+        DeltaBatch::new().set_node_weight(0, -2.0),
+        DeltaBatch::new().remove_node(1).set_node_weight(1, 2.0),
+    ] {
+        assert!(matches!(
+            session.apply_delta(&batch),
+            Err(HitLeidenError::InvalidInput(_))
+        ));
+    }
+    assert_eq!(session.version(), 0);
+}
+
+#[test]
+fn weights_are_summed_into_supergraphs() {
+    let graph = cliques(8);
+    let weights: Vec<f64> = (0..graph.node_count)
+        .map(|i| 1.0 + (i % 3) as f64)
+        .collect();
+    let mut session =
+        IncrementalSession::with_node_weights(&graph, &weights, cpm()).expect("session");
+    assert!(session.state().levels > 1);
+    assert_weights_aggregate(session.state());
+
+    session
+        .apply_delta(
+            &DeltaBatch::new()
+                .add_nodes(2)
+                .insert(32, 33, 3.0)
+                .set_node_weight(33, 4.0)
+                .set_node_weight(7, 0.5),
+        )
+        .expect("delta");
+    assert_eq!(session.state().node_weights_per_level[0][32], 1.0);
+    assert_eq!(session.state().node_weights_per_level[0][33], 4.0);
+    assert_weights_aggregate(session.state());
+}
+
+/// `cliques(4)` plus node 16 hanging off node 0 by an edge of weight 2.9,
+/// too light for either to want the other's community at unit weights.
+fn cliques_with_pendant() -> GraphInput {
+    let mut graph = cliques(4);
+    graph.node_count += 1;
+    graph.edges.push((0, 16, Some(2.9)));
+    graph
+}
+
+#[test]
+fn reweighting_a_node_reoptimises_its_community() {
+    let graph = cliques_with_pendant();
+    let mut session = IncrementalSession::new(&graph, cpm()).expect("session");
+    assert_eq!(session.partition()[0], session.partition()[1]);
+    assert_ne!(session.partition()[0], session.partition()[16]);
+
+    // Too heavy for its clique, node 0 leaves for the pendant
+    session
+        .apply_delta(&DeltaBatch::new().set_node_weight(0, 10.0))
+        .expect("delta");
+    assert_eq!(session.version(), 1);
+    assert_ne!(session.partition()[0], session.partition()[1]);
+    assert_eq!(session.partition()[0], session.partition()[16]);
+
+    let mut weights = vec![1.0; graph.node_count];
+    weights[0] = 10.0;
+    let expected = cpm_from_scratch(&graph, &weights, session.partition());
+    assert!((session.partition_result().quality_score - expected).abs() < 1e-9);
+}
+
+#[test]
+fn lightening_a_community_attracts_its_neighbours() {
+    let graph = cliques_with_pendant();
+    let mut session = IncrementalSession::new(&graph, cpm()).expect("session");
+
+    let mut batch = DeltaBatch::new();
+    for node in 0..4 {
+        batch = batch.set_node_weight(node, 0.5);
+    }
+    session.apply_delta(&batch).expect("delta");
+    assert_eq!(session.partition()[16], session.partition()[0]);
+    assert_weights_aggregate(session.state());
+}
@@ -0,0 +1,109 @@
+use hit_leiden::core::algorithm::quality::{CommunityStats, ConstantPotts, QualityKind};
+use hit_leiden::core::graph::in_memory::InMemoryGraph;
+use hit_leiden::core::session::IncrementalSession;
+use hit_leiden::{run, DeltaBatch, GraphInput, RunConfig, RunMode};
+
+/// Cliques joined by heavy internal edges (weight 3) and a light ring (weight 1),
+/// so CPM at resolution 1 prefers exactly the cliques.
+fn weighted_ring_of_cliques(cliques: usize, size: usize) -> GraphInput {
+    let mut edges = Vec::new();
+    for c in 0..cliques {
+        let base = c * size;
+        for i in 0..size {
+            for j in (i + 1)..size {
+                edges.push((base + i, base + j, Some(3.0)));
+            }
+        }
+        edges.push((base, ((c + 1) % cliques) * size + 1, Some(1.0)));
+    }
+    GraphInput {
+        dataset_id: "quality".to_string(),
+        node_count: cliques * size,
+        edges,
+    }
+}
+
+fn cpm_from_scratch(graph: &GraphInput, node_to_comm: &[usize]) -> f64 {
+    let n = graph.node_count;
+    let graph = InMemoryGraph::from(graph);
+    CommunityStats::from_partition(&graph, node_to_comm, &vec![1.0; n], &bitvec::bitvec![0; n])
+        .quality(&ConstantPotts, graph.total_weight(), 1.0)
+}
+
+#[test]
+fn cpm_recovers_cliques_in_both_modes() {
+    let graph = weighted_ring_of_cliques(8, 4);
+    for mode in [RunMode::Deterministic, RunMode::Throughput] {
+        let partition = run(
+            &graph,
+            &RunConfig {
+                mode,
+                quality: QualityKind::Cpm,
+                ..RunConfig::default()
+            },
+        )
+        .expect("run")
+        .partition
+        .expect("partition");
+
+        assert_eq!(partition.community_count, 8, "{mode:?}");
+        for node in 0..graph.node_count {
+            assert_eq!(
+                partition.node_to_community[node],
+                partition.node_to_community[node - node % 4],
+                "{mode:?}"
+            );
+        }
+        // Each clique: 6 edges of weight 3 minus 4·3/2
+        assert!((partition.quality_score - 8.0 * 12.0).abs() < 1e-9);
+        let expected = cpm_from_scratch(&graph, &partition.node_to_community);
+        assert!((partition.quality_score - expected).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn modularity_and_cpm_score_differently() {
+    let graph = weighted_ring_of_cliques(8, 4);
+    let score = |quality| {
+        run(
+            &graph,
+            &RunConfig {
+                quality,
+                ..RunConfig::default()
+            },
+        )
+        .expect("run")
+        .partition
+        .expect("partition")
+        .quality_score
+    };
+    let modularity = score(QualityKind::Modularity);
+    assert!(modularity > 0.0 && modularity < 1.0);
+    assert!(score(QualityKind::Cpm) > 1.0);
+}
+
+#[test]
+fn session_tracks_cpm_across_updates() {
+    let graph = weighted_ring_of_cliques(6, 4);
+    let mut session = IncrementalSession::new(
+        &graph,
+        RunConfig {
+            quality: QualityKind::Cpm,
+            ..RunConfig::default()
+        },
+    )
+    .expect("session");
+
+    let batch = DeltaBatch::new().set_weight(0, 1, 1.0).insert(2, 9, 3.0);
+    session.apply_delta(&batch).expect("delta");
+
+    let mut updated = graph.clone();
+    for edge in updated.edges.iter_mut() {
+        if (edge.0, edge.1) == (0, 1) {
+            edge.2 = Some(1.0);
+        }
+    }
+    updated.edges.push((2, 9, Some(3.0)));
+    let expected = cpm_from_scratch(&updated, session.partition());
+    assert!((session.partition_result().quality_score - expected).abs() < 1e-9);
+}
@@ -0,0 +1,126 @@
+use hit_leiden::core::algorithm::randomized::RandomMerge;
+use hit_leiden::{
+    run, DeltaBatch, GraphInput, HitLeidenError, IncrementalSession, RunConfig, RunMode,
+};
+
+/// Sparse graph with many equally good merges: a long cycle with chords.
+fn sparse_graph() -> GraphInput {
+    let n = 200;
+    let mut edges: Vec<(usize, usize, Option<f64>)> =
+        (0..n).map(|i| (i, (i + 1) % n, None)).collect();
+    for i in (0..n).step_by(7) {
+        edges.push((i, (i * 13 + 5) % n, None));
+    }
+    GraphInput {
+        dataset_id: "sparse".to_string(),
+        node_count: n,
+        edges,
+    }
+}
+
+fn labels(config: &RunConfig) -> Vec<usize> {
+    run(&sparse_graph(), config)
+        .expect("run")
+        .partition
+        .expect("partition")
+        .node_to_community
+}
+
+#[test]
+fn fixed_seed_is_reproducible_in_both_modes() {
+    for mode in [RunMode::Deterministic, RunMode::Throughput] {
+        let config = RunConfig {
+            mode,
+            seed: Some(42),
+            randomness: 0.5,
+            ..RunConfig::default()
+        };
+        assert_eq!(labels(&config), labels(&config), "{mode:?}");
+
+        let batch = DeltaBatch::new()
+            .add_nodes(1)
+            .insert(200, 3, 1.0)
+            .delete(10, 11);
+        let updated = |config: &RunConfig| {
+            let mut session = IncrementalSession::new(&sparse_graph(), config.clone()).unwrap();
+            session.apply_delta(&batch).unwrap().to_vec()
+        };
+        assert_eq!(updated(&config), updated(&config), "{mode:?}");
+    }
+}
+
+#[test]
+fn seeds_explore_different_partitions() {
+    let partitions: std::collections::HashSet<Vec<usize>> = (0..8)
+        .map(|seed| {
+            labels(&RunConfig {
+                seed: Some(seed),
+                randomness: 1.0,
+                ..RunConfig::default()
+            })
+        })
+        .collect();
+    assert!(partitions.len() > 1);
+}
+
+#[test]
+fn randomised_refinement_keeps_quality() {
+    let greedy = run(&sparse_graph(), &RunConfig::default())
+        .unwrap()
+        .partition
+        .unwrap();
+    let random = run(
+        &sparse_graph(),
+        &RunConfig {
+            seed: Some(7),
+            ..RunConfig::default()
+        },
+    )
+    .unwrap()
+    .partition
+    .unwrap();
+    assert!(random.quality_score > 0.8 * greedy.quality_score);
+    assert!(random.community_count < sparse_graph().node_count);
+}
+
+#[test]
+fn invalid_randomness_is_rejected() {
+    for randomness in [0.0, -0.5, f64::NAN] {
+        let config = RunConfig {
+            seed: Some(1),
+            randomness,
+            ..RunConfig::default()
+        };
+        assert!(matches!(
+            run(&sparse_graph(), &config),
+            Err(HitLeidenError::InvalidInput(_))
+        ));
+    }
+}
+
+#[test]
+fn choice_follows_gain_weights() {
+    let config = |randomness| RunConfig {
+        seed: Some(3),
+        randomness,
+        ..RunConfig::default()
+    };
+    assert_eq!(RandomMerge::from_config(&RunConfig::default()), None);
+
+    // A cold merge behaves greedily whatever the draw
+    let cold = RandomMerge::from_config(&config(1e-6)).unwrap();
+    for draw in [0.0, 0.5, 0.999] {
+        assert_eq!(cold.choose(&mut [(5, 1.0), (2, 0.0), (9, 0.5)], draw), 5);
+    }
+
+    // A hot merge is close to uniform over the id-sorted candidates
+    let hot = RandomMerge::from_config(&config(1e6)).unwrap();
+    assert_eq!(hot.choose(&mut [(5, 1.0), (2, 0.0), (9, 0.5)], 0.1), 2);
+    assert_eq!(hot.choose(&mut [(9, 0.5), (5, 1.0), (2, 0.0)], 0.5), 5);
+    assert_eq!(hot.choose(&mut [(2, 0.0), (9, 0.5), (5, 1.0)], 0.9), 9);
+
+    let draws = cold.for_stream(1).draws(4);
+    assert_eq!(draws, cold.for_stream(1).draws(4));
+    assert_ne!(draws, cold.for_stream(2).draws(4));
+    assert!(draws.iter().all(|d| (0.0..1.0).contains(d)));
+}
@@ -0,0 +1,91 @@
+use crate::integration::ring_of_cliques;
+use hit_leiden::core::algorithm::quality::modularity;
+use hit_leiden::core::graph::in_memory::InMemoryGraph;
+use hit_leiden::{run, DeltaBatch, GraphInput, HitLeidenError, IncrementalSession, RunConfig};
+
+fn community_count(graph: &GraphInput, resolution: f64) -> usize {
+    run(
+        graph,
+        &RunConfig {
+            resolution,
+            ..RunConfig::default()
+        },
+    )
+    .expect("run")
+    .partition
+    .expect("partition")
+    .community_count
+}
+
+#[test]
+fn invalid_resolution_is_rejected() {
+    let graph = ring_of_cliques(4, 4);
+    for resolution in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let config = RunConfig {
+            resolution,
+            ..RunConfig::default()
+        };
+        assert!(matches!(
+            run(&graph, &config),
+            Err(HitLeidenError::InvalidInput(_))
+        ));
+        assert!(IncrementalSession::new(&graph, config).is_err());
+    }
+}
+
+#[test]
+fn higher_resolution_gives_smaller_communities() {
+    let graph = ring_of_cliques(16, 4);
+    let coarse = community_count(&graph, 0.05);
+    let standard = community_count(&graph, 1.0);
+    let fine = community_count(&graph, 20.0);
+    assert!(coarse < standard, "{coarse} vs {standard}");
+    assert!(standard <= fine, "{standard} vs {fine}");
+}
+
+#[test]
+fn session_reoptimises_when_resolution_changes() {
+    let graph = ring_of_cliques(16, 4);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let before = session.partition_result().community_count;
+
+    session.set_resolution(1.0).expect("same resolution");
+    assert_eq!(session.version(), 0);
+
+    assert!(session.set_resolution(-2.0).is_err());
+    assert_eq!(session.config().resolution, 1.0);
+    assert_eq!(session.version(), 0);
+
+    session.set_resolution(0.05).expect("coarser");
+    assert_eq!(session.version(), 1);
+    assert_eq!(session.config().resolution, 0.05);
+    let result = session.partition_result();
+    assert!(result.community_count < before);
+    let scratch = modularity(&InMemoryGraph::from(&graph), session.partition(), 0.05);
+    assert!((result.quality_score - scratch).abs() < 1e-9);
+
+    // Later deltas are optimised and scored at the new resolution
+    session
+        .apply_delta(&DeltaBatch::new().insert(0, 33, 1.0))
+        .expect("delta");
+    let mut updated = graph.clone();
+    updated.edges.push((0, 33, None));
+    let scratch = modularity(&InMemoryGraph::from(&updated), session.partition(), 0.05);
+    assert!((session.partition_result().quality_score - scratch).abs() < 1e-9);
+}
+
+#[test]
+fn graph_export_round_trips_self_loops() {
+    let input = GraphInput {
+        dataset_id: "export".to_string(),
+        node_count: 3,
+        edges: vec![(0, 1, Some(2.0)), (1, 1, Some(3.0)), (1, 2, Some(1.5))],
+    };
+    let graph = InMemoryGraph::from(&input);
+    let exported = graph.to_graph_input("export");
+    assert_eq!(exported.edges, input.edges);
+    assert_eq!(
+        InMemoryGraph::from(&exported).total_weight(),
+        graph.total_weight()
+    );
+}
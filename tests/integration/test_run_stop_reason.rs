@@ -0,0 +1,46 @@
+use crate::integration::ring_of_cliques;
+use hit_leiden::{run, RunConfig, StopReason};
+
+fn partition(config: RunConfig) -> hit_leiden::core::types::PartitionResult {
+    run(&ring_of_cliques(16, 4), &config)
+        .expect("run")
+        .partition
+        .expect("partition")
+}
+
+#[test]
+fn single_pass_when_tolerance_is_large() {
+    let result = partition(RunConfig {
+        quality_tolerance: 1.0,
+        ..RunConfig::default()
+    });
+    assert_eq!(result.pass_count, 1);
+    assert_eq!(result.stop_reason, StopReason::Converged);
+}
+
+#[test]
+fn max_iterations_caps_passes() {
+    let result = partition(RunConfig {
+        quality_tolerance: 0.0,
+        max_iterations: 1,
+        ..RunConfig::default()
+    });
+    assert_eq!(result.pass_count, 1);
+    assert_eq!(result.stop_reason, StopReason::MaxIterations);
+}
+
+#[test]
+fn further_passes_never_lower_quality() {
+    let single = partition(RunConfig {
+        max_iterations: 1,
+        ..RunConfig::default()
+    });
+    let repeated = partition(RunConfig {
+        quality_tolerance: 0.0,
+        max_iterations: 5,
+        ..RunConfig::default()
+    });
+    assert!(repeated.pass_count >= 1 && repeated.pass_count <= 5);
+    assert!(repeated.quality_score >= single.quality_score);
+    assert!(repeated.iteration_count >= single.iteration_count);
+}
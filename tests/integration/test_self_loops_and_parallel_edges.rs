@@ -0,0 +1,286 @@
+use hit_leiden::core::algorithm::quality::modularity;
+use hit_leiden::core::graph::in_memory::InMemoryGraph;
+use hit_leiden::{run, DeltaBatch, GraphInput, IncrementalSession, ParallelEdges, RunConfig};
+
+fn graph(node_count: usize, edges: &[(usize, usize, f64)]) -> GraphInput {
+    GraphInput {
+        dataset_id: "loops".to_string(),
+        node_count,
+        edges: edges.iter().map(|&(u, v, w)| (u, v, Some(w))).collect(),
+    }
+}
+
+/// Two triangles joined by an edge, with self-loops on some of their nodes.
+fn triangles_with_loops() -> GraphInput {
+    graph(
+        6,
+        &[
+            (0, 1, 1.0),
+            (1, 2, 1.0),
+            (2, 0, 1.0),
+            (3, 4, 1.0),
+            (4, 5, 1.0),
+            (5, 3, 1.0),
+            (2, 3, 1.0),
+            (0, 0, 2.0),
+            (4, 4, 0.5),
+            (5, 5, 3.0),
+        ],
+    )
+}
+
+/// Modularity straight from its definition,
+/// `Σ_ij [A_ij − γ·k_i·k_j / 2m]·δ(c_i, c_j) / 2m`, with `A_ii = 2w` for a
+/// self-loop of weight `w` and `k_i = Σ_j A_ij`.
+fn reference_modularity(input: &GraphInput, labels: &[usize], gamma: f64) -> f64 {
+    let n = input.node_count;
+    let mut adjacency = vec![vec![0.0; n]; n];
+    for &(u, v, w) in &input.edges {
+        let w = w.unwrap_or(1.0);
+        adjacency[u][v] += w;
+        adjacency[v][u] += w;
+    }
+    let degree: Vec<f64> = adjacency.iter().map(|row| row.iter().sum()).collect();
+    let two_m: f64 = degree.iter().sum();
+    let mut q = 0.0;
+    for i in 0..n {
+        for j in 0..n {
+            if labels[i] == labels[j] {
+                q += adjacency[i][j] - gamma * degree[i] * degree[j] / two_m;
+            }
+        }
+    }
+    q / two_m
+}
+
+fn strength(graph: &InMemoryGraph, node: usize) -> f64 {
+    graph.neighbors(node).map(|(_, w)| w).sum()
+}
+
+#[test]
+fn self_loop_counts_twice_in_strength_and_once_in_total_weight() {
+    let graph = InMemoryGraph::from(&graph(2, &[(0, 1, 1.0), (0, 0, 3.0)]));
+    assert_eq!(graph.total_weight(), 4.0);
+    assert_eq!(graph.edge_weight(0, 0), Some(3.0));
+    assert_eq!(strength(&graph, 0), 7.0);
+    assert_eq!(strength(&graph, 1), 1.0);
+}
+
+#[test]
+fn modularity_matches_the_reference_on_graphs_with_loops() {
+    let input = triangles_with_loops();
+    let graph = InMemoryGraph::from(&input);
+    for gamma in [0.5, 1.0, 2.0] {
+        for labels in [
+            vec![0, 0, 0, 3, 3, 3],
+            vec![0, 1, 2, 3, 4, 5],
+            vec![0, 0, 0, 0, 0, 0],
+            vec![0, 0, 2, 2, 4, 4],
+        ] {
+            let expected = reference_modularity(&input, &labels, gamma);
+            assert!((modularity(&graph, &labels, gamma) - expected).abs() < 1e-12);
+        }
+    }
+
+    let result = run(&input, &RunConfig::default())
+        .unwrap()
+        .partition
+        .unwrap();
+    assert_eq!(result.community_count, 2);
+    let expected = reference_modularity(&input, &result.node_to_community, 1.0);
+    assert!((result.quality_score - expected).abs() < 1e-12);
+}
+
+#[test]
+fn parallel_edges_merge_by_the_configured_rule() {
+    let input = GraphInput {
+        dataset_id: "parallel".to_string(),
+        node_count: 3,
+        edges: vec![
+            (0, 1, Some(2.0)),
+            (1, 2, None),
+            (1, 0, Some(5.0)),
+            (2, 2, Some(1.5)),
+            (0, 1, Some(1.0)),
+            (2, 2, Some(0.5)),
+        ],
+    };
+    let merged = |merge, directed| input.merge_parallel_edges(merge, directed).into_owned();
+
+    assert_eq!(
+        merged(ParallelEdges::Sum, false).edges,
+        vec![(0, 1, Some(8.0)), (1, 2, Some(1.0)), (2, 2, Some(2.0))]
+    );
+    assert_eq!(
+        merged(ParallelEdges::Max, false).edges,
+        vec![(0, 1, Some(5.0)), (1, 2, Some(1.0)), (2, 2, Some(1.5))]
+    );
+    assert_eq!(
+        merged(ParallelEdges::Count, false).edges,
+        vec![(0, 1, Some(3.0)), (1, 2, Some(1.0)), (2, 2, Some(2.0))]
+    );
+    // Opposite arcs are distinct edges of a directed graph
+    assert_eq!(
+        merged(ParallelEdges::Sum, true).edges,
+        vec![
+            (0, 1, Some(3.0)),
+            (1, 2, Some(1.0)),
+            (1, 0, Some(5.0)),
+            (2, 2, Some(2.0)),
+        ]
+    );
+
+    // Graphs without parallel edges are left alone
+    let simple = triangles_with_loops();
+    for merge in [ParallelEdges::Sum, ParallelEdges::Max] {
+        assert!(matches!(
+            simple.merge_parallel_edges(merge, false),
+            std::borrow::Cow::Borrowed(_)
+        ));
+    }
+    let counted = simple.merge_parallel_edges(ParallelEdges::Count, false);
+    assert!(counted.edges.iter().all(|&(_, _, w)| w == Some(1.0)));
+}
+
+#[test]
+fn parallel_edges_become_one_edge_of_the_graph() {
+    let input = graph(
+        3,
+        &[
+            (0, 1, 1.0),
+            (1, 2, 1.0),
+            (1, 0, 2.0),
+            (2, 2, 1.0),
+            (2, 2, 1.0),
+        ],
+    );
+    // Building a graph keeps the input's edges apart; merging sums them first
+    assert_eq!(InMemoryGraph::from(&input).neighbors(0).count(), 2);
+    let mut graph = InMemoryGraph::from(&*input.merge_parallel_edges(ParallelEdges::Sum, false));
+    assert_eq!(graph.edge_weight(0, 1), Some(3.0));
+    assert_eq!(graph.edge_weight(2, 2), Some(2.0));
+    assert_eq!(graph.total_weight(), 6.0);
+    assert_eq!(graph.neighbors(0).count(), 1);
+
+    assert_eq!(graph.remove_edge(1, 0), Some(3.0));
+    assert_eq!(graph.edge_weight(0, 1), None);
+    assert_eq!(graph.remove_edge(2, 2), Some(2.0));
+    assert_eq!(graph.edge_weight(2, 2), None);
+    assert_eq!(graph.total_weight(), 1.0);
+    assert_eq!(strength(&graph, 2), 1.0);
+    assert_eq!(graph.neighbors(0).count(), 0);
+}
+
+#[test]
+fn runs_merge_parallel_edges_before_clustering() {
+    let mut edges = Vec::new();
+    for c in 0..4 {
+        let base = c * 4;
+        for i in 0..4 {
+            for j in (i + 1)..4 {
+                edges.push((base + i, base + j, 1.0));
+            }
+        }
+        edges.push((base, ((c + 1) % 4) * 4 + 1, 1.0));
+    }
+    // Repeat every bridge backwards and heavier, so each rule merges differently
+    for c in 0..4 {
+        edges.push((((c + 1) % 4) * 4 + 1, c * 4, 4.0));
+    }
+    edges.push((5, 5, 2.0));
+    edges.push((5, 5, 1.0));
+    let input = graph(16, &edges);
+
+    for merge in [ParallelEdges::Sum, ParallelEdges::Max, ParallelEdges::Count] {
+        let config = RunConfig {
+            parallel_edges: merge,
+            ..RunConfig::default()
+        };
+        let merged = input.merge_parallel_edges(merge, false).into_owned();
+        assert!(merged.edges.len() < input.edges.len());
+        let with_duplicates = run(&input, &config).unwrap().partition.unwrap();
+        // Merging is not idempotent under `count`, so the merged graph runs as is
+        let premerged = run(&merged, &RunConfig::default())
+            .unwrap()
+            .partition
+            .unwrap();
+        assert_eq!(with_duplicates, premerged, "{merge:?}");
+        let expected = reference_modularity(&merged, &premerged.node_to_community, 1.0);
+        assert!((premerged.quality_score - expected).abs() < 1e-12);
+    }
+}
+
+#[test]
+fn counted_weights_survive_a_resolution_change() {
+    let input = graph(
+        6,
+        &[
+            (0, 1, 1.0),
+            (1, 2, 1.0),
+            (2, 0, 1.0),
+            (3, 4, 1.0),
+            (4, 5, 1.0),
+            (5, 3, 1.0),
+            (2, 3, 1.0),
+            (3, 2, 5.0),
+            (0, 1, 0.5),
+            (1, 0, 2.0),
+        ],
+    );
+    let config = RunConfig {
+        parallel_edges: ParallelEdges::Count,
+        ..RunConfig::default()
+    };
+    let mut session = IncrementalSession::new(&input, config).unwrap();
+    let counted = session.state().supergraphs[0].clone();
+    assert_eq!(counted.edge_weight(0, 1), Some(3.0));
+    assert_eq!(counted.edge_weight(2, 3), Some(2.0));
+
+    session.set_resolution(0.5).unwrap();
+    let base = &session.state().supergraphs[0];
+    assert_eq!(
+        base.to_graph_input("count"),
+        counted.to_graph_input("count")
+    );
+    assert_eq!(base.total_weight(), counted.total_weight());
+}
+
+#[test]
+fn incremental_updates_weigh_self_loops_consistently() {
+    let mut session =
+        IncrementalSession::new(&triangles_with_loops(), RunConfig::default()).unwrap();
+    let batches = [
+        DeltaBatch::new().insert(1, 1, 2.0).insert(2, 4, 1.0),
+        DeltaBatch::new().adjust_weight(0, 0, 1.5).delete(5, 5),
+        DeltaBatch::new()
+            .add_nodes(1)
+            .insert(6, 6, 1.0)
+            .insert(6, 3, 1.0),
+    ];
+    for batch in &batches {
+        session.apply_delta(batch).unwrap();
+        let state = session.state();
+        let base = &state.supergraphs[0];
+        let input = base.to_graph_input("loops");
+        let expected = reference_modularity(&input, session.partition(), 1.0);
+        assert!((session.partition_result().quality_score - expected).abs() < 1e-9);
+
+        // Aggregation turns internal edges into self-loops without changing
+        // the total weight or any vertex's strength
+        for p in 1..state.levels {
+            let above = &state.supergraphs[p];
+            assert!((above.total_weight() - base.total_weight()).abs() < 1e-9);
+            let mapping = &state.current_subcommunity_mapping_per_level[p - 1];
+            let below = &state.supergraphs[p - 1];
+            let mut expected = vec![0.0; above.node_count];
+            for v in 0..below.node_count {
+                expected[mapping[v]] += strength(below, v);
+            }
+            for (s, &e) in expected.iter().enumerate() {
+                assert!((strength(above, s) - e).abs() < 1e-9, "level {p}");
+            }
+        }
+    }
+    assert_eq!(session.state().supergraphs[0].edge_weight(0, 0), Some(3.5));
+    assert_eq!(session.state().supergraphs[0].edge_weight(5, 5), None);
+}
@@ -0,0 +1,222 @@
+use crate::integration::ring_of_cliques;
+use hit_leiden::core::partition::stable_ids::{LevelChanges, StableCommunityIds};
+use hit_leiden::{DeltaBatch, GraphInput, IncrementalSession, RunConfig, RunMode};
+use std::collections::{BTreeMap, BTreeSet};
+
+fn top_ids(ids: &StableCommunityIds) -> Vec<Option<u64>> {
+    ids.communities()
+}
+
+/// Identifier of each clique's community, keyed by its first node.
+fn clique_ids(ids: &StableCommunityIds, cliques: usize, size: usize) -> BTreeMap<usize, u64> {
+    let top = top_ids(ids);
+    (0..cliques)
+        .map(|c| (c * size, top[c * size].unwrap()))
+        .collect()
+}
+
+#[test]
+fn unrelated_communities_keep_their_ids() {
+    let graph = ring_of_cliques(12, 5);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let before = clique_ids(session.community_ids(), 12, 5);
+    assert_eq!(before.values().collect::<BTreeSet<_>>().len(), 12);
+
+    session
+        .apply_delta(
+            &DeltaBatch::new()
+                .adjust_weight(0, 1, 2.0)
+                .adjust_weight(20, 22, 1.0),
+        )
+        .expect("delta");
+    session
+        .apply_delta(&DeltaBatch::new().delete(0, 1))
+        .expect("delta");
+    assert_eq!(clique_ids(session.community_ids(), 12, 5), before);
+}
+
+#[test]
+fn new_communities_get_fresh_ids() {
+    let graph = ring_of_cliques(6, 4);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let before: BTreeSet<u64> = top_ids(session.community_ids())
+        .into_iter()
+        .flatten()
+        .collect();
+
+    // A separate clique of four new nodes
+    let mut batch = DeltaBatch::new().add_nodes(4);
+    for i in 24..28 {
+        for j in (i + 1)..28 {
+            batch = batch.insert(i, j, 1.0);
+        }
+    }
+    session.apply_delta(&batch).expect("delta");
+
+    let top = top_ids(session.community_ids());
+    let new_id = top[24].unwrap();
+    assert!(before.iter().all(|&id| id < new_id));
+    assert!((25..28).all(|v| top[v] == Some(new_id)));
+    assert_eq!(clique_ids(session.community_ids(), 6, 4).len(), 6);
+    assert!((0..24).all(|v| before.contains(&top[v].unwrap())));
+}
+
+#[test]
+fn merged_communities_keep_one_id_and_retire_the_other() {
+    let graph = ring_of_cliques(8, 4);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    let before = clique_ids(session.community_ids(), 8, 4);
+
+    // Fuse cliques 0 and 1 with heavy edges
+    let mut batch = DeltaBatch::new();
+    for i in 0..4 {
+        for j in 4..8 {
+            if (i, j) != (0, 5) {
+                batch = batch.insert(i, j, 1.0);
+            } else {
+                batch = batch.adjust_weight(i, j, 1.0);
+            }
+        }
+    }
+    session.apply_delta(&batch).expect("delta");
+    let top = top_ids(session.community_ids());
+    assert_eq!(top[0], top[4]);
+    let merged = top[0].unwrap();
+    assert!(merged == before[&0] || merged == before[&4]);
+    let retired = if merged == before[&0] {
+        before[&4]
+    } else {
+        before[&0]
+    };
+    assert!(!top.contains(&Some(retired)));
+
+    // Retired identifiers are never handed out again
+    let mut batch = DeltaBatch::new().add_nodes(3);
+    batch = batch
+        .insert(32, 33, 1.0)
+        .insert(33, 34, 1.0)
+        .insert(32, 34, 1.0);
+    session.apply_delta(&batch).expect("delta");
+    let top = top_ids(session.community_ids());
+    let fresh = top[32].unwrap();
+    assert!(before.values().all(|&id| id < fresh));
+    assert!(!top.contains(&Some(retired)));
+}
+
+#[test]
+fn ids_are_exposed_per_level() {
+    let graph = ring_of_cliques(16, 4);
+    let mut session = IncrementalSession::new(&graph, RunConfig::default()).expect("session");
+    session
+        .apply_delta(&DeltaBatch::new().remove_node(5).insert(0, 9, 1.0))
+        .expect("delta");
+
+    let ids = session.community_ids();
+    let labels = session.state().hierarchy_labels();
+    assert_eq!(ids.level_count(), session.state().levels);
+    for (level, labels) in labels.iter().enumerate() {
+        let node_ids = ids.node_ids(level);
+        assert_eq!(node_ids[5], None);
+        assert_eq!(ids.level(level).len(), {
+            let live: BTreeSet<usize> = labels
+                .iter()
+                .copied()
+                .filter(|&l| l != usize::MAX)
+                .collect();
+            live.len()
+        });
+        for u in (0..graph.node_count).filter(|&u| u != 5) {
+            for v in (0..graph.node_count).filter(|&v| v != 5) {
+                assert_eq!(labels[u] == labels[v], node_ids[u] == node_ids[v]);
+            }
+        }
+    }
+}
+
+/// Check after every batch that the ids the session maintained from the
+/// touched nodes equal those of relabelling every node.
+fn assert_ids_follow_every_update(graph: &GraphInput, batches: &[DeltaBatch]) {
+    let modes = [
+        RunMode::Deterministic,
+        RunMode::Throughput,
+        RunMode::DeterministicParallel,
+    ];
+    for (mode, seed) in modes.into_iter().flat_map(|m| [(m, None), (m, Some(7))]) {
+        let config = RunConfig {
+            mode,
+            seed,
+            randomness: 0.5,
+            ..RunConfig::default()
+        };
+        let mut session = IncrementalSession::new(graph, config).expect("session");
+        for batch in batches {
+            let previous = session.community_ids().clone();
+            session.apply_delta(batch).expect("delta");
+            let mut reference = previous.clone();
+            let changes =
+                reference.update(session.state(), &bitvec::bitvec![1; session.node_count()]);
+            assert_eq!(
+                session.community_ids(),
+                &reference,
+                "{mode:?}, seed {seed:?}"
+            );
+
+            for (level, change) in changes.iter().enumerate() {
+                let expected = LevelChanges::between(
+                    level,
+                    &previous.node_ids(level),
+                    &reference.node_ids(level),
+                );
+                assert_eq!(change, &expected, "{mode:?}, seed {seed:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn incremental_ids_match_relabelling_every_node() {
+    let batches = [
+        DeltaBatch::new()
+            .insert(1, 5, 1.0)
+            .insert(2, 6, 1.0)
+            .insert(3, 7, 1.0),
+        DeltaBatch::new()
+            .add_nodes(3)
+            .insert(40, 41, 1.0)
+            .insert(41, 42, 1.0)
+            .insert(40, 42, 1.0)
+            .insert(42, 12, 1.0),
+        DeltaBatch::new().remove_node(13).delete(1, 5),
+        DeltaBatch::new()
+            .set_node_weight(20, 3.0)
+            .adjust_weight(20, 30, 4.0)
+            .adjust_weight(21, 30, 4.0),
+        DeltaBatch::new()
+            .delete(36, 37)
+            .delete(36, 38)
+            .delete(36, 39),
+    ];
+    assert_ids_follow_every_update(&ring_of_cliques(10, 4), &batches);
+
+    // A long cycle with chords has many equally good merges to reshuffle
+    let n = 150;
+    let mut edges: Vec<(usize, usize, Option<f64>)> =
+        (0..n).map(|i| (i, (i + 1) % n, None)).collect();
+    edges.extend((0..n).step_by(7).map(|i| (i, (i * 13 + 5) % n, None)));
+    let cycle = GraphInput {
+        dataset_id: "cycle".to_string(),
+        node_count: n,
+        edges,
+    };
+    let batches = [
+        DeltaBatch::new()
+            .add_nodes(1)
+            .insert(150, 3, 1.0)
+            .delete(10, 11),
+        DeltaBatch::new().insert(40, 90, 2.0).delete(60, 61),
+        DeltaBatch::new()
+            .remove_node(100)
+            .adjust_weight(20, 21, 3.0),
+    ];
+    assert_ids_follow_every_update(&cycle, &batches);
+}
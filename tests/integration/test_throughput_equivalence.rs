@@ -1,3 +1,7 @@
+use hit_leiden::core::algorithm::quality::{Mass, MoveCost};
+use hit_leiden::core::algorithm::subcommunities::Subcommunities;
+use hit_leiden::core::algorithm::throughput::inc_refinement_parallel;
+use hit_leiden::core::graph::in_memory::InMemoryGraph;
 use hit_leiden::{core::config::RunMode, run, validate, GraphInput, RunConfig};
 
 #[test]
@@ -7,11 +11,48 @@ fn throughput_equivalence_bounds() {
         node_count: 2,
         edges: vec![(0, 1, Some(1.0))],
     };
-    let mut config = RunConfig::default();
-    config.mode = RunMode::Throughput;
+    let config = RunConfig {
+        mode: RunMode::Throughput,
+        ..RunConfig::default()
+    };
     let a = run(&graph, &config).expect("a");
     let b = run(&graph, &config).expect("b");
     let v = validate(&a, &b, RunMode::Throughput);
     assert!(v.hard_invariants_passed);
     assert!(v.equivalence_passed);
 }
+
+#[test]
+fn merges_proposed_together_are_rechecked_before_commit() {
+    // Both singletons propose to join the other against the same snapshot
+    let graph = InMemoryGraph::from(&GraphInput {
+        dataset_id: "pair".to_string(),
+        node_count: 2,
+        edges: vec![(0, 1, Some(1.0))],
+    });
+    let node_masses = [Mass::symmetric(1.0); 2];
+    let cost = MoveCost {
+        node_masses: &node_masses,
+        penalty: 0.1,
+    };
+    let mut node_to_subcommunity = vec![0, 1];
+    let mut subcommunities = Subcommunities {
+        masses: (0..2).map(|s| (s, Mass::symmetric(1.0))).collect(),
+        sizes: vec![1, 1],
+        budget: None,
+    };
+    inc_refinement_parallel(
+        &graph,
+        &[0, 1],
+        &[0, 0],
+        &mut node_to_subcommunity,
+        &mut subcommunities,
+        cost,
+        None,
+    );
+
+    // The second merge would have swapped the pair instead of joining it
+    assert_eq!(node_to_subcommunity, vec![1, 1]);
+    assert_eq!(subcommunities.sizes, vec![0, 2]);
+    assert_eq!(subcommunities.mass(1), Mass::symmetric(2.0));
+}
@@ -1,16 +1,64 @@
+#[path = "integration/mod.rs"]
+mod integration;
 #[path = "integration/test_benchmark_reproducibility.rs"]
 mod test_benchmark_reproducibility;
+#[path = "integration/test_checkpoint.rs"]
+mod test_checkpoint;
+#[path = "integration/test_cli.rs"]
+mod test_cli;
+#[path = "integration/test_community_events.rs"]
+mod test_community_events;
+#[path = "integration/test_community_lineage.rs"]
+mod test_community_lineage;
+#[path = "integration/test_community_tree.rs"]
+mod test_community_tree;
 #[path = "integration/test_connected_graph_not_all_singletons.rs"]
 mod test_connected_graph_not_all_singletons;
+#[path = "integration/test_cross_process_determinism.rs"]
+mod test_cross_process_determinism;
+#[path = "integration/test_csr_binary.rs"]
+mod test_csr_binary;
 #[path = "integration/test_default_config_minimal_args.rs"]
 mod test_default_config_minimal_args;
+#[path = "integration/test_delta_batch.rs"]
+mod test_delta_batch;
+#[path = "integration/test_delta_log.rs"]
+mod test_delta_log;
 #[path = "integration/test_deterministic_identity.rs"]
 mod test_deterministic_identity;
+#[path = "integration/test_deterministic_parallel.rs"]
+mod test_deterministic_parallel;
+#[path = "integration/test_directed_graphs.rs"]
+mod test_directed_graphs;
+#[path = "integration/test_dynamic_nodes.rs"]
+mod test_dynamic_nodes;
+#[path = "integration/test_edge_list.rs"]
+mod test_edge_list;
+#[path = "integration/test_hierarchy_levels.rs"]
+mod test_hierarchy_levels;
+#[path = "integration/test_incremental_session.rs"]
+mod test_incremental_session;
+#[path = "integration/test_max_community_size.rs"]
+mod test_max_community_size;
 #[path = "integration/test_mmap_parity.rs"]
 mod test_mmap_parity;
 #[path = "integration/test_neo4j_snapshot_parity.rs"]
 mod test_neo4j_snapshot_parity;
+#[path = "integration/test_node_weights.rs"]
+mod test_node_weights;
+#[path = "integration/test_quality_functions.rs"]
+mod test_quality_functions;
+#[path = "integration/test_randomized_refinement.rs"]
+mod test_randomized_refinement;
 #[path = "integration/test_release_gate_live_query_ineligible.rs"]
 mod test_release_gate_live_query_ineligible;
+#[path = "integration/test_resolution.rs"]
+mod test_resolution;
+#[path = "integration/test_run_stop_reason.rs"]
+mod test_run_stop_reason;
+#[path = "integration/test_self_loops_and_parallel_edges.rs"]
+mod test_self_loops_and_parallel_edges;
+#[path = "integration/test_stable_community_ids.rs"]
+mod test_stable_community_ids;
 #[path = "integration/test_throughput_equivalence.rs"]
 mod test_throughput_equivalence;
@@ -0,0 +1,68 @@
+use hit_leiden::core::graph::in_memory::InMemoryGraph;
+use hit_leiden::GraphInput;
+use proptest::prelude::*;
+use std::collections::BTreeMap;
+
+const NODES: usize = 12;
+
+fn edge_list(edges: &BTreeMap<(usize, usize), f64>) -> GraphInput {
+    GraphInput {
+        dataset_id: "csr".to_string(),
+        node_count: NODES,
+        edges: edges.iter().map(|(&(u, v), &w)| (u, v, Some(w))).collect(),
+    }
+}
+
+fn sorted_adjacency(graph: &InMemoryGraph, node: usize) -> Vec<(usize, u64)> {
+    let mut adjacency: Vec<_> = graph
+        .neighbors(node)
+        .map(|(v, w)| (v, (w * 1e6).round() as u64))
+        .collect();
+    adjacency.sort_unstable();
+    adjacency
+}
+
+proptest! {
+    #[test]
+    fn in_place_updates_match_rebuild(
+        batches in prop::collection::vec(
+            prop::collection::vec((0..NODES, 0..NODES, -3i32..=3), 0..20),
+            1..8,
+        )
+    ) {
+        let mut reference: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+        let mut graph = InMemoryGraph::from(&edge_list(&reference));
+
+        for batch in batches {
+            let delta = GraphInput {
+                dataset_id: "csr-delta".to_string(),
+                node_count: NODES,
+                edges: batch
+                    .iter()
+                    .map(|&(u, v, w)| (u, v, Some(f64::from(w))))
+                    .collect(),
+            };
+            graph.apply_delta(&delta);
+            for &(u, v, w) in &batch {
+                let key = (u.min(v), u.max(v));
+                let weight = reference.get(&key).copied().unwrap_or(0.0) + f64::from(w);
+                if weight > 0.0 {
+                    reference.insert(key, weight);
+                } else {
+                    reference.remove(&key);
+                }
+            }
+
+            let rebuilt = InMemoryGraph::from(&edge_list(&reference));
+            prop_assert!((graph.total_weight() - rebuilt.total_weight()).abs() < 1e-9);
+            for node in 0..NODES {
+                prop_assert_eq!(graph.degree(node), rebuilt.degree(node));
+                prop_assert_eq!(sorted_adjacency(&graph, node), sorted_adjacency(&rebuilt, node));
+            }
+        }
+
+        graph.compact();
+        let rebuilt = InMemoryGraph::from(&edge_list(&reference));
+        prop_assert_eq!(graph.neighbors.len(), rebuilt.neighbors.len());
+    }
+}
@@ -0,0 +1,176 @@
+use hit_leiden::core::partition::state::PartitionState;
+use hit_leiden::{DeltaBatch, GraphInput, IncrementalSession, RunConfig, RunMode};
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+use std::collections::HashMap;
+
+const NODES: usize = 12;
+
+/// Every live node carries its final community at every level, and the
+/// communities of each level of the hierarchy nest inside those of the next.
+fn check_hierarchy(state: &PartitionState) -> Result<(), TestCaseError> {
+    let live = || (0..state.node_to_comm.len()).filter(|&v| !state.removed_nodes[v]);
+    for v in live() {
+        let mut vertex = v;
+        for p in 0..state.levels {
+            prop_assert_eq!(
+                state.community_mapping_per_level[p][vertex],
+                state.node_to_comm[v],
+                "f_{} of node {}",
+                p,
+                v
+            );
+            if p + 1 < state.levels {
+                vertex = state.current_subcommunity_mapping_per_level[p][vertex];
+            }
+        }
+    }
+
+    let labels = state.hierarchy_labels();
+    for p in 1..labels.len() {
+        let mut parents = HashMap::new();
+        for v in live() {
+            let parent = *parents.entry(labels[p - 1][v]).or_insert(labels[p][v]);
+            prop_assert_eq!(parent, labels[p][v], "level {} of node {}", p - 1, v);
+        }
+    }
+    Ok(())
+}
+
+/// The weight stored for every supervertex is the total weight of its members.
+fn check_weights(state: &PartitionState) -> Result<(), TestCaseError> {
+    for p in 0..state.levels - 1 {
+        let mut totals = vec![0.0; state.node_weights_per_level[p + 1].len()];
+        for (v, &weight) in state.node_weights_per_level[p].iter().enumerate() {
+            totals[state.current_subcommunity_mapping_per_level[p][v]] += weight;
+        }
+        for (c, (&stored, &total)) in state.node_weights_per_level[p + 1]
+            .iter()
+            .zip(&totals)
+            .enumerate()
+        {
+            prop_assert!(
+                (stored - total).abs() < 1e-9,
+                "level {} vertex {} stores {} but its members sum to {}",
+                p + 1,
+                c,
+                stored,
+                total
+            );
+        }
+    }
+    Ok(())
+}
+
+fn mode() -> impl Strategy<Value = RunMode> {
+    prop::sample::select(vec![
+        RunMode::Deterministic,
+        RunMode::Throughput,
+        RunMode::DeterministicParallel,
+    ])
+}
+
+#[test]
+fn a_member_that_changes_community_relabels_every_level_above() {
+    let graph = GraphInput {
+        dataset_id: "relabel".to_string(),
+        node_count: 5,
+        edges: vec![
+            (0, 2, None),
+            (0, 3, None),
+            (2, 3, None),
+            (2, 4, None),
+            (3, 4, None),
+        ],
+    };
+    for mode in [
+        RunMode::Deterministic,
+        RunMode::Throughput,
+        RunMode::DeterministicParallel,
+    ] {
+        let config = RunConfig {
+            mode,
+            ..RunConfig::default()
+        };
+        let mut session = IncrementalSession::new(&graph, config).expect("session");
+        session
+            .apply_delta(&DeltaBatch::new().insert(1, 4, 1.0))
+            .expect("delta");
+        assert!(session.state().levels > 2, "{mode:?}");
+        check_hierarchy(session.state()).unwrap();
+    }
+}
+
+#[test]
+fn supervertex_weights_follow_their_members_at_every_level() {
+    let graph = GraphInput {
+        dataset_id: "weights".to_string(),
+        node_count: 5,
+        edges: vec![
+            (0, 1, None),
+            (0, 2, None),
+            (0, 3, None),
+            (1, 3, None),
+            (2, 3, None),
+        ],
+    };
+    for mode in [
+        RunMode::Deterministic,
+        RunMode::Throughput,
+        RunMode::DeterministicParallel,
+    ] {
+        let config = RunConfig {
+            mode,
+            ..RunConfig::default()
+        };
+        let mut session = IncrementalSession::new(&graph, config).expect("session");
+        session
+            .apply_delta(&DeltaBatch::new().insert(4, 1, 1.0))
+            .expect("delta");
+        check_weights(session.state()).unwrap();
+    }
+}
+
+proptest! {
+    #[test]
+    fn updated_hierarchies_stay_nested_and_weighted(
+        mode in mode(),
+        initial in prop::collection::vec((0..NODES, 0..NODES), 0..24),
+        batches in prop::collection::vec(
+            (prop::collection::vec((0..NODES, 0..NODES, -1i32..=3), 1..8), 0..2usize),
+            1..8,
+        )
+    ) {
+        let initial = GraphInput {
+            dataset_id: "hierarchy".to_string(),
+            node_count: NODES,
+            edges: initial.into_iter().map(|(u, v)| (u, v, None)).collect(),
+        };
+        let config = RunConfig { mode, ..RunConfig::default() };
+        let mut session = IncrementalSession::new(&initial, config).expect("session");
+        check_hierarchy(session.state())?;
+        check_weights(session.state())?;
+
+        for (changes, added) in batches {
+            let n = session.node_count();
+            let mut batch = DeltaBatch::new().add_nodes(added);
+            let mut graph = session.state().supergraphs[0].clone();
+            for (u, v, change) in changes {
+                let present = graph.edge_weight(u, v).is_some();
+                if change < 0 && present {
+                    batch = batch.delete(u, v);
+                    graph.remove_edge(u, v);
+                } else if change > 0 && !present {
+                    batch = batch.insert(u, v, f64::from(change));
+                    graph.set_edge(u, v, f64::from(change));
+                }
+            }
+            for node in n..n + added {
+                batch = batch.insert(node, node % NODES, 1.0);
+            }
+            session.apply_delta(&batch).expect("delta");
+            check_hierarchy(session.state())?;
+            check_weights(session.state())?;
+        }
+    }
+}
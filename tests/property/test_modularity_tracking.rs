@@ -0,0 +1,129 @@
+use hit_leiden::core::algorithm::quality::{modularity, CommunityStats, QualityKind};
+use hit_leiden::{run, DeltaBatch, GraphInput, IncrementalSession, RunConfig};
+use proptest::prelude::*;
+use std::collections::BTreeSet;
+
+const NODES: usize = 16;
+
+#[test]
+fn run_reports_modularity_of_two_triangles() {
+    let graph = GraphInput {
+        dataset_id: "two-triangles".to_string(),
+        node_count: 6,
+        edges: vec![
+            (0, 1, None),
+            (1, 2, None),
+            (2, 0, None),
+            (3, 4, None),
+            (4, 5, None),
+            (5, 3, None),
+        ],
+    };
+    let partition = run(&graph, &RunConfig::default())
+        .expect("run")
+        .partition
+        .expect("partition");
+
+    // Each triangle holds half the edges and half the strength: 2 * (1/2 - 1/4)
+    assert!((partition.quality_score - 0.5).abs() < 1e-12);
+    assert_eq!(partition.community_count, 2);
+    assert!(partition.iteration_count >= 1);
+}
+
+proptest! {
+    #[test]
+    fn session_quality_matches_full_recomputation(
+        batches in prop::collection::vec(
+            prop::collection::vec((0..NODES, 0..NODES, 1i32..=3), 1..12),
+            1..6,
+        )
+    ) {
+        let initial = GraphInput {
+            dataset_id: "tracking".to_string(),
+            node_count: NODES,
+            edges: (0..NODES / 2).map(|i| (2 * i, 2 * i + 1, None)).collect(),
+        };
+        let mut session = IncrementalSession::new(&initial, RunConfig::default()).expect("session");
+
+        for (round, batch) in batches.into_iter().enumerate() {
+            let mut delta = DeltaBatch::new();
+            for (u, v, w) in batch {
+                delta = delta.adjust_weight(u, v, f64::from(w));
+            }
+            // Periodically drop an edge that is known to exist
+            if round % 2 == 1 && session.state().supergraphs[0].edge_weight(0, 1).is_some() {
+                delta = delta.delete(0, 1);
+            }
+            session.apply_delta(&delta).expect("delta");
+
+            let result = session.partition_result();
+            let graph = &session.state().supergraphs[0];
+            let expected = modularity(graph, session.partition(), 1.0);
+            prop_assert!((result.quality_score - expected).abs() < 1e-9);
+            let labels: BTreeSet<_> = session.partition().iter().collect();
+            prop_assert_eq!(result.community_count, labels.len());
+        }
+    }
+
+    #[test]
+    fn session_totals_follow_node_changes(
+        batches in prop::collection::vec(
+            (
+                prop::collection::vec((0..NODES, 0..NODES, 1i32..=3), 0..6),
+                0..3usize,
+                prop::option::of((0..NODES, 1i32..=4)),
+                prop::option::of(0..NODES),
+            ),
+            1..6,
+        )
+    ) {
+        let initial = GraphInput {
+            dataset_id: "tracking".to_string(),
+            node_count: NODES,
+            edges: (0..NODES / 2).map(|i| (2 * i, 2 * i + 1, None)).collect(),
+        };
+        let config = RunConfig {
+            quality: QualityKind::Cpm,
+            resolution: 0.2,
+            ..RunConfig::default()
+        };
+        let mut session = IncrementalSession::new(&initial, config.clone()).expect("session");
+
+        for (edges, added, reweight, removal) in batches {
+            let n = session.node_count();
+            let mut delta = DeltaBatch::new().add_nodes(added);
+            for (u, v, w) in edges {
+                if !session.is_removed(u) && !session.is_removed(v) {
+                    delta = delta.adjust_weight(u, v, f64::from(w));
+                }
+            }
+            if added > 0 && !session.is_removed(0) {
+                delta = delta.adjust_weight(0, n, 1.0);
+            }
+            let reweight = reweight.filter(|&(node, _)| !session.is_removed(node));
+            if let Some((node, weight)) = reweight {
+                delta = delta.set_node_weight(node, f64::from(weight));
+            }
+            let removal = removal.filter(|&node| {
+                !session.is_removed(node) && reweight.map_or(true, |(r, _)| r != node)
+            });
+            if let Some(node) = removal {
+                delta = delta.remove_node(node);
+            }
+            session.apply_delta(&delta).expect("delta");
+
+            let state = session.state();
+            let graph = &state.supergraphs[0];
+            let expected = CommunityStats::from_partition(
+                graph,
+                session.partition(),
+                &state.node_weights_per_level[0],
+                &state.removed_nodes,
+            );
+            let result = session.partition_result();
+            let quality = expected.quality(config.quality.function(), graph.total_weight(), 0.2);
+            prop_assert!((result.quality_score - quality).abs() < 1e-9);
+            prop_assert_eq!(result.community_count, expected.community_count());
+        }
+    }
+}
@@ -1,2 +1,8 @@
+#[path = "property/test_dynamic_csr.rs"]
+mod test_dynamic_csr;
+#[path = "property/test_hierarchy_invariants.rs"]
+mod test_hierarchy_invariants;
+#[path = "property/test_modularity_tracking.rs"]
+mod test_modularity_tracking;
 #[path = "property/test_partition_invariants.rs"]
 mod test_partition_invariants;